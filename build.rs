@@ -1,15 +1,28 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
 pub fn main() {
+    // 链接脚本：memory.x 放到 OUT_DIR 供 link.x 引用（Flash 最后一个扇区留给事件日志镜像）
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("memory.x"), include_bytes!("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
     // defmt 配置
     println!("cargo:rerun-if-env-changed=DEFMT_LOG");
     println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
+    // 示例程序（台架自检）同样是完整固件镜像
+    println!("cargo:rustc-link-arg-examples=-Tdefmt.x");
+    println!("cargo:rustc-link-arg-examples=--nmagic");
+    println!("cargo:rustc-link-arg-examples=-Tlink.x");
 
     // 从 .proto 文件生成 Rust 代码
     let mut config = prost_build::Config::new();
 
     // 为 no_std 环境配置
-    config.btree_map(&["."]);  // 使用 BTreeMap 而不是 HashMap
+    config.btree_map(["."]);  // 使用 BTreeMap 而不是 HashMap
 
     // 生成代码
     config
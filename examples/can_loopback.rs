@@ -0,0 +1,157 @@
+//! CAN 环回自检（台架程序，不进入生产固件）
+//!
+//! 在内存环回总线上同时运行 CanMaster 与 CanNode（节点侧挂 MockActuators），
+//! 通过 RemoteBoard 以本机驱动的方式下发马达 / 灯光命令，校验分段、应答、
+//! 拒绝、超时与事件上报，无需 CAN 收发器即可验证整条链路
+//!
+//! 运行：`cargo run --example can_loopback`
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+use embassy_executor::Spawner;
+use embassy_futures::select::{select3, Either3};
+use embassy_stm32::Config;
+use embassy_time::{with_timeout, Duration, Timer};
+use stm32::drivers::actuator::{ActuatorError, LightCommand, LightDriver, MotorCommand, MotorDriver};
+use stm32::drivers::MockActuators;
+use stm32::event::coinpusher::v1::{MotorCommandType, MotorType};
+use stm32::net::can::{frame_id, CanReceiver, CanSender, MessageClass, Reassembler, Segments, MAX_MESSAGE_LEN};
+use stm32::net::{CanLoopback, CanMaster, CanMasterConfig, CanNode, RemoteBoard};
+use {defmt_rtt as _, panic_probe as _};
+
+/// 被测节点地址
+const NODE: u8 = 0x11;
+
+/// 不存在的节点地址
+const ABSENT_NODE: u8 = 0x12;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) -> ! {
+    stm32::init_heap();
+    let _p = embassy_stm32::init(Config::default());
+
+    static BUS: CanLoopback = CanLoopback::new();
+    static ACTUATORS: MockActuators = MockActuators::new();
+
+    let ((master_tx, master_rx), (node_tx, node_rx)) = BUS.split();
+    let master = CanMaster::new(CanMasterConfig::default(), master_tx, master_rx);
+    let node = CanNode::new(NODE, node_tx, node_rx);
+    let (mut motors, mut lights) = (&ACTUATORS, &ACTUATORS);
+
+    let checks = async {
+        check_segments()?;
+        run_checks(&master, &node, &ACTUATORS).await
+    };
+
+    match select3(master.run(), node.run(&mut motors, &mut lights), checks).await {
+        Either3::Third(Ok(())) => info!("CAN loopback: all checks passed"),
+        Either3::Third(Err(step)) => error!("CAN loopback: check failed at {}", step),
+    }
+
+    loop {
+        Timer::after_secs(60).await;
+    }
+}
+
+/// 分段 / 重组往返（含两条消息交错）
+fn check_segments() -> Result<(), &'static str> {
+    let mut message = [0u8; MAX_MESSAGE_LEN];
+    for (i, b) in message.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+
+    for len in [0, 7, 8, 13, 100, MAX_MESSAGE_LEN] {
+        let mut reassembler = Reassembler::new();
+        let mut result = None;
+        for frame in Segments::new(0x123, &message[..len]).map_err(|_| "segment")? {
+            result = reassembler.push(&frame);
+        }
+        if result.as_deref() != Some(&message[..len]) {
+            return Err("segment round trip");
+        }
+    }
+
+    // 两个来源的分段交错到达
+    let first = &message[..20];
+    let second = &message[100..130];
+    let mut a = Segments::new(frame_id(MessageClass::Event, 1), first).map_err(|_| "segment")?;
+    let mut b = Segments::new(frame_id(MessageClass::Event, 2), second).map_err(|_| "segment")?;
+    let mut reassembler = Reassembler::new();
+    let mut done = 0;
+    loop {
+        let (fa, fb) = (a.next(), b.next());
+        if fa.is_none() && fb.is_none() {
+            break;
+        }
+        for frame in [fa, fb].into_iter().flatten() {
+            if let Some(m) = reassembler.push(&frame) {
+                done += (m.as_slice() == first || m.as_slice() == second) as usize;
+            }
+        }
+    }
+    if done != 2 {
+        return Err("interleaved reassembly");
+    }
+
+    Ok(())
+}
+
+/// 主控侧检查步骤，失败时返回步骤名
+async fn run_checks<T, R, NT, NR>(
+    master: &CanMaster<T, R>,
+    node: &CanNode<NT, NR>,
+    actuators: &MockActuators,
+) -> Result<(), &'static str>
+where
+    T: CanSender,
+    R: CanReceiver,
+    NT: CanSender,
+    NR: CanReceiver,
+{
+    let mut board = RemoteBoard::new(master, NODE);
+
+    // 1. 灯光命令（8 字节，首帧 + 续帧）
+    let light = LightCommand { light_id: 3, on: true, pattern: 2 };
+    board.set_light(&light).await.map_err(|_| "light command")?;
+    if actuators.last_light() != Some(light) {
+        return Err("light applied");
+    }
+
+    // 2. 马达命令（13 字节，可选字段往返）
+    let motor = MotorCommand {
+        motor_type: MotorType::Payout as i32,
+        command: MotorCommandType::MotorCmdRunTime as i32,
+        duration_ms: Some(1500),
+        count: None,
+        speed_level: Some(3),
+    };
+    board.command(&motor).await.map_err(|_| "motor command")?;
+    if actuators.last_motor() != Some(motor) {
+        return Err("motor applied");
+    }
+
+    // 3. 节点拒绝无效命令
+    let invalid = MotorCommand { command: MotorCommandType::MotorCmdUnknown as i32, ..motor };
+    if board.command(&invalid).await != Err(ActuatorError::Rejected) {
+        return Err("motor rejected");
+    }
+
+    // 4. 不存在的节点超时
+    let mut absent = RemoteBoard::new(master, ABSENT_NODE);
+    if absent.set_light(&light).await != Err(ActuatorError::Unavailable) {
+        return Err("absent node");
+    }
+
+    // 5. 节点事件（多帧）上报到主控
+    let body = [0x5A; 20];
+    node.send_event(0x1004, &body).await.map_err(|_| "send event")?;
+    let event = with_timeout(Duration::from_millis(100), master.next_event())
+        .await
+        .map_err(|_| "recv event")?;
+    if event.node != NODE || event.cmd != 0x1004 || event.body.as_slice() != body {
+        return Err("event content");
+    }
+
+    Ok(())
+}
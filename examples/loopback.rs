@@ -0,0 +1,92 @@
+//! 传输层环回自检（台架程序，不进入生产固件）
+//!
+//! 在 LoopbackPair 上运行真实的分发器（connection::serve），由主机端发送
+//! 编码后的 Ping 与命令包，校验 Pong 与应答，无需任何硬件即可验证
+//! 编解码 → 传输 → 路由 → 应答整条链路
+//!
+//! 运行：`cargo run --example loopback`
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_stm32::Config;
+use embassy_time::{with_timeout, Duration, Timer};
+use stm32::net::connection::serve;
+use stm32::net::uplink::Connection;
+use stm32::net::{example_handler, Frame, LoopbackPair, PacketCodec, PacketType, Router, Transport};
+use {defmt_rtt as _, panic_probe as _};
+
+/// 自检使用的命令码（回显）
+const ECHO_CMD: u16 = 0x0001;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) -> ! {
+    stm32::init_heap();
+    let _p = embassy_stm32::init(Config::default());
+
+    static LOOPBACK: LoopbackPair = LoopbackPair::new();
+
+    let mut router = Router::new();
+    router.add_route(ECHO_CMD, example_handler);
+
+    let (mut device, mut host) = LOOPBACK.split();
+    let conn = Connection::open();
+
+    match select(serve(&mut device, &router, conn.id()), run_checks(&mut host)).await {
+        Either::First(result) => error!("Loopback: dispatcher exited early: {:?}", result),
+        Either::Second(Ok(())) => info!("Loopback: all checks passed"),
+        Either::Second(Err(step)) => error!("Loopback: check failed at {}", step),
+    }
+
+    loop {
+        Timer::after_secs(60).await;
+    }
+}
+
+/// 主机端检查步骤，失败时返回步骤名
+async fn run_checks<T: Transport>(host: &mut T) -> Result<(), &'static str> {
+    let mut frame = [0u8; 64];
+
+    // 1. Ping → Pong
+    let len = PacketCodec::encode_simple(PacketType::Ping, 1, &mut frame).map_err(|_| "encode ping")?;
+    host.send_frame(&frame[..len]).await.map_err(|_| "send ping")?;
+    let reply = recv_reply(host).await.map_err(|_| "recv pong")?;
+    if reply.packet_type != PacketType::Pong {
+        return Err("pong type");
+    }
+
+    // 2. 命令 → 应答：[error_code=0][cmd][回显数据]
+    let payload = [(ECHO_CMD >> 8) as u8, ECHO_CMD as u8, 0xDE, 0xAD];
+    let len = PacketCodec::encode(PacketType::Command, 2, &payload, &mut frame).map_err(|_| "encode cmd")?;
+
+    // 拆成两次写入，验证跨读取的重组
+    host.send_frame(&frame[..3]).await.map_err(|_| "send cmd")?;
+    host.send_frame(&frame[3..len]).await.map_err(|_| "send cmd")?;
+
+    let reply = recv_reply(host).await.map_err(|_| "recv response")?;
+    if reply.packet_type != PacketType::Response {
+        return Err("response type");
+    }
+    if reply.payload.as_slice() != [0x00, 0x00, 0x00, 0x01, 0xDE, 0xAD] {
+        return Err("response payload");
+    }
+
+    Ok(())
+}
+
+/// 接收应答，跳过期间到达的上行推送（Command 包）
+async fn recv_reply<T: Transport>(host: &mut T) -> Result<Frame, ()> {
+    with_timeout(Duration::from_secs(1), async {
+        loop {
+            match host.recv_frame().await {
+                Ok(frame) if frame.packet_type == PacketType::Command => continue,
+                Ok(frame) => return Ok(frame),
+                Err(_) => return Err(()),
+            }
+        }
+    })
+    .await
+    .map_err(|_| ())?
+}
@@ -0,0 +1,9 @@
+/* STM32F407ZG：1 MB Flash / 128 KB SRAM（SRAM1 + SRAM2）
+ *
+ * Flash 最后一个扇区（扇区 11，0x080E0000，128 KB）留给事件日志镜像
+ * （drivers/journal_flash.rs），程序只能使用前 896 KB */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 896K
+  RAM   : ORIGIN = 0x20000000, LENGTH = 128K
+}
@@ -0,0 +1,81 @@
+// 执行器命令队列
+//
+// 2002 / 2003 处理器（及安全策略停机）在分发上下文中同步校验命令后放入队列，
+// actuator_task 依次交给 MotorDriver / LightDriver 执行：
+// - 本机执行器：LocalActuators（写入 app::state）
+// - 执行器在 CAN 驱动板上时：Mirrored<RemoteBoard>，远端接受后同步写入本机状态
+//
+// 执行前再检查一次安全策略：命令排队期间可能已进入停机 / 禁用
+
+use crate::app::safety;
+use crate::drivers::actuator::{LightCommand, LightDriver, MotorCommand, MotorDriver, MotorSpeed};
+use crate::error::{Error, Result};
+use defmt::{debug, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+
+/// 队列深度
+pub const ACTUATOR_QUEUE_DEPTH: usize = 16;
+
+/// 执行器命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+enum Request {
+    Motor(MotorCommand),
+    Speed(MotorSpeed),
+    Light(LightCommand),
+}
+
+static QUEUE: Channel<CriticalSectionRawMutex, Request, ACTUATOR_QUEUE_DEPTH> = Channel::new();
+
+fn submit(request: Request) -> Result<()> {
+    QUEUE.try_send(request).map_err(|_| {
+        crate::log_warn!("Actuators: queue full, {:?} dropped", request);
+        Error::BufferFull
+    })
+}
+
+/// 提交马达命令（调用方已校验参数与安全策略）
+pub fn motor(cmd: MotorCommand) -> Result<()> {
+    submit(Request::Motor(cmd))
+}
+
+/// 提交调速命令（调用方已校验马达类型）
+pub fn motor_speed(cmd: MotorSpeed) -> Result<()> {
+    submit(Request::Speed(cmd))
+}
+
+/// 队列剩余空间（批量写入前检查，避免只提交一部分）
+pub fn free_slots() -> usize {
+    QUEUE.free_capacity()
+}
+
+/// 提交灯光命令（调用方已校验灯光 ID）
+pub fn light(cmd: LightCommand) -> Result<()> {
+    submit(Request::Light(cmd))
+}
+
+/// 执行循环：依次把队列中的命令交给驱动
+pub async fn run<M: MotorDriver, L: LightDriver>(motors: &mut M, lights: &mut L) -> ! {
+    loop {
+        match QUEUE.receive().await {
+            Request::Motor(cmd) => {
+                if !safety::motor_allowed(cmd.motor_type, cmd.command) {
+                    crate::log_warn!("Actuators: motor {} command refused by safety policy", cmd.motor_type);
+                    continue;
+                }
+                match motors.command(&cmd).await {
+                    Ok(()) => debug!("Actuators: motor {} cmd={} done", cmd.motor_type, cmd.command),
+                    Err(e) => crate::log_warn!("Actuators: motor {} command failed: {:?}", cmd.motor_type, e),
+                }
+            }
+            Request::Speed(cmd) => match motors.set_speed(&cmd).await {
+                Ok(()) => debug!("Actuators: motor {} speed {}", cmd.motor_type, cmd.speed_level),
+                Err(e) => crate::log_warn!("Actuators: motor {} speed failed: {:?}", cmd.motor_type, e),
+            },
+            Request::Light(cmd) => match lights.set_light(&cmd).await {
+                Ok(()) => debug!("Actuators: light {} done", cmd.light_id),
+                Err(e) => crate::log_warn!("Actuators: light {} command failed: {:?}", cmd.light_id, e),
+            },
+        }
+    }
+}
@@ -0,0 +1,33 @@
+// 设备身份信息（机器 ID、固件版本）与复位
+#[cfg(target_os = "none")]
+use embassy_stm32::uid;
+
+/// 固件版本（取自 Cargo.toml）
+pub const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 机器 ID
+///
+/// 使用 STM32 96 位唯一 ID 的十六进制表示（24 个字符），
+/// 出厂即固定，不依赖 DHCP 或人工配置
+#[cfg(target_os = "none")]
+pub fn machine_id() -> &'static str {
+    uid::uid_hex()
+}
+
+/// 机器 ID（主机测试：固定值）
+#[cfg(not(target_os = "none"))]
+pub fn machine_id() -> &'static str {
+    "000000000000000000000000"
+}
+
+/// 软件复位
+#[cfg(target_os = "none")]
+pub fn reboot() -> ! {
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// 软件复位（主机测试：不可达）
+#[cfg(not(target_os = "none"))]
+pub fn reboot() -> ! {
+    panic!("reboot requested")
+}
@@ -0,0 +1,43 @@
+// 灯光效果
+//
+// 事件总线观察者：按钮按下时点亮同号灯光，抬起时熄灭（灯光 ID 0 保留，按钮 0 没有对应灯光）。
+// 观察者处理慢时丢事件（Lagged），之后按状态中的按钮状态重新同步全部按钮灯。
+// 灯光命令走执行器队列，与 2002 共用；队列满时本次效果放弃
+
+use crate::app::{actuators, state};
+use crate::drivers::actuator::LightCommand;
+use crate::event::Event;
+use crate::event_bus::{EventKind, KindFilter};
+
+/// 灯光效果任务观察的事件种类
+pub const EFFECT_KINDS: KindFilter = KindFilter::only(EventKind::Button);
+
+/// 按钮灯图案（常亮）
+const BUTTON_LIGHT_PATTERN: u32 = 0;
+
+/// 按事件更新灯光
+pub fn on_event(event: &Event) {
+    match *event {
+        Event::ButtonPress { button_id, .. } => set_button_light(button_id, true),
+        Event::ButtonRelease { button_id, .. } => set_button_light(button_id, false),
+        _ => {}
+    }
+}
+
+/// 丢事件后按当前按钮状态重新同步按钮灯
+pub fn resync() {
+    let buttons = state::snapshot().inputs.buttons;
+    for (id, pressed) in buttons.iter().enumerate() {
+        set_button_light(id as u32, *pressed);
+    }
+}
+
+fn set_button_light(button_id: u32, on: bool) {
+    if state::light_valid(button_id) {
+        let _ = actuators::light(LightCommand {
+            light_id: button_id,
+            on,
+            pattern: BUTTON_LIGHT_PATTERN,
+        });
+    }
+}
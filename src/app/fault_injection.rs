@@ -0,0 +1,48 @@
+// 模拟故障注入队列
+//
+// 2005 处理器在分发上下文中校验后把每个 SimulatedFault 包装成 Event::FaultDetected
+// （simulated = true，带持续时间），由 fault_injection_task 发布到事件总线。
+// 注入的故障因此与真实检测完全同路：分发 → 故障登记 / 安全策略，事件日志同样记录，
+// 模拟的 FATAL 也会冻结日志并转储镜像。信封在处理器中创建，cause_id 指向 2005 命令
+
+use crate::error::{Error, Result};
+use crate::event::coinpusher::v1::{FaultCode, SimulatedFault};
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+
+/// 队列深度（单条 2005 最多注入的故障数）
+pub const INJECTION_QUEUE_DEPTH: usize = 8;
+
+static QUEUE: Channel<CriticalSectionRawMutex, EventEnvelope, INJECTION_QUEUE_DEPTH> = Channel::new();
+
+/// 排队注入一组模拟故障（队列放不下全部时一个都不注入）
+pub fn submit(faults: &[SimulatedFault]) -> Result<()> {
+    if faults.len() > QUEUE.free_capacity() {
+        crate::log_warn!("Fault injection: queue full, {} faults refused", faults.len());
+        return Err(Error::BufferFull);
+    }
+    for fault in faults {
+        let event = Event::FaultDetected {
+            hardware_type: fault.hardware_type,
+            hardware_id: fault.hardware_id,
+            fault: fault.fault.unwrap_or(FaultCode::Unspecified as i32),
+            severity: fault.severity,
+            fault_code: fault.fault_code,
+            message: fault.message.clone(),
+            simulated: true,
+            duration_ms: fault.duration_ms,
+        };
+        QUEUE.try_send(EventEnvelope::new(Source::System, event)).ok();
+    }
+    Ok(())
+}
+
+/// 发布循环：把排队的模拟故障事件发布到事件总线（fault_injection_task 运行）
+pub async fn run(event_tx: EventPublisher) -> ! {
+    loop {
+        let envelope = QUEUE.receive().await;
+        event_tx.publish(envelope).await;
+    }
+}
@@ -0,0 +1,376 @@
+// 故障登记表
+//
+// 当前存在的故障按 (HardwareType, hardware_id, FaultCode) 区分，同一故障重复发生只累计次数：
+// - 新故障或等级变化时上报 1006；重复发生只更新最近发生时间和次数
+// - 故障消失（传感器恢复）或 2004 清除时上报 1006（cleared=1）
+// - 登记表满时新故障替换等级最低的一条，被替换的故障按清除上报 1006（cleared=1，带恢复动作）；
+//   没有更低等级可替换时新故障不登记，只上报 1006
+// - 2004 清除范围：不填=全部、只填 hardware_type=该类硬件、两者都填=单个硬件
+// - 2005 注入的模拟故障走同一路径，标记 simulated；可带持续时间，到期由
+//   fault_expiry_task 自动清除。同一故障被真实检测到后转为真实故障，不再自动清除
+//
+// 每次变化后把数量 / 最高等级同步到 state.faults，1002 报告与心跳由此得到故障概况；
+// 再交给 safety 按等级执行 / 恢复安全动作，动作随 1006 上报
+
+use crate::app::safety;
+use crate::app::state::{self, Faults};
+use crate::event::coinpusher::v1::{
+    BoolFlag, FaultCode, FaultSeverity, M1006Toc, M2004Tos, SafetyAction,
+};
+use crate::event::current_meta;
+use crate::net::uplink;
+use alloc::string::String;
+use alloc::vec::Vec as AllocVec;
+use core::cell::RefCell;
+use defmt::{info, Format};
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use heapless::Vec;
+
+/// 同时登记的故障数上限
+pub const FAULT_CAPACITY: usize = 16;
+
+/// 故障标识
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct FaultKey {
+    /// HardwareType
+    pub hardware_type: i32,
+    /// 硬件编号（不区分编号的硬件为 None）
+    pub hardware_id: Option<u32>,
+    /// FaultCode
+    pub fault: i32,
+}
+
+/// 一条登记中的故障
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultEntry {
+    pub key: FaultKey,
+    /// FaultSeverity
+    pub severity: i32,
+    /// 首次发生时间
+    pub first_seen: Instant,
+    /// 最近发生时间
+    pub last_seen: Instant,
+    /// 累计发生次数
+    pub occurrences: u32,
+    /// 自定义故障码（1006 fault_code）
+    pub fault_code: Option<u32>,
+    /// 简短说明
+    pub message: Option<String>,
+    /// 2005 注入的模拟故障
+    pub simulated: bool,
+    /// 模拟故障自动清除的时间
+    pub expires_at: Option<Instant>,
+}
+
+/// 2004 清除范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum ClearScope {
+    /// 全部故障
+    All,
+    /// 某类硬件的全部故障
+    Type(i32),
+    /// 单个硬件的全部故障
+    Device(i32, u32),
+}
+
+impl ClearScope {
+    /// 由 2004 请求得到清除范围（只填 hardware_id 视为非法）
+    pub fn from_request(req: &M2004Tos) -> Option<Self> {
+        match (req.hardware_type, req.hardware_id) {
+            (None, None) => Some(ClearScope::All),
+            (Some(hw), None) => Some(ClearScope::Type(hw)),
+            (Some(hw), Some(id)) => Some(ClearScope::Device(hw, id)),
+            (None, Some(_)) => None,
+        }
+    }
+
+    fn matches(&self, key: &FaultKey) -> bool {
+        match *self {
+            ClearScope::All => true,
+            ClearScope::Type(hw) => key.hardware_type == hw,
+            ClearScope::Device(hw, id) => key.hardware_type == hw && key.hardware_id == Some(id),
+        }
+    }
+}
+
+/// 登记结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Raised {
+    /// 新故障（登记表满时可能替换了一条更低等级的故障）
+    New,
+    /// 已存在，等级变化
+    SeverityChanged,
+    /// 已存在，只累计次数
+    Repeated,
+    /// 登记表已满且没有更低等级的故障可替换，未登记（仍上报 1006）
+    Overflow,
+}
+
+static REGISTRY: Mutex<CriticalSectionRawMutex, RefCell<Vec<FaultEntry, FAULT_CAPACITY>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// 模拟故障的到期时间有变化，唤醒 fault_expiry_task
+static EXPIRY_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// 模拟故障参数
+#[derive(Clone, Copy)]
+struct Simulation {
+    expires_at: Option<Instant>,
+}
+
+/// 登记一次故障发生
+pub fn raise(key: FaultKey, severity: i32, fault_code: Option<u32>, message: Option<&str>) -> Raised {
+    record(key, severity, fault_code, message, None)
+}
+
+/// 登记一次模拟故障（`duration` 为 None 时直到 2004 清除或回到正常模式）
+pub fn raise_simulated(
+    key: FaultKey,
+    severity: i32,
+    fault_code: Option<u32>,
+    message: Option<&str>,
+    duration: Option<Duration>,
+) -> Raised {
+    let expires_at = duration.map(|d| Instant::now() + d);
+    let raised = record(key, severity, fault_code, message, Some(Simulation { expires_at }));
+    if expires_at.is_some() {
+        EXPIRY_CHANGED.signal(());
+    }
+    raised
+}
+
+fn record(
+    key: FaultKey,
+    severity: i32,
+    fault_code: Option<u32>,
+    message: Option<&str>,
+    simulation: Option<Simulation>,
+) -> Raised {
+    let now = Instant::now();
+    let (raised, entry, evicted) = REGISTRY.lock(|r| {
+        let mut registry = r.borrow_mut();
+        if let Some(entry) = registry.iter_mut().find(|e| e.key == key) {
+            entry.last_seen = now;
+            entry.occurrences = entry.occurrences.saturating_add(1);
+            if fault_code.is_some() {
+                entry.fault_code = fault_code;
+            }
+            if let Some(message) = message {
+                entry.message = Some(message.into());
+            }
+            match simulation {
+                // 真实检测：模拟故障转为真实故障
+                None => {
+                    entry.simulated = false;
+                    entry.expires_at = None;
+                }
+                // 再次注入：只刷新模拟故障的到期时间，真实故障保持不变
+                Some(sim) if entry.simulated => entry.expires_at = sim.expires_at,
+                Some(_) => {}
+            }
+            let raised = if entry.severity != severity {
+                entry.severity = severity;
+                Raised::SeverityChanged
+            } else {
+                Raised::Repeated
+            };
+            return (raised, entry.clone(), None);
+        }
+
+        let entry = FaultEntry {
+            key,
+            severity,
+            first_seen: now,
+            last_seen: now,
+            occurrences: 1,
+            fault_code,
+            message: message.map(String::from),
+            simulated: simulation.is_some(),
+            expires_at: simulation.and_then(|sim| sim.expires_at),
+        };
+        let mut evicted = None;
+        if registry.is_full() {
+            // 替换等级最低的一条，保证高等级故障（及其安全动作）不被挤掉
+            let lowest = registry
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, e)| e.severity)
+                .filter(|(_, e)| e.severity < severity)
+                .map(|(idx, _)| idx);
+            match lowest {
+                Some(idx) => evicted = Some(registry.swap_remove(idx)),
+                None => return (Raised::Overflow, entry, None),
+            }
+        }
+        registry.push(entry.clone()).ok();
+        (Raised::New, entry, evicted)
+    });
+
+    match raised {
+        Raised::Repeated => {
+            info!("Fault {:?} repeated ({} times)", key, entry.occurrences);
+        }
+        Raised::Overflow => {
+            crate::log_warn!("Fault registry full, {:?} not tracked", key);
+            report(&entry, false, None);
+        }
+        Raised::New | Raised::SeverityChanged => {
+            crate::log_warn!("Fault {:?} raised (severity {}, {:?})", key, severity, raised);
+            sync_state();
+            let action = safety::on_raised(&entry);
+            report(&entry, false, Some(action));
+        }
+    }
+
+    // 被替换的故障按清除上报；其限制已在 on_raised 重新推导时解除（除非仍由其它故障维持）
+    if let Some(evicted) = evicted {
+        crate::log_warn!("Fault registry full, {:?} evicted", evicted.key);
+        report(&evicted, true, safety::recovery_for(&evicted));
+    }
+    raised
+}
+
+/// 故障消失（如传感器恢复），返回是否存在该故障
+pub fn resolve(key: FaultKey) -> bool {
+    let removed = remove_where(|e| e.key == key);
+    finish_clear(&removed);
+    !removed.is_empty()
+}
+
+/// 按范围清除（2004），返回被清除的故障
+pub fn clear(scope: ClearScope) -> AllocVec<FaultEntry> {
+    let removed = remove_where(|e| scope.matches(&e.key));
+    info!("Faults cleared ({:?}): {}", scope, removed.len());
+    finish_clear(&removed);
+    removed
+}
+
+/// 清除全部模拟故障（回到正常模式时）
+pub fn clear_simulated() -> AllocVec<FaultEntry> {
+    let removed = remove_where(|e| e.simulated);
+    if !removed.is_empty() {
+        info!("Simulated faults cleared: {}", removed.len());
+    }
+    finish_clear(&removed);
+    removed
+}
+
+/// 清除已到期的模拟故障
+fn expire(now: Instant) {
+    let removed = remove_where(|e| e.simulated && e.expires_at.is_some_and(|at| at <= now));
+    for entry in removed.iter() {
+        info!("Simulated fault {:?} expired", entry.key);
+    }
+    finish_clear(&removed);
+}
+
+/// 最早到期的模拟故障
+fn next_expiry() -> Option<Instant> {
+    REGISTRY.lock(|r| r.borrow().iter().filter_map(|e| e.expires_at).min())
+}
+
+/// 到期清除循环：等到最早的到期时间（或到期时间变化）后清除到期的模拟故障
+pub async fn run_expiry() -> ! {
+    loop {
+        expire(Instant::now());
+
+        let timer = async {
+            match next_expiry() {
+                Some(at) => Timer::at(at).await,
+                None => core::future::pending().await,
+            }
+        };
+        select(EXPIRY_CHANGED.wait(), timer).await;
+    }
+}
+
+fn remove_where(mut pred: impl FnMut(&FaultEntry) -> bool) -> AllocVec<FaultEntry> {
+    REGISTRY.lock(|r| {
+        let mut registry = r.borrow_mut();
+        let mut removed = AllocVec::new();
+        registry.retain(|e| {
+            if pred(e) {
+                removed.push(e.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    })
+}
+
+fn finish_clear(removed: &[FaultEntry]) {
+    if removed.is_empty() {
+        return;
+    }
+    sync_state();
+    safety::on_cleared();
+    for entry in removed {
+        report(entry, true, safety::recovery_for(entry));
+    }
+}
+
+/// 当前登记的全部故障
+pub fn active() -> AllocVec<FaultEntry> {
+    REGISTRY.lock(|r| r.borrow().iter().cloned().collect())
+}
+
+/// 是否存在该故障
+pub fn is_active(key: FaultKey) -> bool {
+    REGISTRY.lock(|r| r.borrow().iter().any(|e| e.key == key))
+}
+
+/// 由登记表计算故障概况
+pub fn summary() -> Faults {
+    REGISTRY.lock(|r| {
+        let registry = r.borrow();
+        Faults {
+            active_count: registry.len() as u32,
+            max_severity: registry.iter().map(|e| e.severity).max().unwrap_or(0),
+        }
+    })
+}
+
+/// 是否全部正常（没有 ERROR 及以上的故障）
+pub fn all_ok() -> bool {
+    summary().max_severity < FaultSeverity::Error as i32
+}
+
+fn sync_state() {
+    let faults = summary();
+    state::update(|s| s.faults = faults);
+}
+
+fn millis(at: Instant) -> u32 {
+    at.as_millis() as u32
+}
+
+/// 上报 1006
+fn report(entry: &FaultEntry, cleared: bool, action: Option<SafetyAction>) {
+    let fault = (entry.key.fault != FaultCode::Unspecified as i32).then_some(entry.key.fault);
+    uplink::publish(
+        0x1006,
+        &M1006Toc {
+            hardware_type: entry.key.hardware_type,
+            hardware_id: entry.key.hardware_id,
+            severity: entry.severity,
+            fault_code: entry.fault_code,
+            message: entry.message.clone(),
+            state_version: Some(state::version()),
+            fault,
+            cleared: cleared.then_some(BoolFlag::BoolTrue as i32),
+            occurrences: Some(entry.occurrences),
+            first_seen_ms: Some(millis(entry.first_seen)),
+            last_seen_ms: Some(millis(entry.last_seen)),
+            active_count: Some(summary().active_count),
+            safety_action: action.map(|a| a as i32),
+            simulated: entry.simulated.then_some(BoolFlag::BoolTrue as i32),
+            meta: current_meta(),
+        },
+    );
+}
@@ -1,5 +1,6 @@
-// 按键事件处理
+// 按键事件处理（上行任务调用，按钮状态由状态任务记录）
 use crate::error::Result;
+use crate::event::Event;
 use defmt::info;
 use prost::Message;
 
@@ -11,11 +12,37 @@ pub fn on_button_press(button_id: u32, duration_ms: Option<u32>) -> Result<()> {
         duration_ms
     );
 
-    // 创建 protobuf 消息
-    let button_event = crate::event::coinpusher::v1::M1003Toc {
+    publish(&Event::ButtonPress {
         button_id,
-        action: crate::event::coinpusher::v1::ButtonAction::ButtonPressed as i32,
         duration_ms,
+    })
+}
+
+/// 处理按钮抬起事件
+pub fn on_button_release(button_id: u32, held_ms: u32) -> Result<()> {
+    info!("Handler: Button {} released (held {} ms)", button_id, held_ms);
+
+    publish(&Event::ButtonRelease { button_id, held_ms })
+}
+
+/// 处理按钮长按事件
+pub fn on_button_long_press(button_id: u32, held_ms: u32) -> Result<()> {
+    info!("Handler: Button {} long press ({} ms)", button_id, held_ms);
+
+    publish(&Event::ButtonLongPress { button_id, held_ms })
+}
+
+/// 处理按钮双击事件
+pub fn on_button_double_click(button_id: u32) -> Result<()> {
+    info!("Handler: Button {} double click", button_id);
+
+    publish(&Event::ButtonDoubleClick { button_id })
+}
+
+/// 编码为 1003 按钮事件并上行
+fn publish(event: &Event) -> Result<()> {
+    let Some(button_event) = event.to_button_event_proto() else {
+        return Err(crate::error::Error::InvalidParameter);
     };
 
     // 编码为字节
@@ -24,8 +51,7 @@ pub fn on_button_press(button_id: u32, duration_ms: Option<u32>) -> Result<()> {
 
     info!("  Encoded button event: {} bytes", buf.len());
 
-    // TODO: 发送到网络层
-    // 在真实系统中，这里会将消息发送给 TCP 客户端或服务器
+    crate::net::uplink::publish_raw(0x1003, buf);
 
     Ok(())
 }
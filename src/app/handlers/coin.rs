@@ -1,14 +1,60 @@
-// 投币事件处理
+// 投币事件处理（上行任务调用）
+//
+// 计数由状态任务落账（`state::on_event`），这里按其累计值上行
 use crate::error::Result;
+use crate::event::coinpusher::v1::{M1004Toc, M1005Toc, M1009Toc};
+use crate::event::current_meta;
+use crate::net::uplink;
 use defmt::info;
 
 /// 处理投币事件
-pub fn on_coin_insert(channel_id: u32, value: u32) -> Result<()> {
+pub fn on_coin_insert(channel_id: u32, value: u32, total: u64) -> Result<()> {
     info!("Handler: Coin inserted (channel: {}, value: {})", channel_id, value);
 
-    // TODO: 更新投币统计
     // TODO: 触发马达或其他动作
-    // TODO: 发送投币事件到服务器
+
+    uplink::publish(
+        0x1004,
+        &M1004Toc {
+            channel_id,
+            coin_value: Some(value),
+            quantity: 1,
+            total: Some(total),
+            meta: current_meta(),
+        },
+    );
+
+    Ok(())
+}
+
+/// 处理回币计数事件
+pub fn on_payout(delta: u32, total: u64) -> Result<()> {
+    info!("Handler: Payout {} coins", delta);
+
+    uplink::publish(
+        0x1005,
+        &M1005Toc {
+            delta,
+            total: total as u32,
+            meta: current_meta(),
+        },
+    );
+
+    Ok(())
+}
+
+/// 处理退币事件（退币器出币传感器）
+pub fn on_coin_out(count: u32, total: u64) -> Result<()> {
+    info!("Handler: Coin out {} coins", count);
+
+    uplink::publish(
+        0x1009,
+        &M1009Toc {
+            count,
+            total,
+            meta: current_meta(),
+        },
+    );
 
     Ok(())
 }
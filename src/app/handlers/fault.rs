@@ -1,14 +1,83 @@
 // 故障事件处理
-use crate::error::Result;
-use defmt::info;
+use crate::app::fault_registry::{self, ClearScope, FaultKey};
+use crate::app::mode;
+use crate::error::{Error, Result};
+use defmt::{info, Format};
+use embassy_time::Duration;
+
+/// 故障摘要（供发现服务、状态接口使用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct FaultSummary {
+    /// 当前存在的故障数量
+    pub active_count: u32,
+    /// 最高故障等级（0=无故障）
+    pub max_severity: i32,
+}
+
+/// 获取当前故障摘要
+pub fn fault_summary() -> FaultSummary {
+    let faults = fault_registry::summary();
+    FaultSummary {
+        active_count: faults.active_count,
+        max_severity: faults.max_severity,
+    }
+}
+
+/// 按范围清除故障
+pub fn clear_faults(scope: ClearScope) {
+    fault_registry::clear(scope);
+}
 
 /// 处理故障检测事件
-pub fn on_fault_detected(hardware_type: i32, severity: i32) -> Result<()> {
-    info!("Handler: Fault detected (hw_type: {}, severity: {})", hardware_type, severity);
+pub fn on_fault_detected(
+    key: FaultKey,
+    severity: i32,
+    fault_code: Option<u32>,
+    message: Option<&str>,
+) -> Result<()> {
+    crate::log_warn!(
+        "Fault detected (hw_type: {}, hw_id: {:?}, fault: {}, severity: {})",
+        key.hardware_type,
+        key.hardware_id,
+        key.fault,
+        severity
+    );
+
+    // 登记后由 safety 按严重程度采取措施
+    fault_registry::raise(key, severity, fault_code, message);
+
+    Ok(())
+}
+
+/// 处理模拟故障事件（2005 注入），与真实检测走同一登记 / 安全策略路径
+///
+/// 注入后、分发前可能已回到正常模式，此时不再登记
+pub fn on_simulated_fault(
+    key: FaultKey,
+    severity: i32,
+    fault_code: Option<u32>,
+    message: Option<&str>,
+    duration_ms: Option<u32>,
+) -> Result<()> {
+    if !mode::allows_fault_injection() {
+        crate::log_warn!("Simulated fault {:?} dropped in {:?} mode", key, mode::current());
+        return Err(Error::SystemError);
+    }
 
-    // TODO: 记录故障
-    // TODO: 发送故障报告到服务器
-    // TODO: 根据严重程度采取措施
+    info!(
+        "Simulated fault (hw_type: {}, hw_id: {:?}, severity: {}, duration: {:?} ms)",
+        key.hardware_type,
+        key.hardware_id,
+        severity,
+        duration_ms
+    );
 
+    fault_registry::raise_simulated(
+        key,
+        severity,
+        fault_code,
+        message,
+        duration_ms.map(|ms| Duration::from_millis(ms as u64)),
+    );
     Ok(())
 }
@@ -10,12 +10,35 @@ pub fn on_heartbeat() -> Result<()> {
 
     info!("Handler: Heartbeat (uptime: {} ms)", uptime_ms);
 
+    let faults = crate::app::fault_registry::summary();
+    let all_ok = if crate::app::fault_registry::all_ok() {
+        crate::event::coinpusher::v1::BoolFlag::BoolTrue
+    } else {
+        crate::event::coinpusher::v1::BoolFlag::BoolFalse
+    };
+
+    let overflow = crate::event_bus::overflow_totals();
+    let event_bus = crate::event::coinpusher::v1::EventBusStats {
+        blocked: overflow.blocked,
+        coalesced: overflow.coalesced,
+        dropped: overflow.dropped,
+        lagged: overflow.lagged,
+        staged: crate::event_bus::staged_len() as u32,
+    };
+
     // 创建心跳 protobuf 消息
     let heartbeat = crate::event::coinpusher::v1::M1001Toc {
         uptime_ms,
-        all_ok: crate::event::coinpusher::v1::BoolFlag::BoolTrue as i32,
-        error_count: 0,
-        state_version: Some(1),
+        all_ok: all_ok as i32,
+        error_count: faults.active_count,
+        state_version: Some(crate::app::state::version()),
+        event_bus: Some(event_bus),
+        coin_inhibited: Some(if crate::app::safety::coins_inhibited() {
+            crate::event::coinpusher::v1::BoolFlag::BoolTrue as i32
+        } else {
+            crate::event::coinpusher::v1::BoolFlag::BoolFalse as i32
+        }),
+        meta: crate::event::current_meta(),
     };
 
     // 编码为字节
@@ -24,7 +47,7 @@ pub fn on_heartbeat() -> Result<()> {
 
     info!("  Encoded heartbeat: {} bytes", buf.len());
 
-    // TODO: 发送到网络层
+    crate::net::uplink::publish_raw(0x1001, buf);
 
     Ok(())
 }
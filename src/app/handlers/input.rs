@@ -0,0 +1,47 @@
+// 门开关 / 倾斜传感器事件处理（输入状态由状态任务记录）
+use crate::app::fault_registry::{self, FaultKey};
+use crate::app::mode;
+use crate::error::Result;
+use crate::event::coinpusher::v1::{FaultCode, FaultSeverity, HardwareType};
+use defmt::info;
+
+const DOOR_OPEN: FaultKey = FaultKey {
+    hardware_type: HardwareType::HwDoor as i32,
+    hardware_id: None,
+    fault: FaultCode::DoorOpen as i32,
+};
+
+const TILT: FaultKey = FaultKey {
+    hardware_type: HardwareType::HwTiltSensor as i32,
+    hardware_id: None,
+    fault: FaultCode::Tilt as i32,
+};
+
+/// 处理机门开关变化
+///
+/// 开门登记故障（WARN / DOOR_OPEN）并进入维护模式，关门自动清除并退出
+pub fn on_door_changed(open: bool) -> Result<()> {
+    info!("Handler: Door {}", if open { "OPENED" } else { "CLOSED" });
+
+    mode::set_service(open);
+    if open {
+        fault_registry::raise(DOOR_OPEN, FaultSeverity::Warn as i32, None, Some("door open"));
+    } else {
+        fault_registry::resolve(DOOR_OPEN);
+    }
+    Ok(())
+}
+
+/// 处理倾斜 / 震动传感器变化
+///
+/// 触发时登记故障（ERROR / TILT），恢复后自动清除
+pub fn on_tilt_changed(active: bool) -> Result<()> {
+    info!("Handler: Tilt {}", if active { "ACTIVE" } else { "CLEARED" });
+
+    if active {
+        fault_registry::raise(TILT, FaultSeverity::Error as i32, None, Some("tilt detected"));
+    } else {
+        fault_registry::resolve(TILT);
+    }
+    Ok(())
+}
@@ -6,3 +6,4 @@ pub mod coin;
 pub mod motor;
 pub mod network;
 pub mod fault;
+pub mod input;
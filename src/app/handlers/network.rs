@@ -1,7 +1,19 @@
 // 网络消息处理
-use crate::error::Result;
+use crate::app::actuators;
+use crate::app::fault_injection;
+use crate::app::fault_registry::ClearScope;
+use crate::app::journal::{self, JOURNAL_PAGE_MAX};
+use crate::app::mode;
+use crate::app::safety;
+use crate::app::state;
+use crate::drivers::actuator::{LightCommand, MotorCommand};
+use crate::error::{Error, Result};
+use crate::app::subscriptions;
+use crate::event::coinpusher::v1::{BoolFlag, M2001Tos, M2002Tos, M2003Tos, M2004Tos, M2005Tos, M2006Tos, M2007Tos};
+use crate::event::{self, current_meta, current_origin};
 use alloc::vec::Vec;
 use defmt::info;
+use prost::Message;
 
 /// 处理网络接收的消息
 pub fn on_network_message(cmd: u16, payload: Vec<u8>) -> Result<()> {
@@ -14,39 +26,127 @@ pub fn on_network_message(cmd: u16, payload: Vec<u8>) -> Result<()> {
         0x2003 => handle_motor_command(&payload),
         0x2004 => handle_clear_fault(&payload),
         0x2005 => handle_simulate_fault(&payload),
+        0x2006 => handle_journal_query(&payload),
+        0x2007 => handle_time_sync(&payload),
         _ => {
-            defmt::warn!("Unknown network command: {:04X}", cmd);
-            Err(crate::error::Error::NotFound)
+            crate::log_warn!("Unknown network command: {:04X}", cmd);
+            Err(Error::NotFound)
         }
     }
 }
 
-fn handle_request_status(_payload: &[u8]) -> Result<()> {
+fn handle_request_status(payload: &[u8]) -> Result<()> {
     info!("  -> Request Status");
-    // TODO: 发送状态报告
-    Ok(())
+
+    let req = M2001Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    subscriptions::request(current_origin(), &req)
 }
 
-fn handle_light_command(_payload: &[u8]) -> Result<()> {
+fn handle_light_command(payload: &[u8]) -> Result<()> {
     info!("  -> Light Command");
-    // TODO: 控制灯光
+
+    let cmd = M2002Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    // 先校验全部灯光 ID 与队列空间，避免部分生效
+    if let Some(light) = cmd.lights.iter().find(|l| !state::light_valid(l.light_id)) {
+        crate::log_warn!("Invalid light id: {}", light.light_id);
+        return Err(Error::InvalidParameter);
+    }
+    if actuators::free_slots() < cmd.lights.len() {
+        crate::log_warn!("Actuator queue busy, {} light commands refused", cmd.lights.len());
+        return Err(Error::BufferFull);
+    }
+    for light in cmd.lights.iter() {
+        let on = light.on == BoolFlag::BoolTrue as i32;
+        actuators::light(LightCommand {
+            light_id: light.light_id,
+            on,
+            pattern: light.pattern.unwrap_or(0),
+        })?;
+        info!("     light {} -> {}", light.light_id, if on { "ON" } else { "OFF" });
+    }
     Ok(())
 }
 
-fn handle_motor_command(_payload: &[u8]) -> Result<()> {
+fn handle_motor_command(payload: &[u8]) -> Result<()> {
     info!("  -> Motor Command");
-    // TODO: 控制马达
+
+    let cmd = M2003Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    if !state::motor_command_valid(cmd.motor_type, cmd.command, cmd.duration_ms, cmd.count) {
+        crate::log_warn!("Invalid motor command: type={}, cmd={}", cmd.motor_type, cmd.command);
+        return Err(Error::InvalidParameter);
+    }
+    if !safety::motor_allowed(cmd.motor_type, cmd.command) {
+        crate::log_warn!("Motor command refused by safety policy: type={}", cmd.motor_type);
+        return Err(Error::SystemError);
+    }
+    actuators::motor(MotorCommand {
+        motor_type: cmd.motor_type,
+        command: cmd.command,
+        duration_ms: cmd.duration_ms,
+        count: cmd.count,
+        speed_level: cmd.speed_level,
+    })?;
+    info!("     motor type={} cmd={}", cmd.motor_type, cmd.command);
     Ok(())
 }
 
-fn handle_clear_fault(_payload: &[u8]) -> Result<()> {
+fn handle_clear_fault(payload: &[u8]) -> Result<()> {
     info!("  -> Clear Fault");
-    // TODO: 清除故障
+
+    let req = M2004Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    let Some(scope) = ClearScope::from_request(&req) else {
+        crate::log_warn!("Clear fault: hardware_id without hardware_type");
+        return Err(Error::InvalidParameter);
+    };
+    super::fault::clear_faults(scope);
     Ok(())
 }
 
-fn handle_simulate_fault(_payload: &[u8]) -> Result<()> {
+fn handle_simulate_fault(payload: &[u8]) -> Result<()> {
     info!("  -> Simulate Fault");
-    // TODO: 模拟故障
+
+    if !mode::allows_fault_injection() {
+        crate::log_warn!("Fault injection refused in {:?} mode", mode::current());
+        return Err(Error::SystemError);
+    }
+
+    let req = M2005Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    fault_injection::submit(&req.faults)
+}
+
+fn handle_journal_query(payload: &[u8]) -> Result<()> {
+    info!("  -> Journal Query");
+
+    let query = M2006Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    let max = query.max_entries.map_or(JOURNAL_PAGE_MAX, |n| n as usize);
+
+    let mut page = if query.from_flash == Some(BoolFlag::BoolTrue as i32) {
+        journal::page_from_mirror(query.from_event_id, query.from_timestamp_ms, max)
+            .ok_or(Error::NotFound)?
+    } else {
+        journal::page(query.from_event_id, query.from_timestamp_ms, max)
+    };
+    page.meta = current_meta();
+    info!("     {} entries, next={:?}", page.entries.len(), page.next_event_id);
+    crate::net::uplink::publish_to(current_origin(), 0x1008, &page);
+
+    if query.clear_flash == Some(BoolFlag::BoolTrue as i32) {
+        journal::clear_mirror().map_err(|_| Error::SystemError)?;
+    }
+    if query.unfreeze == Some(BoolFlag::BoolTrue as i32) {
+        journal::unfreeze();
+    }
+    Ok(())
+}
+
+fn handle_time_sync(payload: &[u8]) -> Result<()> {
+    info!("  -> Time Sync");
+
+    let req = M2007Tos::decode(payload).map_err(|_| Error::InvalidParameter)?;
+    if req.unix_ms == 0 {
+        return Err(Error::InvalidParameter);
+    }
+    event::set_wall_clock(req.unix_ms);
+    info!("     wall clock set: {} ms", req.unix_ms);
     Ok(())
 }
@@ -0,0 +1,431 @@
+// 事件日志（最近 N 条事件的环形缓冲）
+//
+// 现场排查“吞币”等问题时，通过 2006 命令分页取回最近的事件（1008 应答）。
+// - journal_task 作为事件总线订阅者写入（心跳不记录，避免冲掉有用的记录）
+// - 收到 FATAL 故障后自动冻结：该故障本身仍会记录，之后的事件只计数不写入，
+//   保留故障发生前的现场；2006 unfreeze=1 解冻
+// - 可选 Flash 镜像（JournalMirror）：冻结时把整个日志转储一次，
+//   断电重启后仍可用 2006 from_flash=1 读取上次的现场
+// - 镜像擦除很慢（整扇区，CPU 停顿），不在 FATAL 时进行：启动时准备好空白镜像；
+//   上次的转储保留到 2006 clear_flash=1 清除为止，期间新的 FATAL 不转储（保留最早的现场）
+// - 冻结后系统决策（record_decision）仍写入，但日志满时不再挤掉冻结前的记录
+//
+// 条目以定长 32 字节记录写入镜像：
+//   [event_id u32][timestamp_ms u32][wall_clock_ms u64][cause_id u32]
+//   [source u8][kind u8][flags u8][0][arg0 u32][arg1 u32]  （小端）
+
+use crate::event::coinpusher::v1::{
+    BoolFlag, EventMeta, FaultSeverity, JournalEntry, JournalEventKind, M1008Toc,
+};
+use crate::event::{Event, EventEnvelope};
+use core::cell::{Cell, RefCell};
+use defmt::{info, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use heapless::Deque;
+
+/// 日志容量（条）
+pub const JOURNAL_CAPACITY: usize = 256;
+
+/// 每页最大条数（受单包载荷长度限制）
+pub const JOURNAL_PAGE_MAX: usize = 16;
+
+/// 镜像记录长度
+pub const RECORD_SIZE: usize = 32;
+
+const FLAG_WALL_CLOCK: u8 = 1 << 0;
+const FLAG_CAUSE: u8 = 1 << 1;
+const FLAG_ARG0: u8 = 1 << 2;
+const FLAG_ARG1: u8 = 1 << 3;
+
+/// 镜像错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum MirrorError {
+    /// 擦除 / 写入失败
+    Write,
+    /// 读取失败
+    Read,
+    /// 镜像未就绪（存有未清除的转储，或擦除失败）
+    NotReady,
+}
+
+/// 日志持久化镜像（Flash 等）
+///
+/// 转储写入已擦除的镜像：逐条 `write_record` → `commit`；
+/// `commit` 之前断电，镜像视为无效（不会读到半份转储）
+pub trait JournalMirror {
+    /// 擦除镜像（可能停顿较久，只在启动 / 运维清除时调用）
+    fn erase(&mut self) -> Result<(), MirrorError>;
+    /// 镜像是否为擦除态，可直接写入转储
+    fn is_blank(&mut self) -> bool;
+    /// 写入第 `index` 条记录
+    fn write_record(&mut self, index: usize, record: &[u8; RECORD_SIZE]) -> Result<(), MirrorError>;
+    /// 写入条数标记，转储生效
+    fn commit(&mut self, count: usize) -> Result<(), MirrorError>;
+    /// 有效转储的条数（无转储为 None）
+    fn stored_count(&mut self) -> Option<usize>;
+    /// 读取第 `index` 条记录
+    fn read_record(&mut self, index: usize, record: &mut [u8; RECORD_SIZE]) -> Result<(), MirrorError>;
+}
+
+struct Journal {
+    entries: Deque<JournalEntry, JOURNAL_CAPACITY>,
+    frozen: bool,
+    missed: u32,
+}
+
+static JOURNAL: Mutex<CriticalSectionRawMutex, RefCell<Journal>> = Mutex::new(RefCell::new(Journal {
+    entries: Deque::new(),
+    frozen: false,
+    missed: 0,
+}));
+
+/// Flash 镜像（启动时注册，未注册则只保留 RAM 日志）
+static MIRROR: Mutex<CriticalSectionRawMutex, RefCell<Option<&'static mut (dyn JournalMirror + Send)>>> =
+    Mutex::new(RefCell::new(None));
+
+/// 镜像是否已擦除、可接受下一次转储
+static MIRROR_READY: Mutex<CriticalSectionRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// 注册持久化镜像（启动时、任务运行前调用）
+///
+/// 存有上次的转储时保留；否则确保镜像为擦除态，FATAL 时只需写入
+pub fn set_mirror(mirror: &'static mut (dyn JournalMirror + Send)) {
+    let ready = match mirror.stored_count() {
+        Some(count) => {
+            info!("Journal: mirror holds a dump of {} entries (kept until 2006 clear_flash=1)", count);
+            false
+        }
+        None if mirror.is_blank() => {
+            info!("Journal: mirror empty");
+            true
+        }
+        None => {
+            info!("Journal: erasing mirror");
+            match mirror.erase() {
+                Ok(()) => true,
+                Err(e) => {
+                    crate::log_warn!("Journal: mirror erase failed: {:?}", e);
+                    false
+                }
+            }
+        }
+    };
+    MIRROR.lock(|m| *m.borrow_mut() = Some(mirror));
+    MIRROR_READY.lock(|r| r.set(ready));
+}
+
+/// 清除镜像中的转储并准备下一次转储（擦除期间 CPU 停顿约 1~2 s）
+pub fn clear_mirror() -> Result<(), MirrorError> {
+    let Some(mirror) = MIRROR.lock(|m| m.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let result = mirror.erase();
+    MIRROR.lock(|m| *m.borrow_mut() = Some(mirror));
+    MIRROR_READY.lock(|r| r.set(result.is_ok()));
+    if result.is_ok() {
+        info!("Journal: mirror cleared");
+    }
+    result
+}
+
+/// 日志条目（事件信封 + 种类 + 两个参数）
+fn entry_for(envelope: &EventEnvelope) -> JournalEntry {
+    let (kind, arg0, arg1) = match &envelope.event {
+        Event::ButtonPress {
+            button_id,
+            duration_ms,
+        } => (JournalEventKind::Button, Some(*button_id), *duration_ms),
+        Event::ButtonRelease { button_id, held_ms } => {
+            (JournalEventKind::ButtonRelease, Some(*button_id), Some(*held_ms))
+        }
+        Event::ButtonLongPress { button_id, held_ms } => {
+            (JournalEventKind::ButtonLongPress, Some(*button_id), Some(*held_ms))
+        }
+        Event::ButtonDoubleClick { button_id } => {
+            (JournalEventKind::ButtonDoubleClick, Some(*button_id), None)
+        }
+        Event::CoinInsert { channel_id, value } => {
+            (JournalEventKind::Coin, Some(*channel_id), Some(*value))
+        }
+        Event::PayoutCount { delta } => (JournalEventKind::Payout, Some(*delta), None),
+        Event::CoinOut { count } => (JournalEventKind::CoinOut, Some(*count), None),
+        Event::DoorChanged { open } => (JournalEventKind::Door, Some(*open as u32), None),
+        Event::TiltChanged { active } => (JournalEventKind::Tilt, Some(*active as u32), None),
+        Event::NetworkIncoming { cmd, payload } => (
+            JournalEventKind::Network,
+            Some(*cmd as u32),
+            Some(payload.len() as u32),
+        ),
+        Event::HeartbeatTick => (JournalEventKind::Heartbeat, None, None),
+        Event::MotorStateChanged { motor_id, running } => {
+            (JournalEventKind::Motor, Some(*motor_id), Some(*running as u32))
+        }
+        Event::FaultDetected {
+            hardware_type,
+            severity,
+            ..
+        } => (
+            JournalEventKind::Fault,
+            Some(*hardware_type as u32),
+            Some(*severity as u32),
+        ),
+    };
+
+    JournalEntry {
+        meta: envelope.meta(),
+        kind: kind as i32,
+        arg0,
+        arg1,
+    }
+}
+
+/// 记录一个事件；FATAL 故障后冻结。返回本次是否触发了冻结
+pub fn record(envelope: &EventEnvelope) -> bool {
+    let fatal = matches!(
+        envelope.event,
+        Event::FaultDetected { severity, .. } if severity == FaultSeverity::Fatal as i32
+    );
+
+    JOURNAL.lock(|j| {
+        let mut j = j.borrow_mut();
+        if j.frozen {
+            j.missed = j.missed.saturating_add(1);
+            return false;
+        }
+        if j.entries.is_full() {
+            j.entries.pop_front();
+        }
+        j.entries.push_back(entry_for(envelope)).ok();
+        if fatal {
+            j.frozen = true;
+        }
+        fatal
+    })
+}
+
+/// 记录一条不经事件总线的系统决策（安全策略等）
+///
+/// 冻结后仍写入：决策是对冻结现场的响应，属于现场的一部分；
+/// 但冻结且日志已满时不挤掉冻结前的记录，只计入 missed
+pub fn record_decision(kind: JournalEventKind, arg0: Option<u32>, arg1: Option<u32>) {
+    let entry = JournalEntry {
+        meta: crate::event::system_meta(),
+        kind: kind as i32,
+        arg0,
+        arg1,
+    };
+    JOURNAL.lock(|j| {
+        let mut j = j.borrow_mut();
+        if j.entries.is_full() {
+            if j.frozen {
+                j.missed = j.missed.saturating_add(1);
+                return;
+            }
+            j.entries.pop_front();
+        }
+        j.entries.push_back(entry).ok();
+    });
+}
+
+/// 手动冻结（安全策略等使用）
+pub fn freeze() {
+    JOURNAL.lock(|j| j.borrow_mut().frozen = true);
+}
+
+/// 解冻并恢复记录
+pub fn unfreeze() {
+    JOURNAL.lock(|j| {
+        let mut j = j.borrow_mut();
+        if j.frozen {
+            info!("Journal: unfrozen ({} events missed)", j.missed);
+        }
+        j.frozen = false;
+        j.missed = 0;
+    });
+}
+
+/// 日志是否已冻结
+pub fn is_frozen() -> bool {
+    JOURNAL.lock(|j| j.borrow().frozen)
+}
+
+fn flag(value: bool) -> i32 {
+    if value {
+        BoolFlag::BoolTrue as i32
+    } else {
+        BoolFlag::BoolFalse as i32
+    }
+}
+
+/// 是否落在查询范围内
+fn matches(entry: &JournalEntry, from_id: u32, from_ms: u32) -> bool {
+    entry.meta.event_id >= from_id && entry.meta.timestamp_ms >= from_ms
+}
+
+/// 从 RAM 日志取一页
+pub fn page(from_event_id: Option<u32>, from_timestamp_ms: Option<u32>, max: usize) -> M1008Toc {
+    let from_id = from_event_id.unwrap_or(0);
+    let from_ms = from_timestamp_ms.unwrap_or(0);
+    let max = max.clamp(1, JOURNAL_PAGE_MAX);
+
+    JOURNAL.lock(|j| {
+        let j = j.borrow();
+        let mut matching = j.entries.iter().filter(|e| matches(e, from_id, from_ms));
+        let entries = matching.by_ref().take(max).cloned().collect();
+
+        M1008Toc {
+            entries,
+            oldest_event_id: j.entries.front().map(|e| e.meta.event_id),
+            newest_event_id: j.entries.back().map(|e| e.meta.event_id),
+            frozen: flag(j.frozen),
+            next_event_id: matching.next().map(|e| e.meta.event_id),
+            missed: (j.missed > 0).then_some(j.missed),
+            from_flash: flag(false),
+            meta: None,
+        }
+    })
+}
+
+/// 从 Flash 镜像取一页（无镜像或无转储时返回 None）
+pub fn page_from_mirror(
+    from_event_id: Option<u32>,
+    from_timestamp_ms: Option<u32>,
+    max: usize,
+) -> Option<M1008Toc> {
+    let from_id = from_event_id.unwrap_or(0);
+    let from_ms = from_timestamp_ms.unwrap_or(0);
+    let max = max.clamp(1, JOURNAL_PAGE_MAX);
+
+    MIRROR.lock(|m| {
+        let mut m = m.borrow_mut();
+        let mirror = m.as_mut()?;
+        let count = mirror.stored_count()?;
+
+        let mut record = [0u8; RECORD_SIZE];
+        let stored = (0..count).filter_map(|i| {
+            mirror.read_record(i, &mut record).ok()?;
+            decode_record(&record)
+        });
+
+        let mut page = M1008Toc {
+            frozen: flag(true),
+            from_flash: flag(true),
+            ..Default::default()
+        };
+        for entry in stored {
+            page.oldest_event_id.get_or_insert(entry.meta.event_id);
+            page.newest_event_id = Some(entry.meta.event_id);
+            if !matches(&entry, from_id, from_ms) {
+                continue;
+            }
+            if page.entries.len() == max {
+                page.next_event_id.get_or_insert(entry.meta.event_id);
+                continue;
+            }
+            page.entries.push(entry);
+        }
+        Some(page)
+    })
+}
+
+/// 把当前 RAM 日志转储到已擦除的镜像（冻结时调用，不擦除）
+///
+/// 镜像中还有未清除的转储时返回 `NotReady`，保留最早的现场
+pub fn dump_to_mirror() -> Result<usize, MirrorError> {
+    // 镜像暂时取出，避免在临界区内写 Flash
+    let Some(mirror) = MIRROR.lock(|m| m.borrow_mut().take()) else {
+        return Ok(0);
+    };
+    if !MIRROR_READY.lock(|r| r.replace(false)) {
+        MIRROR.lock(|m| *m.borrow_mut() = Some(mirror));
+        return Err(MirrorError::NotReady);
+    }
+
+    let records = JOURNAL.lock(|j| {
+        j.borrow()
+            .entries
+            .iter()
+            .map(encode_record)
+            .collect::<alloc::vec::Vec<_>>()
+    });
+
+    let result = write_dump(mirror, &records);
+    MIRROR.lock(|m| *m.borrow_mut() = Some(mirror));
+    result
+}
+
+fn write_dump(mirror: &mut dyn JournalMirror, records: &[[u8; RECORD_SIZE]]) -> Result<usize, MirrorError> {
+    for (i, record) in records.iter().enumerate() {
+        mirror.write_record(i, record)?;
+    }
+    mirror.commit(records.len())?;
+    Ok(records.len())
+}
+
+/// 编码为定长记录
+pub fn encode_record(entry: &JournalEntry) -> [u8; RECORD_SIZE] {
+    let mut r = [0u8; RECORD_SIZE];
+    let mut flags = 0u8;
+    r[0..4].copy_from_slice(&entry.meta.event_id.to_le_bytes());
+    r[4..8].copy_from_slice(&entry.meta.timestamp_ms.to_le_bytes());
+    if let Some(wall) = entry.meta.wall_clock_ms {
+        r[8..16].copy_from_slice(&wall.to_le_bytes());
+        flags |= FLAG_WALL_CLOCK;
+    }
+    if let Some(cause) = entry.meta.cause_id {
+        r[16..20].copy_from_slice(&cause.to_le_bytes());
+        flags |= FLAG_CAUSE;
+    }
+    r[20] = entry.meta.source as u8;
+    r[21] = entry.kind as u8;
+    if let Some(arg0) = entry.arg0 {
+        r[24..28].copy_from_slice(&arg0.to_le_bytes());
+        flags |= FLAG_ARG0;
+    }
+    if let Some(arg1) = entry.arg1 {
+        r[28..32].copy_from_slice(&arg1.to_le_bytes());
+        flags |= FLAG_ARG1;
+    }
+    r[22] = flags;
+    r
+}
+
+/// 解码定长记录（擦除态 / 损坏记录返回 None）
+pub fn decode_record(r: &[u8; RECORD_SIZE]) -> Option<JournalEntry> {
+    let u32_at = |i: usize| u32::from_le_bytes([r[i], r[i + 1], r[i + 2], r[i + 3]]);
+    let flags = r[22];
+    if r[20] == 0 || r[20] == 0xFF || r[21] == 0 || r[21] == 0xFF {
+        return None;
+    }
+
+    Some(JournalEntry {
+        meta: EventMeta {
+            event_id: u32_at(0),
+            timestamp_ms: u32_at(4),
+            source: r[20] as i32,
+            wall_clock_ms: (flags & FLAG_WALL_CLOCK != 0)
+                .then(|| u64::from_le_bytes(r[8..16].try_into().unwrap())),
+            cause_id: (flags & FLAG_CAUSE != 0).then(|| u32_at(16)),
+        },
+        kind: r[21] as i32,
+        arg0: (flags & FLAG_ARG0 != 0).then(|| u32_at(24)),
+        arg1: (flags & FLAG_ARG1 != 0).then(|| u32_at(28)),
+    })
+}
+
+/// 记录一个事件，若因此冻结则转储到镜像（journal_task 调用）
+pub fn on_event(envelope: &EventEnvelope) {
+    if !record(envelope) {
+        return;
+    }
+    crate::log_warn!("Journal: FATAL fault, journal frozen at event #{}", envelope.id);
+    match dump_to_mirror() {
+        Ok(0) => {}
+        Ok(n) => info!("Journal: {} entries dumped to mirror", n),
+        Err(MirrorError::NotReady) => {
+            crate::log_warn!("Journal: mirror holds an uncleared dump, not overwritten")
+        }
+        Err(e) => crate::log_warn!("Journal: mirror dump failed: {:?}", e),
+    }
+}
@@ -1,3 +1,13 @@
 pub mod router;
 pub mod handlers;
 pub mod types;
+pub mod device;
+pub mod state;
+pub mod journal;
+pub mod fault_registry;
+pub mod safety;
+pub mod mode;
+pub mod subscriptions;
+pub mod actuators;
+pub mod effects;
+pub mod fault_injection;
@@ -0,0 +1,83 @@
+// 运行模式
+//
+// - Normal   正常营业
+// - Service  维护：机门打开期间（操作员在机台旁）
+// - Debug    调试：台架固件启动时设定，一直有效
+//
+// 2005 故障注入只在维护 / 调试模式下允许；回到正常模式时清除全部模拟故障，
+// 避免台架演练的故障遗留到营业中
+
+use crate::app::fault_registry;
+use core::cell::Cell;
+use defmt::{info, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+/// 运行模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum OperatingMode {
+    Normal,
+    Service,
+    Debug,
+}
+
+#[derive(Clone, Copy)]
+struct Modes {
+    debug: bool,
+    service: bool,
+}
+
+impl Modes {
+    fn resolve(self) -> OperatingMode {
+        if self.debug {
+            OperatingMode::Debug
+        } else if self.service {
+            OperatingMode::Service
+        } else {
+            OperatingMode::Normal
+        }
+    }
+}
+
+static MODES: Mutex<CriticalSectionRawMutex, Cell<Modes>> = Mutex::new(Cell::new(Modes {
+    debug: false,
+    service: false,
+}));
+
+/// 当前运行模式
+pub fn current() -> OperatingMode {
+    MODES.lock(|m| m.get()).resolve()
+}
+
+/// 是否允许故障注入
+pub fn allows_fault_injection() -> bool {
+    current() != OperatingMode::Normal
+}
+
+/// 启用 / 关闭调试模式（启动时设定）
+pub fn set_debug(enabled: bool) {
+    set(|m| m.debug = enabled);
+}
+
+/// 进入 / 退出维护模式（机门开关）
+pub fn set_service(enabled: bool) {
+    set(|m| m.service = enabled);
+}
+
+fn set(f: impl FnOnce(&mut Modes)) {
+    let (before, after) = MODES.lock(|cell| {
+        let mut modes = cell.get();
+        let before = modes.resolve();
+        f(&mut modes);
+        cell.set(modes);
+        (before, modes.resolve())
+    });
+    if before == after {
+        return;
+    }
+
+    info!("Operating mode: {:?} -> {:?}", before, after);
+    if after == OperatingMode::Normal {
+        fault_registry::clear_simulated();
+    }
+}
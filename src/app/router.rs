@@ -1,11 +1,46 @@
 // 事件路由器
-use crate::app::handlers;
+//
+// 分发任务：网络命令、门开关 / 倾斜、马达、故障（`route_event`）
+// 上行任务：按钮、投币 / 回币 / 退币、心跳上行（`route_uplink`）
+use crate::app::fault_registry::FaultKey;
+use crate::app::{handlers, state};
 use crate::error::Result;
-use crate::event::Event;
+use crate::event::{Event, EventEnvelope};
+use crate::event_bus::{EventKind, KindFilter};
 use defmt::info;
 
+/// 分发任务订阅的事件种类
+pub const DISPATCH_KINDS: KindFilter = KindFilter::only(EventKind::Network)
+    .with(EventKind::Input)
+    .with(EventKind::Motor)
+    .with(EventKind::Fault);
+
+/// 上行任务订阅的事件种类
+pub const UPLINK_KINDS: KindFilter = KindFilter::only(EventKind::Button)
+    .with(EventKind::Coin)
+    .with(EventKind::Payout)
+    .with(EventKind::CoinOut)
+    .with(EventKind::Heartbeat);
+
 /// 路由事件到对应的处理器
-pub fn route_event(event: Event) -> Result<()> {
+///
+/// 处理期间 `event::current_meta()` 返回该事件的信封元数据
+pub fn route_event(envelope: EventEnvelope) -> Result<()> {
+    envelope.dispatch(route)
+}
+
+/// 路由事件到上行处理器
+///
+/// 资金事件先等状态任务落账，按其累计值上行
+pub async fn route_uplink(envelope: EventEnvelope) -> Result<()> {
+    let total = match envelope.event.kind() {
+        EventKind::Coin | EventKind::Payout | EventKind::CoinOut => state::next_total().await,
+        _ => 0,
+    };
+    envelope.dispatch(|event| uplink(event, total))
+}
+
+fn uplink(event: Event, total: u64) -> Result<()> {
     match event {
         Event::ButtonPress {
             button_id,
@@ -15,9 +50,34 @@ pub fn route_event(event: Event) -> Result<()> {
             handlers::button::on_button_press(button_id, duration_ms)
         }
 
+        Event::ButtonRelease { button_id, held_ms } => {
+            info!("Routing button release: id={}, held={}ms", button_id, held_ms);
+            handlers::button::on_button_release(button_id, held_ms)
+        }
+
+        Event::ButtonLongPress { button_id, held_ms } => {
+            info!("Routing button long press: id={}, held={}ms", button_id, held_ms);
+            handlers::button::on_button_long_press(button_id, held_ms)
+        }
+
+        Event::ButtonDoubleClick { button_id } => {
+            info!("Routing button double click: id={}", button_id);
+            handlers::button::on_button_double_click(button_id)
+        }
+
         Event::CoinInsert { channel_id, value } => {
             info!("Routing coin event: channel={}, value={}", channel_id, value);
-            handlers::coin::on_coin_insert(channel_id, value)
+            handlers::coin::on_coin_insert(channel_id, value, total)
+        }
+
+        Event::PayoutCount { delta } => {
+            info!("Routing payout event: delta={}", delta);
+            handlers::coin::on_payout(delta, total)
+        }
+
+        Event::CoinOut { count } => {
+            info!("Routing coin out event: count={}", count);
+            handlers::coin::on_coin_out(count, total)
         }
 
         Event::HeartbeatTick => {
@@ -25,6 +85,22 @@ pub fn route_event(event: Event) -> Result<()> {
             handlers::heartbeat::on_heartbeat()
         }
 
+        _ => Ok(()),
+    }
+}
+
+fn route(event: Event) -> Result<()> {
+    match event {
+        Event::DoorChanged { open } => {
+            info!("Routing door event: open={}", open);
+            handlers::input::on_door_changed(open)
+        }
+
+        Event::TiltChanged { active } => {
+            info!("Routing tilt event: active={}", active);
+            handlers::input::on_tilt_changed(active)
+        }
+
         Event::NetworkIncoming { cmd, payload } => {
             info!("Routing network event: cmd={:04X}", cmd);
             handlers::network::on_network_message(cmd, payload)
@@ -37,10 +113,33 @@ pub fn route_event(event: Event) -> Result<()> {
 
         Event::FaultDetected {
             hardware_type,
+            hardware_id,
+            fault,
             severity,
+            fault_code,
+            message,
+            simulated,
+            duration_ms,
         } => {
-            info!("Routing fault event: hw_type={}", hardware_type);
-            handlers::fault::on_fault_detected(hardware_type, severity)
+            info!("Routing fault event: hw_type={}, simulated={}", hardware_type, simulated);
+            let key = FaultKey {
+                hardware_type,
+                hardware_id,
+                fault,
+            };
+            if simulated {
+                handlers::fault::on_simulated_fault(
+                    key,
+                    severity,
+                    fault_code,
+                    message.as_deref(),
+                    duration_ms,
+                )
+            } else {
+                handlers::fault::on_fault_detected(key, severity, fault_code, message.as_deref())
+            }
         }
+
+        _ => Ok(()),
     }
 }
@@ -0,0 +1,274 @@
+// 故障安全策略
+//
+// 按故障等级决定动作（SafetyConfig 可配置，默认）：
+//   INFO / WARN  只上报
+//   ERROR        出故障的是马达时停止并禁用该马达，其它硬件只上报
+//   FATAL        停止全部马达、禁止投币（事件日志在记录 FATAL 故障时自行冻结）
+//
+// 禁止投币通过投币器的禁止线（drivers::coin_acceptor::CoinInhibit，启动时注册）执行，
+// 当前状态见 `coins_inhibited()`，随心跳（1001 coin_inhibited）上报
+//
+// 限制状态不单独累计，每次故障登记 / 清除后都由当前全部故障重新推导：
+// 新出现的限制立即执行，消失的限制执行恢复动作（解除禁用、允许投币；
+// 马达不会自动重新启动，需上位机再次下发命令）。
+// 每个决策写入事件日志（JOURNAL_EVENT_KIND_SAFETY），并随 1006 上报
+
+use crate::app::actuators;
+use crate::app::fault_registry::{self, FaultEntry};
+use crate::app::journal;
+use crate::app::state::{self, MOTOR_COUNT, MOTOR_TYPES};
+use crate::drivers::actuator::MotorCommand;
+use crate::drivers::coin_acceptor::CoinInhibit;
+use crate::event::coinpusher::v1::{
+    FaultSeverity, HardwareType, JournalEventKind, MotorCommandType, MotorType, SafetyAction,
+};
+use core::cell::{Cell, RefCell};
+use defmt::{info, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+/// 各故障等级对应的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyConfig {
+    pub info: SafetyAction,
+    pub warn: SafetyAction,
+    pub error: SafetyAction,
+    pub fatal: SafetyAction,
+}
+
+impl SafetyConfig {
+    const DEFAULT: Self = Self {
+        info: SafetyAction::Notify,
+        warn: SafetyAction::Notify,
+        error: SafetyAction::DisableMotor,
+        fatal: SafetyAction::Shutdown,
+    };
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// 当前生效的限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Format)]
+pub struct Restrictions {
+    /// 全部马达停止、禁止投币
+    pub shutdown: bool,
+    /// 被禁用的马达（按 MOTOR_TYPES 索引）
+    pub disabled_motors: [bool; MOTOR_COUNT],
+}
+
+impl Restrictions {
+    const NONE: Self = Self {
+        shutdown: false,
+        disabled_motors: [false; MOTOR_COUNT],
+    };
+}
+
+static CONFIG: Mutex<CriticalSectionRawMutex, Cell<SafetyConfig>> =
+    Mutex::new(Cell::new(SafetyConfig::DEFAULT));
+
+static RESTRICTIONS: Mutex<CriticalSectionRawMutex, Cell<Restrictions>> =
+    Mutex::new(Cell::new(Restrictions::NONE));
+
+/// 投币器禁止线（启动时注册，未注册时只记录状态）
+static COIN_INHIBIT: Mutex<CriticalSectionRawMutex, RefCell<Option<&'static mut (dyn CoinInhibit + Send)>>> =
+    Mutex::new(RefCell::new(None));
+
+/// 禁止线当前是否有效
+static COINS_INHIBITED: Mutex<CriticalSectionRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// 设置策略（启动时调用；之后的故障按新策略处理）
+pub fn configure(config: SafetyConfig) {
+    info!(
+        "Safety policy: info={} warn={} error={} fatal={}",
+        config.info as i32,
+        config.warn as i32,
+        config.error as i32,
+        config.fatal as i32
+    );
+    CONFIG.lock(|c| c.set(config));
+}
+
+/// 注册投币器禁止线，并按当前限制设置一次
+pub fn set_coin_inhibit(driver: &'static mut (dyn CoinInhibit + Send)) {
+    COIN_INHIBIT.lock(|d| *d.borrow_mut() = Some(driver));
+    apply_coin_inhibit(restrictions().shutdown);
+}
+
+/// 投币器禁止线是否有效
+pub fn coins_inhibited() -> bool {
+    COINS_INHIBITED.lock(|i| i.get())
+}
+
+fn apply_coin_inhibit(inhibited: bool) {
+    COIN_INHIBIT.lock(|d| {
+        if let Some(driver) = d.borrow_mut().as_mut() {
+            driver.set_inhibited(inhibited);
+        }
+    });
+    COINS_INHIBITED.lock(|i| i.set(inhibited));
+}
+
+/// 当前生效的限制
+pub fn restrictions() -> Restrictions {
+    RESTRICTIONS.lock(|r| r.get())
+}
+
+/// 是否允许投币
+pub fn coins_accepted() -> bool {
+    !restrictions().shutdown
+}
+
+/// 是否允许执行该马达命令（STOP 总是允许）
+pub fn motor_allowed(motor_type: i32, command: i32) -> bool {
+    if command == MotorCommandType::MotorCmdStop as i32 {
+        return true;
+    }
+    let r = restrictions();
+    let disabled = state::motor_index(motor_type).is_some_and(|idx| r.disabled_motors[idx]);
+    !r.shutdown && !disabled
+}
+
+/// 故障所在的马达（硬件类型 -> 马达索引）
+fn motor_of(hardware_type: i32) -> Option<usize> {
+    let motor_type = match HardwareType::try_from(hardware_type).ok()? {
+        HardwareType::HwMotorPusher => MotorType::Pusher,
+        HardwareType::HwMotorFeed => MotorType::Feed,
+        HardwareType::HwMotorPayout => MotorType::Payout,
+        HardwareType::HwMotorRefund => MotorType::Refund,
+        HardwareType::HwTicketMachine => MotorType::Ticket,
+        _ => return None,
+    };
+    state::motor_index(motor_type as i32)
+}
+
+/// 某条故障对应的动作（DISABLE_MOTOR 用于非马达硬件时降为只上报）
+pub fn action_for(entry: &FaultEntry) -> SafetyAction {
+    let config = CONFIG.lock(|c| c.get());
+    let action = match FaultSeverity::try_from(entry.severity) {
+        Ok(FaultSeverity::Fatal) => config.fatal,
+        Ok(FaultSeverity::Error) => config.error,
+        Ok(FaultSeverity::Warn) => config.warn,
+        _ => config.info,
+    };
+    match action {
+        SafetyAction::DisableMotor if motor_of(entry.key.hardware_type).is_none() => SafetyAction::Notify,
+        action => action,
+    }
+}
+
+/// 恢复动作（故障清除时上报）
+pub fn recovery_for(entry: &FaultEntry) -> Option<SafetyAction> {
+    match action_for(entry) {
+        SafetyAction::DisableMotor => Some(SafetyAction::EnableMotor),
+        SafetyAction::Shutdown => Some(SafetyAction::Resume),
+        _ => None,
+    }
+}
+
+/// 由当前全部故障推导限制
+fn derive(faults: &[FaultEntry]) -> Restrictions {
+    let mut r = Restrictions::NONE;
+    for entry in faults {
+        match action_for(entry) {
+            SafetyAction::Shutdown => r.shutdown = true,
+            SafetyAction::DisableMotor => {
+                if let Some(idx) = motor_of(entry.key.hardware_type) {
+                    r.disabled_motors[idx] = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    r
+}
+
+fn log_decision(action: SafetyAction, hardware_type: Option<i32>) {
+    journal::record_decision(
+        JournalEventKind::Safety,
+        Some(action as u32),
+        hardware_type.map(|hw| hw as u32),
+    );
+}
+
+/// 故障登记（新故障 / 等级变化）后调用，返回采取的动作
+pub fn on_raised(entry: &FaultEntry) -> SafetyAction {
+    let action = action_for(entry);
+    crate::log_warn!(
+        "Safety: {:?} (hw_type {}, severity {})",
+        action,
+        entry.key.hardware_type,
+        entry.severity
+    );
+    log_decision(action, Some(entry.key.hardware_type));
+    reconcile();
+    action
+}
+
+/// 故障清除后调用，执行解除的限制对应的恢复动作
+pub fn on_cleared() {
+    reconcile();
+}
+
+/// 比较新旧限制，执行新增限制并恢复已解除的限制
+fn reconcile() {
+    let next = derive(&fault_registry::active());
+    let prev = RESTRICTIONS.lock(|r| r.replace(next));
+    if prev == next {
+        return;
+    }
+
+    if next.shutdown && !prev.shutdown {
+        crate::log_warn!("Safety: shutdown, all motors stopped, coins inhibited");
+        apply_coin_inhibit(true);
+        for idx in 0..MOTOR_COUNT {
+            stop_motor(idx);
+        }
+    } else if prev.shutdown && !next.shutdown {
+        info!("Safety: shutdown lifted, coins accepted");
+        apply_coin_inhibit(false);
+        log_decision(SafetyAction::Resume, None);
+    }
+
+    for (idx, motor_type) in MOTOR_TYPES.iter().enumerate() {
+        match (prev.disabled_motors[idx], next.disabled_motors[idx]) {
+            (false, true) => {
+                crate::log_warn!("Safety: motor {} disabled", *motor_type as i32);
+                stop_motor(idx);
+            }
+            (true, false) => {
+                info!("Safety: motor {} enabled", *motor_type as i32);
+                log_decision(SafetyAction::EnableMotor, Some(hardware_of(*motor_type) as i32));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 经执行器队列停止马达；队列已满时直接写入状态，保证停机不丢失
+fn stop_motor(idx: usize) {
+    let cmd = MotorCommand {
+        motor_type: MOTOR_TYPES[idx] as i32,
+        command: MotorCommandType::MotorCmdStop as i32,
+        duration_ms: None,
+        count: None,
+        speed_level: None,
+    };
+    if actuators::motor(cmd).is_err() {
+        state::apply_motor_command(cmd.motor_type, cmd.command, None, None, None);
+    }
+}
+
+fn hardware_of(motor_type: MotorType) -> HardwareType {
+    match motor_type {
+        MotorType::Pusher => HardwareType::HwMotorPusher,
+        MotorType::Feed => HardwareType::HwMotorFeed,
+        MotorType::Payout => HardwareType::HwMotorPayout,
+        MotorType::Refund => HardwareType::HwMotorRefund,
+        MotorType::Ticket => HardwareType::HwTicketMachine,
+        MotorType::Unknown => HardwareType::HwUnknown,
+    }
+}
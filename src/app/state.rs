@@ -0,0 +1,602 @@
+// 机器状态（输入、灯光、马达、故障、计数）
+//
+// 由 2002/2003 等命令处理器写入，HTTP 状态页等只读方查询。
+// 按钮、门开关 / 倾斜与投币计数由状态任务（事件总线订阅者）按事件写入，见 `on_event`。
+//
+// 版本：每次 `update` 后与修改前比较，有变化则 state_version + 1，
+// 并把变化字段的“最后修改版本”记为新版本。任何消费方只要记住自己上次看到的版本，
+// 就能用 `report_since(v)` 得到期间全部变化（多次修改自然合并），无需各自维护差量
+//
+// RUN_TIME 到期由 motor_timer_task 写回 running = false（版本递增，订阅方收到 1002），
+// 到期前后的短暂间隔内 `MotorSlot::is_running` 同样视为停止
+
+use crate::event::coinpusher::v1::{
+    BoolFlag, ButtonState, ChangeField, CounterState, FaultState, InputState, LightState, M1002Toc,
+    MotorCommandType, MotorStatus, MotorType,
+};
+use crate::event::Event;
+use crate::event_bus::{EventKind, KindFilter, EVENT_BUS_CAPACITY};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_futures::select::select;
+use embassy_time::{Duration, Instant, Timer};
+
+/// 按钮数量（ID 0~15）
+pub const BUTTON_COUNT: usize = 16;
+
+/// 灯光数量（ID 1~15，0 保留）
+pub const LIGHT_COUNT: usize = 16;
+
+/// 马达数量（MotorType::Pusher ~ MotorType::Ticket）
+pub const MOTOR_COUNT: usize = 5;
+
+/// 全部马达类型（按索引顺序）
+pub const MOTOR_TYPES: [MotorType; MOTOR_COUNT] = [
+    MotorType::Pusher,
+    MotorType::Feed,
+    MotorType::Payout,
+    MotorType::Refund,
+    MotorType::Ticket,
+];
+
+/// 单个灯光状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LightSlot {
+    pub on: bool,
+    pub pattern: u32,
+}
+
+/// 单个马达状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MotorSlot {
+    pub running: bool,
+    /// RUN_TIME 模式的截止时间
+    pub run_until: Option<Instant>,
+    /// RUN_COUNT 模式剩余数量
+    pub remaining_count: Option<u32>,
+    pub speed_level: u32,
+}
+
+impl MotorSlot {
+    /// 当前是否运行（RUN_TIME 到期视为停止）
+    pub fn is_running(&self, now: Instant) -> bool {
+        match self.run_until {
+            Some(until) => self.running && now < until,
+            None => self.running,
+        }
+    }
+
+    /// RUN_TIME 模式剩余时间
+    pub fn remaining_ms(&self, now: Instant) -> Option<u32> {
+        self.run_until
+            .map(|until| until.saturating_duration_since(now).as_millis() as u32)
+    }
+}
+
+/// 计数器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// 累计投币数
+    pub coins_in: u64,
+    /// 累计回币数
+    pub payout: u64,
+    /// 累计退币数
+    pub coins_out: u64,
+}
+
+/// 故障概况
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Faults {
+    /// 当前存在的故障数量
+    pub active_count: u32,
+    /// 最高故障等级（FaultSeverity 数值，0=无故障）
+    pub max_severity: i32,
+}
+
+/// 输入状态（按钮、门开关）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inputs {
+    /// 按钮当前是否按下
+    pub buttons: [bool; BUTTON_COUNT],
+    /// 门是否打开
+    pub door_open: bool,
+    /// 倾斜 / 震动传感器是否触发
+    pub tilt: bool,
+}
+
+/// 机器状态
+#[derive(Debug, Clone, Copy)]
+pub struct MachineState {
+    pub inputs: Inputs,
+    pub lights: [LightSlot; LIGHT_COUNT],
+    pub motors: [MotorSlot; MOTOR_COUNT],
+    pub faults: Faults,
+    pub counters: Counters,
+}
+
+impl MachineState {
+    pub const fn new() -> Self {
+        Self {
+            inputs: Inputs {
+                buttons: [false; BUTTON_COUNT],
+                door_open: false,
+                tilt: false,
+            },
+            lights: [LightSlot { on: false, pattern: 0 }; LIGHT_COUNT],
+            motors: [MotorSlot {
+                running: false,
+                run_until: None,
+                remaining_count: None,
+                speed_level: 0,
+            }; MOTOR_COUNT],
+            faults: Faults {
+                active_count: 0,
+                max_severity: 0,
+            },
+            counters: Counters {
+                coins_in: 0,
+                payout: 0,
+                coins_out: 0,
+            },
+        }
+    }
+}
+
+impl Default for MachineState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// change_mask 位（与 m_1002 定义一致）
+pub const CHANGE_BUTTONS: u64 = 1 << 0;
+pub const CHANGE_LIGHTS: u64 = 1 << 1;
+pub const CHANGE_MOTORS: u64 = 1 << 2;
+pub const CHANGE_FAULTS: u64 = 1 << 3;
+pub const CHANGE_COUNTERS: u64 = 1 << 4;
+pub const CHANGE_INPUTS: u64 = 1 << 5;
+
+/// 各字段最后一次变化时的版本
+#[derive(Clone, Copy)]
+struct Versions {
+    buttons: [u64; BUTTON_COUNT],
+    door_open: u64,
+    tilt: u64,
+    lights: [u64; LIGHT_COUNT],
+    motors: [u64; MOTOR_COUNT],
+    faults: u64,
+    coins_in: u64,
+    payout: u64,
+    coins_out: u64,
+}
+
+struct Store {
+    state: MachineState,
+    version: u64,
+    versions: Versions,
+}
+
+impl Store {
+    /// 比较修改前后，有变化则递增版本
+    fn record_changes(&mut self, before: &MachineState) {
+        let next = self.version + 1;
+        let after = &self.state;
+        let v = &mut self.versions;
+        let mut changed = false;
+        let mut mark = |slot: &mut u64, differs: bool| {
+            if differs {
+                *slot = next;
+                changed = true;
+            }
+        };
+
+        for i in 0..BUTTON_COUNT {
+            mark(&mut v.buttons[i], before.inputs.buttons[i] != after.inputs.buttons[i]);
+        }
+        mark(&mut v.door_open, before.inputs.door_open != after.inputs.door_open);
+        mark(&mut v.tilt, before.inputs.tilt != after.inputs.tilt);
+        for i in 0..LIGHT_COUNT {
+            mark(&mut v.lights[i], before.lights[i] != after.lights[i]);
+        }
+        for i in 0..MOTOR_COUNT {
+            mark(&mut v.motors[i], before.motors[i] != after.motors[i]);
+        }
+        mark(&mut v.faults, before.faults != after.faults);
+        mark(&mut v.coins_in, before.counters.coins_in != after.counters.coins_in);
+        mark(&mut v.payout, before.counters.payout != after.counters.payout);
+        mark(&mut v.coins_out, before.counters.coins_out != after.counters.coins_out);
+
+        if changed {
+            self.version = next;
+        }
+    }
+}
+
+static STATE: Mutex<CriticalSectionRawMutex, RefCell<Store>> = Mutex::new(RefCell::new(Store {
+    state: MachineState::new(),
+    version: 0,
+    versions: Versions {
+        buttons: [0; BUTTON_COUNT],
+        door_open: 0,
+        tilt: 0,
+        lights: [0; LIGHT_COUNT],
+        motors: [0; MOTOR_COUNT],
+        faults: 0,
+        coins_in: 0,
+        payout: 0,
+        coins_out: 0,
+    },
+}));
+
+/// 版本变化通知（状态订阅推送任务等待）
+static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// RUN_TIME 截止时间有变化，唤醒 motor_timer_task
+static DEADLINE_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// 状态任务订阅的事件种类
+pub const STATE_KINDS: KindFilter = KindFilter::only(EventKind::Button)
+    .with(EventKind::Input)
+    .with(EventKind::Coin)
+    .with(EventKind::Payout)
+    .with(EventKind::CoinOut);
+
+/// 资金事件（投币 / 回币 / 退币）落账后的累计值，按总线顺序交给上行任务
+///
+/// 两者都是总线订阅者，状态任务最多领先上行任务 EVENT_BUS_CAPACITY 条事件，队列不会满
+static TOTALS: Channel<CriticalSectionRawMutex, u64, EVENT_BUS_CAPACITY> = Channel::new();
+
+/// 读取状态快照
+pub fn snapshot() -> MachineState {
+    STATE.lock(|s| s.borrow().state)
+}
+
+/// 当前状态版本（开机为 0，每次变化 +1）
+pub fn version() -> u64 {
+    STATE.lock(|s| s.borrow().version)
+}
+
+/// 修改状态（有实际变化时递增版本）
+pub fn update<R>(f: impl FnOnce(&mut MachineState) -> R) -> R {
+    let (result, changed) = STATE.lock(|s| {
+        let mut store = s.borrow_mut();
+        let before = store.state;
+        let version = store.version;
+        let result = f(&mut store.state);
+        store.record_changes(&before);
+        (result, store.version != version)
+    });
+    if changed {
+        CHANGED.signal(());
+    }
+    result
+}
+
+/// 等待下一次状态变化（只供单个等待方使用）
+pub async fn wait_changed() {
+    CHANGED.wait().await
+}
+
+/// 全量状态报告（1002，is_full_snapshot = 1）
+pub fn full_report() -> M1002Toc {
+    STATE.lock(|s| build_report(&s.borrow(), None))
+}
+
+/// 自 `since` 版本以来的增量报告（无变化返回 None）
+pub fn report_since(since: u64) -> Option<M1002Toc> {
+    STATE.lock(|s| {
+        let store = s.borrow();
+        (store.version > since).then(|| build_report(&store, Some(since)))
+    })
+}
+
+fn flag(value: bool) -> i32 {
+    if value {
+        BoolFlag::BoolTrue as i32
+    } else {
+        BoolFlag::BoolFalse as i32
+    }
+}
+
+/// 生成 1002 报告；`since` 为 None 时全量
+fn build_report(store: &Store, since: Option<u64>) -> M1002Toc {
+    let state = &store.state;
+    let v = &store.versions;
+    let now = Instant::now();
+    let include = |version: u64| since.is_none_or(|since| version > since);
+
+    let mut report = M1002Toc {
+        is_full_snapshot: flag(since.is_none()),
+        state_version: store.version,
+        ..Default::default()
+    };
+    let mut mask = 0u64;
+    let mut fields: Vec<String> = Vec::new();
+
+    for (id, pressed) in state.inputs.buttons.iter().enumerate() {
+        if include(v.buttons[id]) {
+            report.buttons.push(ButtonState {
+                button_id: id as u32,
+                pressed: flag(*pressed),
+            });
+            mask |= CHANGE_BUTTONS;
+            fields.push(format!("buttons[{}]", id));
+        }
+    }
+
+    let door_changed = include(v.door_open);
+    let tilt_changed = include(v.tilt);
+    if door_changed || tilt_changed {
+        report.inputs = Some(InputState {
+            door_open: flag(state.inputs.door_open),
+            tilt: flag(state.inputs.tilt),
+        });
+        mask |= CHANGE_INPUTS;
+        if door_changed {
+            fields.push("door_open".into());
+        }
+        if tilt_changed {
+            fields.push("tilt".into());
+        }
+    }
+
+    // 灯光 ID 0 保留
+    for id in 1..LIGHT_COUNT {
+        if include(v.lights[id]) {
+            let light = state.lights[id];
+            report.lights.push(LightState {
+                light_id: id as u32,
+                on: flag(light.on),
+                pattern: Some(light.pattern),
+            });
+            mask |= CHANGE_LIGHTS;
+            fields.push(format!("lights[{}]", id));
+        }
+    }
+
+    for (idx, motor_type) in MOTOR_TYPES.iter().enumerate() {
+        if include(v.motors[idx]) {
+            let motor = state.motors[idx];
+            report.motors.push(MotorStatus {
+                motor_type: *motor_type as i32,
+                running: flag(motor.is_running(now)),
+                remaining_ms: motor.remaining_ms(now),
+                remaining_count: motor.remaining_count,
+                speed_level: Some(motor.speed_level),
+            });
+            mask |= CHANGE_MOTORS;
+            fields.push(format!("motors[{}]", *motor_type as i32));
+        }
+    }
+
+    if include(v.faults) {
+        report.faults = Some(FaultState {
+            active_count: state.faults.active_count,
+            max_severity: (state.faults.max_severity > 0).then_some(state.faults.max_severity),
+        });
+        mask |= CHANGE_FAULTS;
+        fields.push("faults".into());
+    }
+
+    let counters = [
+        (v.coins_in, "counters.coins_in"),
+        (v.payout, "counters.payout"),
+        (v.coins_out, "counters.coins_out"),
+    ];
+    if counters.iter().any(|(version, _)| include(*version)) {
+        report.counters = Some(CounterState {
+            coins_in: state.counters.coins_in,
+            payout: state.counters.payout,
+            coins_out: state.counters.coins_out,
+        });
+        mask |= CHANGE_COUNTERS;
+        fields.extend(
+            counters
+                .iter()
+                .filter(|(version, _)| include(*version))
+                .map(|(_, name)| String::from(*name)),
+        );
+    }
+
+    // 全量报告不携带变更信息
+    if since.is_some() {
+        report.change_mask = Some(mask);
+        report.changed_fields = fields;
+        report.changed_categories = [
+            (CHANGE_BUTTONS, ChangeField::Buttons),
+            (CHANGE_LIGHTS, ChangeField::Lights),
+            (CHANGE_MOTORS, ChangeField::Motors),
+            (CHANGE_FAULTS, ChangeField::Faults),
+            (CHANGE_COUNTERS, ChangeField::Counter),
+            (CHANGE_INPUTS, ChangeField::Inputs),
+        ]
+        .iter()
+        .filter(|(bit, _)| mask & bit != 0)
+        .map(|(_, field)| *field as i32)
+        .collect();
+    }
+
+    report
+}
+
+/// 按事件更新状态（状态任务调用）
+pub fn on_event(event: &Event) {
+    match *event {
+        Event::ButtonPress { button_id, .. } => set_pressed(button_id, true),
+        Event::ButtonRelease { button_id, .. } => set_pressed(button_id, false),
+        Event::DoorChanged { open } => update(|s| s.inputs.door_open = open),
+        Event::TiltChanged { active } => update(|s| s.inputs.tilt = active),
+        Event::CoinInsert { channel_id, .. } => {
+            // 停机期间投币器禁止线有效；仍然进来的币（禁止生效前已在通道中）照常计数，避免账目丢失
+            if crate::app::safety::coins_inhibited() {
+                crate::log_warn!("Coin accepted while inhibited (channel: {})", channel_id);
+            }
+            record_total(update(|s| {
+                s.counters.coins_in += 1;
+                s.counters.coins_in
+            }));
+        }
+        Event::PayoutCount { delta } => record_total(update(|s| {
+            s.counters.payout += delta as u64;
+            s.counters.payout
+        })),
+        Event::CoinOut { count } => record_total(count_coins_out(count)),
+        _ => {}
+    }
+}
+
+/// 下一条资金事件落账后的累计值（上行任务按总线顺序逐条取）
+pub async fn next_total() -> u64 {
+    TOTALS.receive().await
+}
+
+fn record_total(total: u64) {
+    if TOTALS.try_send(total).is_err() {
+        crate::log_warn!("State: totals queue full, total {} not reported", total);
+    }
+}
+
+/// 记录按钮当前状态
+fn set_pressed(button_id: u32, pressed: bool) {
+    let idx = button_id as usize;
+    if idx < BUTTON_COUNT {
+        update(|s| s.inputs.buttons[idx] = pressed);
+    }
+}
+
+/// 累计退币数；退币马达以 RUN_COUNT 模式运行时按出币数递减剩余数量，到 0 停止
+fn count_coins_out(count: u32) -> u64 {
+    let refund = motor_index(MotorType::Refund as i32);
+    update(|s| {
+        s.counters.coins_out += count as u64;
+        if let Some(motor) = refund.map(|idx| &mut s.motors[idx])
+            && let Some(remaining) = motor.remaining_count
+        {
+            let remaining = remaining.saturating_sub(count);
+            motor.remaining_count = Some(remaining);
+            if remaining == 0 {
+                motor.running = false;
+                motor.remaining_count = None;
+            }
+        }
+        s.counters.coins_out
+    })
+}
+
+/// 马达类型 -> 状态索引
+pub fn motor_index(motor_type: i32) -> Option<usize> {
+    MOTOR_TYPES.iter().position(|t| *t as i32 == motor_type)
+}
+
+/// 灯光 ID 是否有效（1 ~ LIGHT_COUNT-1）
+pub fn light_valid(light_id: u32) -> bool {
+    (1..LIGHT_COUNT).contains(&(light_id as usize))
+}
+
+/// 设置灯光
+pub fn set_light(light_id: u32, on: bool, pattern: u32) -> bool {
+    if !light_valid(light_id) {
+        return false;
+    }
+    update(|s| s.lights[light_id as usize] = LightSlot { on, pattern });
+    true
+}
+
+/// 设置马达速度等级（不改变运行状态）
+pub fn set_motor_speed(motor_type: i32, speed_level: u32) -> bool {
+    let Some(idx) = motor_index(motor_type) else {
+        return false;
+    };
+    update(|s| s.motors[idx].speed_level = speed_level);
+    true
+}
+
+/// 马达命令对应的运行方式 (running, run_until, remaining_count)；参数不合法返回 None
+fn plan_motor(
+    command: i32,
+    duration_ms: Option<u32>,
+    count: Option<u32>,
+) -> Option<(bool, Option<Instant>, Option<u32>)> {
+    match MotorCommandType::try_from(command).ok()? {
+        MotorCommandType::MotorCmdStart => Some((true, None, None)),
+        MotorCommandType::MotorCmdStop => Some((false, None, None)),
+        MotorCommandType::MotorCmdRunTime => {
+            let ms = duration_ms?;
+            Some((true, Some(Instant::now() + Duration::from_millis(ms as u64)), None))
+        }
+        MotorCommandType::MotorCmdRunCount => Some((true, None, Some(count?))),
+        MotorCommandType::MotorCmdUnknown => None,
+    }
+}
+
+/// 马达命令是否合法（不修改状态）
+pub fn motor_command_valid(motor_type: i32, command: i32, duration_ms: Option<u32>, count: Option<u32>) -> bool {
+    motor_index(motor_type).is_some() && plan_motor(command, duration_ms, count).is_some()
+}
+
+/// 执行马达命令（命令不合法时不修改状态）
+pub fn apply_motor_command(
+    motor_type: i32,
+    command: i32,
+    duration_ms: Option<u32>,
+    count: Option<u32>,
+    speed_level: Option<u32>,
+) -> bool {
+    let Some(idx) = motor_index(motor_type) else {
+        return false;
+    };
+    // 先校验命令参数，合法后再一并写入速度和运行方式
+    let Some((running, run_until, remaining_count)) = plan_motor(command, duration_ms, count) else {
+        return false;
+    };
+
+    update(|s| {
+        let motor = &mut s.motors[idx];
+        *motor = MotorSlot {
+            running,
+            run_until,
+            remaining_count,
+            speed_level: speed_level.unwrap_or(motor.speed_level),
+        };
+    });
+    DEADLINE_CHANGED.signal(());
+    true
+}
+
+/// 停止已到 RUN_TIME 截止时间的马达
+fn expire_motors(now: Instant) {
+    update(|s| {
+        for motor in s.motors.iter_mut() {
+            if motor.run_until.is_some_and(|until| until <= now) {
+                motor.running = false;
+                motor.run_until = None;
+            }
+        }
+    });
+}
+
+/// 最早的 RUN_TIME 截止时间
+fn next_deadline() -> Option<Instant> {
+    STATE.lock(|s| s.borrow().state.motors.iter().filter_map(|m| m.run_until).min())
+}
+
+/// RUN_TIME 到期循环：等到最早的截止时间（或截止时间变化）后停止到期的马达
+pub async fn run_motor_timers() -> ! {
+    loop {
+        expire_motors(Instant::now());
+
+        let timer = async {
+            match next_deadline() {
+                Some(at) => Timer::at(at).await,
+                None => core::future::pending().await,
+            }
+        };
+        select(DEADLINE_CHANGED.wait(), timer).await;
+    }
+}
@@ -0,0 +1,210 @@
+// 状态订阅（2001 -> 1002）
+//
+// 每条连接最多一个订阅，按 ConnectionId 区分：
+// - full（缺省 1）：立即回一份全量报告；full=2 时回自上次推送以来的增量
+//   （该连接没有订阅时无从比较，仍回全量）
+// - subscribe_changes=1：建立 / 替换订阅，之后状态变化时推送增量 1002；
+//   full 与单次请求同样缺省为 1，先推一份全量作为基线；full=2 时以当前版本为基线，
+//   不推全量（send_full_on_subscribe=1 时仍推）
+// - subscribe_changes=2：取消订阅
+// - min_interval_ms：两次推送的最小间隔，期间的多次变化合并为一次增量
+//
+// 连接断开（Connection 释放）时自动取消订阅
+
+use crate::app::state;
+use crate::event::coinpusher::v1::{BoolFlag, M1002Toc, M2001Tos};
+use crate::net::uplink::{self, ConnectionId};
+use core::cell::RefCell;
+use defmt::info;
+use embassy_futures::select::select3;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use heapless::Vec;
+
+/// 最大订阅数（同时在线的连接数，与上行订阅者一致）
+pub const MAX_SUBSCRIPTIONS: usize = uplink::UPLINK_SUBSCRIBERS;
+
+/// 单个订阅
+#[derive(Debug, Clone, Copy)]
+struct Subscription {
+    /// 订阅的连接（None = 不属于任何连接，广播推送）
+    conn: Option<ConnectionId>,
+    /// 推送最小间隔
+    min_interval: Duration,
+    /// 已推送到的版本
+    last_version: u64,
+    /// 上次推送时间
+    last_push: Option<Instant>,
+}
+
+impl Subscription {
+    /// 最早可推送的时间
+    fn due_at(&self) -> Option<Instant> {
+        self.last_push.map(|at| at + self.min_interval)
+    }
+}
+
+static SUBSCRIPTIONS: Mutex<CriticalSectionRawMutex, RefCell<Vec<Subscription, MAX_SUBSCRIPTIONS>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// 订阅表变化，唤醒推送任务重新计算
+static WAKE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+fn is_true(flag: Option<i32>) -> bool {
+    flag == Some(BoolFlag::BoolTrue as i32)
+}
+
+fn is_false(flag: Option<i32>) -> bool {
+    flag == Some(BoolFlag::BoolFalse as i32)
+}
+
+fn with_subscription<R>(
+    conn: Option<ConnectionId>,
+    f: impl FnOnce(Option<&mut Subscription>) -> R,
+) -> R {
+    SUBSCRIPTIONS.lock(|subs| {
+        let mut subs = subs.borrow_mut();
+        f(subs.iter_mut().find(|sub| sub.conn == conn))
+    })
+}
+
+/// 处理 2001 请求（`conn` 为发起连接，应答只发往该连接）
+pub fn request(conn: Option<ConnectionId>, req: &M2001Tos) -> crate::error::Result<()> {
+    if is_true(req.subscribe_changes) {
+        return subscribe(conn, req);
+    }
+    if is_false(req.subscribe_changes) {
+        remove(conn);
+    }
+
+    if is_false(req.full) {
+        send_incremental(conn);
+    } else {
+        send_full(conn);
+    }
+    Ok(())
+}
+
+/// 建立 / 替换订阅
+fn subscribe(conn: Option<ConnectionId>, req: &M2001Tos) -> crate::error::Result<()> {
+    let min_interval = Duration::from_millis(req.min_interval_ms.unwrap_or(0) as u64);
+    let full_first = is_true(req.send_full_on_subscribe) || !is_false(req.full);
+
+    let sub = Subscription {
+        conn,
+        min_interval,
+        last_version: state::version(),
+        last_push: None,
+    };
+    let inserted = SUBSCRIPTIONS.lock(|subs| {
+        let mut subs = subs.borrow_mut();
+        match subs.iter_mut().find(|s| s.conn == conn) {
+            Some(existing) => {
+                *existing = sub;
+                true
+            }
+            None => subs.push(sub).is_ok(),
+        }
+    });
+    if !inserted {
+        crate::log_warn!("Subscriptions: table full, {:?} rejected", conn);
+        return Err(crate::error::Error::BufferFull);
+    }
+
+    info!(
+        "Subscriptions: {:?} subscribed (min interval {} ms, full={})",
+        conn,
+        min_interval.as_millis(),
+        full_first
+    );
+    if full_first {
+        send_full(conn);
+    }
+    WAKE.signal(());
+    Ok(())
+}
+
+/// 取消订阅
+pub fn remove(conn: Option<ConnectionId>) {
+    let removed = SUBSCRIPTIONS.lock(|subs| {
+        let mut subs = subs.borrow_mut();
+        let before = subs.len();
+        subs.retain(|sub| sub.conn != conn);
+        subs.len() != before
+    });
+    if removed {
+        info!("Subscriptions: {:?} unsubscribed", conn);
+        WAKE.signal(());
+    }
+}
+
+/// 推送报告并记录到该连接的订阅
+fn push(conn: Option<ConnectionId>, report: &M1002Toc) {
+    let version = report.state_version;
+    with_subscription(conn, |sub| {
+        if let Some(sub) = sub {
+            sub.last_version = sub.last_version.max(version);
+            sub.last_push = Some(Instant::now());
+        }
+    });
+    uplink::publish_to(conn, 0x1002, report);
+}
+
+fn send_full(conn: Option<ConnectionId>) {
+    push(conn, &state::full_report());
+}
+
+/// 自该连接上次推送以来的增量；没有订阅时回全量
+fn send_incremental(conn: Option<ConnectionId>) {
+    let Some(since) = with_subscription(conn, |sub| sub.map(|sub| sub.last_version)) else {
+        send_full(conn);
+        return;
+    };
+
+    let report = state::report_since(since).unwrap_or_else(|| M1002Toc {
+        is_full_snapshot: BoolFlag::BoolFalse as i32,
+        state_version: state::version(),
+        change_mask: Some(0),
+        ..Default::default()
+    });
+    push(conn, &report);
+}
+
+/// 推送循环：状态变化后向到期的订阅推送增量，未到期的等到期后合并推送
+pub async fn run() -> ! {
+    loop {
+        let now = Instant::now();
+        let version = state::version();
+        let mut due: Vec<(Option<ConnectionId>, u64), MAX_SUBSCRIPTIONS> = Vec::new();
+        let mut next_due: Option<Instant> = None;
+
+        SUBSCRIPTIONS.lock(|subs| {
+            for sub in subs.borrow().iter().filter(|sub| version > sub.last_version) {
+                match sub.due_at() {
+                    Some(at) if at > now => {
+                        next_due = Some(next_due.map_or(at, |t| t.min(at)));
+                    }
+                    _ => {
+                        due.push((sub.conn, sub.last_version)).ok();
+                    }
+                }
+            }
+        });
+
+        for (conn, since) in due {
+            if let Some(report) = state::report_since(since) {
+                push(conn, &report);
+            }
+        }
+
+        let timer = async {
+            match next_due {
+                Some(at) => Timer::at(at).await,
+                None => core::future::pending().await,
+            }
+        };
+        select3(state::wait_changed(), WAKE.wait(), timer).await;
+    }
+}
@@ -0,0 +1,124 @@
+// 执行器驱动接口（马达、灯光）
+//
+// 应用层只依赖 MotorDriver / LightDriver：本机板载执行器由 LocalActuators 实现，
+// 挂在 CAN 总线上的远端驱动板由 net::can::RemoteBoard 实现，调用方式相同。
+// 2002 / 2003 命令经 app::actuators 队列交给 actuator_task 中的驱动执行，
+// 安全策略由队列在执行前检查，驱动只负责动作本身
+
+use crate::app::state;
+use defmt::Format;
+
+/// 马达命令（字段含义同 M2003 SingleMotorCommand）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct MotorCommand {
+    /// MotorType
+    pub motor_type: i32,
+    /// MotorCommandType
+    pub command: i32,
+    /// RUN_TIME 模式运行时长
+    pub duration_ms: Option<u32>,
+    /// RUN_COUNT 模式数量
+    pub count: Option<u32>,
+    pub speed_level: Option<u32>,
+}
+
+/// 马达调速（只改速度等级，不改变运行状态）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct MotorSpeed {
+    /// MotorType
+    pub motor_type: i32,
+    pub speed_level: u32,
+}
+
+/// 灯光命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct LightCommand {
+    pub light_id: u32,
+    pub on: bool,
+    pub pattern: u32,
+}
+
+/// 执行器错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum ActuatorError {
+    /// 参数无效 / 执行器拒绝
+    Rejected,
+    /// 执行器不可达（远端板离线、总线错误）
+    Unavailable,
+}
+
+/// 马达驱动
+pub trait MotorDriver {
+    async fn command(&mut self, cmd: &MotorCommand) -> Result<(), ActuatorError>;
+
+    /// 调速，运行中的定时 / 计数任务不受影响
+    async fn set_speed(&mut self, cmd: &MotorSpeed) -> Result<(), ActuatorError>;
+}
+
+/// 灯光驱动
+pub trait LightDriver {
+    async fn set_light(&mut self, cmd: &LightCommand) -> Result<(), ActuatorError>;
+}
+
+/// 本机执行器（写入 app::state）
+pub struct LocalActuators;
+
+impl MotorDriver for LocalActuators {
+    async fn command(&mut self, cmd: &MotorCommand) -> Result<(), ActuatorError> {
+        state::apply_motor_command(
+            cmd.motor_type,
+            cmd.command,
+            cmd.duration_ms,
+            cmd.count,
+            cmd.speed_level,
+        )
+        .then_some(())
+        .ok_or(ActuatorError::Rejected)
+    }
+
+    async fn set_speed(&mut self, cmd: &MotorSpeed) -> Result<(), ActuatorError> {
+        state::set_motor_speed(cmd.motor_type, cmd.speed_level)
+            .then_some(())
+            .ok_or(ActuatorError::Rejected)
+    }
+}
+
+impl LightDriver for LocalActuators {
+    async fn set_light(&mut self, cmd: &LightCommand) -> Result<(), ActuatorError> {
+        state::set_light(cmd.light_id, cmd.on, cmd.pattern)
+            .then_some(())
+            .ok_or(ActuatorError::Rejected)
+    }
+}
+
+/// 远端执行器 + 本机状态：远端接受命令后再写入本机状态，
+/// 保证 1002 报告反映远端驱动板的实际动作
+pub struct Mirrored<D> {
+    remote: D,
+    local: LocalActuators,
+}
+
+impl<D> Mirrored<D> {
+    pub fn new(remote: D) -> Self {
+        Self { remote, local: LocalActuators }
+    }
+}
+
+impl<D: MotorDriver> MotorDriver for Mirrored<D> {
+    async fn command(&mut self, cmd: &MotorCommand) -> Result<(), ActuatorError> {
+        self.remote.command(cmd).await?;
+        self.local.command(cmd).await
+    }
+
+    async fn set_speed(&mut self, cmd: &MotorSpeed) -> Result<(), ActuatorError> {
+        self.remote.set_speed(cmd).await?;
+        self.local.set_speed(cmd).await
+    }
+}
+
+impl<D: LightDriver> LightDriver for Mirrored<D> {
+    async fn set_light(&mut self, cmd: &LightCommand) -> Result<(), ActuatorError> {
+        self.remote.set_light(cmd).await?;
+        self.local.set_light(cmd).await
+    }
+}
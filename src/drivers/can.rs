@@ -0,0 +1,100 @@
+// bxCAN 驱动（CAN1）
+//
+// 引脚分配（STM32F407ZG）：
+//   PB8 = CAN1_RX    PB9 = CAN1_TX（外接 TJA1050 / SN65HVD230 等收发器）
+//
+// 过滤器接收全部标准帧，按标识符分发由 net::can 完成。
+// loopback = true 时启用控制器内部环回（不驱动总线），用于单板自检
+
+use crate::net::can::{CanError, CanFrame, CanReceiver, CanSender};
+use embassy_stm32::can::filter::Mask32;
+use embassy_stm32::can::{
+    self, Can, Fifo, Frame, Id, Rx0InterruptHandler, Rx1InterruptHandler, SceInterruptHandler,
+    TxInterruptHandler,
+};
+use embassy_stm32::peripherals::{CAN1, PB8, PB9};
+use embassy_stm32::{bind_interrupts, Peri};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    CAN1_TX => TxInterruptHandler<CAN1>;
+    CAN1_RX0 => Rx0InterruptHandler<CAN1>;
+    CAN1_RX1 => Rx1InterruptHandler<CAN1>;
+    CAN1_SCE => SceInterruptHandler<CAN1>;
+});
+
+static CAN: StaticCell<Can<'static>> = StaticCell::new();
+
+/// CAN 发送端（实现 net::can::CanSender）
+pub type CanBusTx = can::CanTx<'static>;
+
+/// CAN 接收端（实现 net::can::CanReceiver）
+pub type CanBusRx = can::CanRx<'static>;
+
+/// CAN 配置
+#[derive(Clone, Copy)]
+pub struct CanConfig {
+    /// 波特率
+    pub bitrate: u32,
+    /// 控制器内部环回
+    pub loopback: bool,
+}
+
+impl Default for CanConfig {
+    fn default() -> Self {
+        Self {
+            bitrate: 250_000,
+            loopback: false,
+        }
+    }
+}
+
+/// CAN1 所需外设
+pub struct CanPeripherals {
+    pub can: Peri<'static, CAN1>,
+    pub rx: Peri<'static, PB8>,
+    pub tx: Peri<'static, PB9>,
+}
+
+/// 初始化 CAN1，返回 (发送端, 接收端)
+///
+/// 只能调用一次（控制器为静态分配）
+pub async fn init(p: CanPeripherals, config: CanConfig) -> (CanBusTx, CanBusRx) {
+    let can = CAN.init(Can::new(p.can, p.rx, p.tx, Irqs));
+
+    can.modify_filters()
+        .enable_bank(0, Fifo::Fifo0, Mask32::accept_all());
+    can.modify_config()
+        .set_bitrate(config.bitrate)
+        .set_loopback(config.loopback)
+        .set_silent(false);
+    can.enable().await;
+
+    defmt::info!(
+        "CAN initialized: bitrate={}, loopback={}",
+        config.bitrate,
+        config.loopback
+    );
+
+    can.split()
+}
+
+impl CanSender for CanBusTx {
+    async fn send(&mut self, frame: &CanFrame) -> Result<(), CanError> {
+        let frame = Frame::new_standard(frame.id, frame.data()).map_err(|_| CanError::Bus)?;
+        self.write(&frame).await;
+        Ok(())
+    }
+}
+
+impl CanReceiver for CanBusRx {
+    async fn recv(&mut self) -> Result<CanFrame, CanError> {
+        loop {
+            let (frame, _) = self.read().await.map_err(|_| CanError::Bus)?.parts();
+            // 本协议只使用标准帧，扩展帧忽略
+            if let Id::Standard(id) = frame.id() {
+                return Ok(CanFrame::new(id.as_raw(), frame.data()));
+            }
+        }
+    }
+}
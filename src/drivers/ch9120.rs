@@ -0,0 +1,351 @@
+// CH9120 串口转以太网芯片配置驱动
+//
+// 配置方式：CFG0 引脚拉低进入配置模式，串口发送 `0x57 0xAB <cmd> [data]`，
+// 设置类命令返回 0xAA 表示成功，查询类命令直接返回数据；配置完成后
+// 保存(0x0D) → 执行并复位(0x0E) → 退出配置模式(0x5E)，CFG0 恢复高电平。
+//
+// 配置期间芯片不透传数据，因此应在 SerialTransport 接管串口之前调用。
+// 配置命令使用当前串口波特率；修改 `baudrate` 后 MCU 侧需同步调整。
+//
+// 多字节参数：IP / 掩码 / 网关按网络字节序，端口与波特率为小端。
+
+use defmt::{info, Format};
+use embassy_time::{with_timeout, Duration, Timer};
+use embedded_hal::digital::OutputPin;
+use embedded_io_async::{Read, Write};
+
+/// 命令前缀
+const CMD_PREFIX: [u8; 2] = [0x57, 0xAB];
+
+/// 设置类命令成功应答
+const ACK: u8 = 0xAA;
+
+// 查询命令
+const CMD_GET_VERSION: u8 = 0x01;
+const CMD_GET_TCP_STATUS: u8 = 0x03;
+const CMD_GET_MAC: u8 = 0x81;
+
+// 控制命令
+const CMD_SAVE: u8 = 0x0D;
+const CMD_EXECUTE: u8 = 0x0E;
+const CMD_EXIT: u8 = 0x5E;
+
+// 设置命令（端口 1）
+const CMD_SET_MODE: u8 = 0x10;
+const CMD_SET_IP: u8 = 0x11;
+const CMD_SET_MASK: u8 = 0x12;
+const CMD_SET_GATEWAY: u8 = 0x13;
+const CMD_SET_LOCAL_PORT: u8 = 0x14;
+const CMD_SET_REMOTE_IP: u8 = 0x15;
+const CMD_SET_REMOTE_PORT: u8 = 0x16;
+const CMD_SET_BAUDRATE: u8 = 0x21;
+const CMD_SET_DHCP: u8 = 0x33;
+
+/// 拉低 CFG0 后等待芯片进入配置模式
+const ENTER_DELAY: Duration = Duration::from_millis(50);
+
+/// 执行配置后芯片复位时间
+const RESET_DELAY: Duration = Duration::from_millis(500);
+
+/// 工作模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+#[repr(u8)]
+pub enum BridgeMode {
+    TcpServer = 0,
+    TcpClient = 1,
+    UdpServer = 2,
+    UdpClient = 3,
+}
+
+/// 网桥网络配置
+#[derive(Debug, Clone, Copy, Format)]
+pub struct BridgeConfig {
+    pub mode: BridgeMode,
+    /// 启用 DHCP（启用时 IP / 掩码 / 网关由 DHCP 分配）
+    pub dhcp: bool,
+    pub local_ip: [u8; 4],
+    pub subnet_mask: [u8; 4],
+    pub gateway: [u8; 4],
+    /// 本地端口（TcpServer / UdpServer 模式监听端口）
+    pub local_port: u16,
+    /// 目的 IP（客户端模式）
+    pub remote_ip: [u8; 4],
+    /// 目的端口（客户端模式）
+    pub remote_port: u16,
+    /// 串口波特率
+    pub baudrate: u32,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            mode: BridgeMode::TcpServer,
+            dhcp: false,
+            local_ip: [192, 168, 1, 200],
+            subnet_mask: [255, 255, 255, 0],
+            gateway: [192, 168, 1, 1],
+            local_port: 8080,
+            remote_ip: [0, 0, 0, 0],
+            remote_port: 0,
+            baudrate: 115_200,
+        }
+    }
+}
+
+/// 网桥状态（诊断用）
+#[derive(Debug, Clone, Copy, Format)]
+pub struct BridgeStatus {
+    /// 芯片版本
+    pub version: u8,
+    pub mac: [u8; 6],
+    /// 端口 1 TCP 是否已连接
+    pub tcp_connected: bool,
+}
+
+/// 配置错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum BridgeError {
+    /// 串口读写失败
+    Io,
+    /// 超时无应答
+    NoResponse { cmd: u8 },
+    /// 应答不是 0xAA
+    Rejected { cmd: u8, reply: u8 },
+    /// CFG0 引脚操作失败
+    Pin,
+}
+
+/// CH9120 配置驱动
+pub struct Ch9120<P> {
+    /// CFG0 引脚（低电平 = 配置模式）
+    cfg_pin: P,
+    /// 单条命令应答超时
+    timeout: Duration,
+}
+
+impl<P: OutputPin> Ch9120<P> {
+    /// 创建驱动（CFG0 置高，保持透传模式）
+    pub fn new(mut cfg_pin: P) -> Self {
+        let _ = cfg_pin.set_high();
+        Self {
+            cfg_pin,
+            timeout: Duration::from_millis(200),
+        }
+    }
+
+    /// 写入网络配置并保存到芯片 EEPROM，芯片随后复位生效
+    pub async fn apply<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+        config: &BridgeConfig,
+    ) -> Result<(), BridgeError> {
+        info!("CH9120: applying {:?}", config);
+
+        self.enter(rx).await?;
+        let result = self.write_config(rx, tx, config).await;
+        self.exit(tx).await?;
+
+        if result.is_ok() {
+            // 0x0E 之后芯片复位
+            Timer::after(RESET_DELAY).await;
+            info!("CH9120: configuration applied");
+        }
+        result
+    }
+
+    /// 读取芯片版本、MAC 与 TCP 连接状态
+    pub async fn status<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+    ) -> Result<BridgeStatus, BridgeError> {
+        self.enter(rx).await?;
+        let result = self.read_status(rx, tx).await;
+        self.exit(tx).await?;
+        result
+    }
+
+    async fn write_config<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+        config: &BridgeConfig,
+    ) -> Result<(), BridgeError> {
+        self.set(rx, tx, CMD_SET_MODE, &[config.mode as u8]).await?;
+        self.set(rx, tx, CMD_SET_DHCP, &[config.dhcp as u8]).await?;
+        self.set(rx, tx, CMD_SET_IP, &config.local_ip).await?;
+        self.set(rx, tx, CMD_SET_MASK, &config.subnet_mask).await?;
+        self.set(rx, tx, CMD_SET_GATEWAY, &config.gateway).await?;
+        self.set(rx, tx, CMD_SET_LOCAL_PORT, &config.local_port.to_le_bytes()).await?;
+        self.set(rx, tx, CMD_SET_REMOTE_IP, &config.remote_ip).await?;
+        self.set(rx, tx, CMD_SET_REMOTE_PORT, &config.remote_port.to_le_bytes()).await?;
+        self.set(rx, tx, CMD_SET_BAUDRATE, &config.baudrate.to_le_bytes()).await?;
+        self.set(rx, tx, CMD_SAVE, &[]).await?;
+        self.set(rx, tx, CMD_EXECUTE, &[]).await
+    }
+
+    async fn read_status<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+    ) -> Result<BridgeStatus, BridgeError> {
+        let mut version = [0u8; 1];
+        self.query(rx, tx, CMD_GET_VERSION, &[], &mut version).await?;
+
+        let mut mac = [0u8; 6];
+        self.query(rx, tx, CMD_GET_MAC, &[], &mut mac).await?;
+
+        let mut tcp = [0u8; 1];
+        self.query(rx, tx, CMD_GET_TCP_STATUS, &[], &mut tcp).await?;
+
+        Ok(BridgeStatus {
+            version: version[0],
+            mac,
+            tcp_connected: tcp[0] == 1,
+        })
+    }
+
+    /// 拉低 CFG0 进入配置模式，并清空串口中残留的透传数据
+    async fn enter<R: Read>(&mut self, rx: &mut R) -> Result<(), BridgeError> {
+        self.cfg_pin.set_low().map_err(|_| BridgeError::Pin)?;
+        Timer::after(ENTER_DELAY).await;
+
+        let mut scratch = [0u8; 32];
+        while let Ok(Ok(n)) = with_timeout(Duration::from_millis(10), rx.read(&mut scratch)).await {
+            if n == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// 发送退出命令并恢复 CFG0
+    async fn exit<W: Write>(&mut self, tx: &mut W) -> Result<(), BridgeError> {
+        let sent = send_command(tx, CMD_EXIT, &[]).await;
+        self.cfg_pin.set_high().map_err(|_| BridgeError::Pin)?;
+        sent
+    }
+
+    /// 发送设置命令并等待 0xAA
+    async fn set<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+        cmd: u8,
+        data: &[u8],
+    ) -> Result<(), BridgeError> {
+        let mut reply = [0u8; 1];
+        self.query(rx, tx, cmd, data, &mut reply).await?;
+        if reply[0] != ACK {
+            crate::log_warn!("CH9120: cmd {:02X} rejected ({:02X})", cmd, reply[0]);
+            return Err(BridgeError::Rejected { cmd, reply: reply[0] });
+        }
+        Ok(())
+    }
+
+    /// 发送命令并读取固定长度应答
+    async fn query<R: Read, W: Write>(
+        &mut self,
+        rx: &mut R,
+        tx: &mut W,
+        cmd: u8,
+        data: &[u8],
+        reply: &mut [u8],
+    ) -> Result<(), BridgeError> {
+        send_command(tx, cmd, data).await?;
+        match with_timeout(self.timeout, rx.read_exact(reply)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(BridgeError::Io),
+            Err(_) => {
+                crate::log_warn!("CH9120: no response to cmd {:02X}", cmd);
+                Err(BridgeError::NoResponse { cmd })
+            }
+        }
+    }
+}
+
+/// 发送 `0x57 0xAB <cmd> [data]`
+async fn send_command<W: Write>(tx: &mut W, cmd: u8, data: &[u8]) -> Result<(), BridgeError> {
+    tx.write_all(&CMD_PREFIX).await.map_err(|_| BridgeError::Io)?;
+    tx.write_all(&[cmd]).await.map_err(|_| BridgeError::Io)?;
+    tx.write_all(data).await.map_err(|_| BridgeError::Io)?;
+    tx.flush().await.map_err(|_| BridgeError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::mock_hw::{MockPin, ScriptStep, ScriptedDevice, CH9120_SCRIPT};
+    use embassy_futures::block_on;
+
+    fn bridge() -> Ch9120<MockPin> {
+        Ch9120::new(MockPin { high: true })
+    }
+
+    #[test]
+    fn default_config_and_status_follow_the_script() {
+        let device = ScriptedDevice::new(CH9120_SCRIPT);
+        let (mut rx, mut tx) = (&device, &device);
+        let mut bridge = bridge();
+
+        block_on(async {
+            bridge.apply(&mut rx, &mut tx, &BridgeConfig::default()).await.unwrap();
+            let status = bridge.status(&mut rx, &mut tx).await.unwrap();
+            assert_eq!(status.version, 0x04);
+            assert_eq!(status.mac, [0x84, 0xC2, 0xE4, 0x12, 0x34, 0x56]);
+            assert!(status.tcp_connected);
+        });
+        assert!(device.passed());
+        assert!(bridge.cfg_pin.high);
+    }
+
+    #[test]
+    fn rejected_setting_stops_the_write_and_still_leaves_config_mode() {
+        static SCRIPT: &[ScriptStep] = &[
+            ScriptStep { expect: &[0x57, 0xAB, 0x10, 0x00], reply: &[0xAA] },
+            ScriptStep { expect: &[0x57, 0xAB, 0x33, 0x00], reply: &[0xAA] },
+            ScriptStep { expect: &[0x57, 0xAB, 0x11, 192, 168, 1, 200], reply: &[0x00] },
+            ScriptStep { expect: &[0x57, 0xAB, 0x5E], reply: &[] },
+        ];
+        let device = ScriptedDevice::new(SCRIPT);
+        let (mut rx, mut tx) = (&device, &device);
+        let mut bridge = bridge();
+
+        let result = block_on(bridge.apply(&mut rx, &mut tx, &BridgeConfig::default()));
+        assert_eq!(result, Err(BridgeError::Rejected { cmd: CMD_SET_IP, reply: 0x00 }));
+        assert!(device.passed());
+        assert!(bridge.cfg_pin.high);
+    }
+
+    #[test]
+    fn silent_device_reports_no_response() {
+        static SCRIPT: &[ScriptStep] = &[
+            ScriptStep { expect: &[0x57, 0xAB, 0x01], reply: &[] },
+            ScriptStep { expect: &[0x57, 0xAB, 0x5E], reply: &[] },
+        ];
+        let device = ScriptedDevice::new(SCRIPT);
+        let (mut rx, mut tx) = (&device, &device);
+        let mut bridge = bridge();
+
+        let result = block_on(bridge.status(&mut rx, &mut tx));
+        assert_eq!(result.unwrap_err(), BridgeError::NoResponse { cmd: CMD_GET_VERSION });
+        assert!(device.passed());
+    }
+
+    #[test]
+    fn config_that_differs_from_the_script_fails() {
+        let device = ScriptedDevice::new(CH9120_SCRIPT);
+        let (mut rx, mut tx) = (&device, &device);
+        let mut bridge = bridge();
+        let config = BridgeConfig {
+            local_port: 502,
+            ..BridgeConfig::default()
+        };
+
+        // 端口与脚本不符：设备不应答，驱动在该命令上超时
+        let result = block_on(bridge.apply(&mut rx, &mut tx, &config));
+        assert_eq!(result, Err(BridgeError::NoResponse { cmd: CMD_SET_LOCAL_PORT }));
+        assert!(!device.passed());
+    }
+}
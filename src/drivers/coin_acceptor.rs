@@ -0,0 +1,37 @@
+// 投币器禁止线
+//
+// 投币器（硬币识别器）带一根禁止输入：有效时拒收硬币（退回）。
+// 安全策略停机时由 app::safety 置为有效，解除后恢复；有效电平随投币器型号不同。
+// 默认接线：PE2（main.rs 中注册）
+
+use embedded_hal::digital::OutputPin;
+
+/// 投币器禁止控制
+pub trait CoinInhibit {
+    /// 设置禁止状态（true = 拒收硬币）
+    fn set_inhibited(&mut self, inhibited: bool);
+}
+
+/// GPIO 驱动的禁止线
+pub struct InhibitPin<P> {
+    pin: P,
+    /// 高电平有效
+    active_high: bool,
+}
+
+impl<P: OutputPin> InhibitPin<P> {
+    pub fn new(pin: P, active_high: bool) -> Self {
+        Self { pin, active_high }
+    }
+}
+
+impl<P: OutputPin> CoinInhibit for InhibitPin<P> {
+    fn set_inhibited(&mut self, inhibited: bool) {
+        // GPIO 输出不会失败
+        let _ = if inhibited == self.active_high {
+            self.pin.set_high()
+        } else {
+            self.pin.set_low()
+        };
+    }
+}
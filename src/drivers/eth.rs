@@ -0,0 +1,104 @@
+// 以太网驱动（ETH MAC + RMII PHY，LAN8720 / LAN8742）与 embassy-net 协议栈
+//
+// 引脚分配（STM32F407ZG，RMII）：
+//   PA1  = REF_CLK    PA2  = MDIO      PC1  = MDC       PA7  = CRS_DV
+//   PC4  = RXD0       PC5  = RXD1      PB11 = TX_EN     PB12 = TXD0    PB13 = TXD1
+//
+// 与 CH9120 串口网桥二选一：板上焊有 PHY 时在 main.rs 打开 ETHERNET，
+// 网络服务（发现、HTTP、MQTT、WebSocket、Modbus TCP、网关……）运行在这个协议栈上。
+// 以太网要求 HCLK ≥ 25 MHz，使用与 USB 相同的 168 MHz 时钟（`clock_config`）
+
+use embassy_net::{Runner, Stack, StackResources};
+use embassy_stm32::eth::{self, Ethernet, GenericPhy, PacketQueue};
+use embassy_stm32::peripherals::{ETH, PA1, PA2, PA7, PB11, PB12, PB13, PC1, PC4, PC5, RNG};
+use embassy_stm32::rng::{self, Rng};
+use embassy_stm32::{bind_interrupts, uid, Peri};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    ETH => eth::InterruptHandler;
+    RNG => rng::InterruptHandler<RNG>;
+});
+
+/// 协议栈 socket 数量（DHCP + 各网络服务）
+const STACK_SOCKETS: usize = 12;
+
+/// 以太网设备类型
+pub type EthDevice = Ethernet<'static, ETH, GenericPhy>;
+
+/// 以太网配置（缺省：PHY 地址 0，MAC 由唯一 ID 生成）
+#[derive(Clone, Copy, Default)]
+pub struct EthConfig {
+    /// PHY 的 SMI 地址（LAN8720 默认 0）
+    pub phy_address: u8,
+    /// MAC 地址（None = 由芯片唯一 ID 生成本地管理地址）
+    pub mac: Option<[u8; 6]>,
+}
+
+/// ETH / RNG 所需外设
+pub struct EthPeripherals {
+    pub eth: Peri<'static, ETH>,
+    pub rng: Peri<'static, RNG>,
+    pub ref_clk: Peri<'static, PA1>,
+    pub mdio: Peri<'static, PA2>,
+    pub mdc: Peri<'static, PC1>,
+    pub crs_dv: Peri<'static, PA7>,
+    pub rx_d0: Peri<'static, PC4>,
+    pub rx_d1: Peri<'static, PC5>,
+    pub tx_en: Peri<'static, PB11>,
+    pub tx_d0: Peri<'static, PB12>,
+    pub tx_d1: Peri<'static, PB13>,
+}
+
+/// 配置系统时钟（与 USB 相同：HSE 8 MHz → SYSCLK 168 MHz，RNG 使用 PLL Q 48 MHz）
+pub fn clock_config(config: &mut embassy_stm32::Config) {
+    super::usb::clock_config(config);
+}
+
+/// 由 96 位唯一 ID 生成的本地管理 MAC 地址（02:xx:xx:xx:xx:xx）
+pub fn default_mac() -> [u8; 6] {
+    let id = uid::uid();
+    let mut mac = [0x02, 0, 0, 0, 0, 0];
+    for (i, byte) in mac[1..].iter_mut().enumerate() {
+        *byte = id[i] ^ id[i + 5] ^ id[(i + 10) % 12];
+    }
+    mac
+}
+
+/// 初始化以太网与协议栈（DHCPv4），返回 (协议栈, 协议栈运行器)
+///
+/// 运行器须在独立任务中 `run()`。只能调用一次（收发队列与协议栈资源为静态分配）
+pub fn init(p: EthPeripherals, config: EthConfig) -> (Stack<'static>, Runner<'static, EthDevice>) {
+    static PACKETS: StaticCell<PacketQueue<4, 4>> = StaticCell::new();
+    static RESOURCES: StaticCell<StackResources<STACK_SOCKETS>> = StaticCell::new();
+
+    let mac = config.mac.unwrap_or_else(default_mac);
+    let device = Ethernet::new(
+        PACKETS.init(PacketQueue::new()),
+        p.eth,
+        Irqs,
+        p.ref_clk,
+        p.mdio,
+        p.mdc,
+        p.crs_dv,
+        p.rx_d0,
+        p.rx_d1,
+        p.tx_d0,
+        p.tx_d1,
+        p.tx_en,
+        GenericPhy::new(config.phy_address),
+        mac,
+    );
+
+    // 协议栈随机种子（TCP 初始序号、本地端口）取自硬件 RNG
+    let seed = Rng::new(p.rng, Irqs).next_u64();
+
+    defmt::info!("Ethernet initialized: mac={:02X}, phy={}", mac, config.phy_address);
+
+    embassy_net::new(
+        device,
+        embassy_net::Config::dhcpv4(Default::default()),
+        RESOURCES.init(StackResources::new()),
+        seed,
+    )
+}
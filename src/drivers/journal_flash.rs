@@ -0,0 +1,92 @@
+// 事件日志 Flash 镜像（app::journal::JournalMirror）
+//
+// 占用最后一个扇区（STM32F407ZG 扇区 11，0x080E_0000，128 KB），memory.x 把程序限制在前 896 KB。
+// 布局（偏移相对扇区起始）：
+//   0x00  [magic u32 "JRNL"][count u32][保留，补齐 32 字节]
+//   0x20  记录 0、记录 1 ……（每条 RECORD_SIZE 字节）
+//
+// 头部在全部记录写完后才写入（commit），中途断电则 magic 仍为擦除态，视为无转储。
+// 擦除 128 KB 扇区约需 1~2 s，期间 CPU 取指停顿：只在启动时（任务运行前）或运维清除时擦除，
+// FATAL 冻结时只写入已擦除的扇区（256 条约 8 KB，几十毫秒）
+
+use crate::app::journal::{JournalMirror, MirrorError, JOURNAL_CAPACITY, RECORD_SIZE};
+use embassy_stm32::flash::{Blocking, Flash};
+use embassy_stm32::peripherals::FLASH;
+use embassy_stm32::Peri;
+
+/// 镜像扇区偏移（相对 Flash 起始）
+const SECTOR_OFFSET: u32 = 0xE_0000;
+
+/// 镜像扇区大小
+const SECTOR_SIZE: u32 = 128 * 1024;
+
+/// 头部长度
+const HEADER_SIZE: u32 = RECORD_SIZE as u32;
+
+const MAGIC: u32 = u32::from_le_bytes(*b"JRNL");
+
+/// 片上 Flash 日志镜像
+pub struct JournalFlash {
+    flash: Flash<'static, Blocking>,
+}
+
+impl JournalFlash {
+    pub fn new(flash: Peri<'static, FLASH>) -> Self {
+        Self {
+            flash: Flash::new_blocking(flash),
+        }
+    }
+
+    fn record_offset(index: usize) -> u32 {
+        SECTOR_OFFSET + HEADER_SIZE + (index * RECORD_SIZE) as u32
+    }
+}
+
+impl JournalMirror for JournalFlash {
+    fn erase(&mut self) -> Result<(), MirrorError> {
+        self.flash
+            .blocking_erase(SECTOR_OFFSET, SECTOR_OFFSET + SECTOR_SIZE)
+            .map_err(|_| MirrorError::Write)
+    }
+
+    fn is_blank(&mut self) -> bool {
+        // 只检查转储会用到的区域（头部 + JOURNAL_CAPACITY 条记录）
+        let mut chunk = [0u8; RECORD_SIZE];
+        (0..=JOURNAL_CAPACITY).all(|i| {
+            let offset = SECTOR_OFFSET + (i * RECORD_SIZE) as u32;
+            self.flash.blocking_read(offset, &mut chunk).is_ok() && chunk.iter().all(|&b| b == 0xFF)
+        })
+    }
+
+    fn write_record(&mut self, index: usize, record: &[u8; RECORD_SIZE]) -> Result<(), MirrorError> {
+        if index >= JOURNAL_CAPACITY {
+            return Err(MirrorError::Write);
+        }
+        self.flash
+            .blocking_write(Self::record_offset(index), record)
+            .map_err(|_| MirrorError::Write)
+    }
+
+    fn commit(&mut self, count: usize) -> Result<(), MirrorError> {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&(count as u32).to_le_bytes());
+        self.flash
+            .blocking_write(SECTOR_OFFSET, &header)
+            .map_err(|_| MirrorError::Write)
+    }
+
+    fn stored_count(&mut self) -> Option<usize> {
+        let mut header = [0u8; 8];
+        self.flash.blocking_read(SECTOR_OFFSET, &mut header).ok()?;
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let count = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        (magic == MAGIC && count <= JOURNAL_CAPACITY).then_some(count)
+    }
+
+    fn read_record(&mut self, index: usize, record: &mut [u8; RECORD_SIZE]) -> Result<(), MirrorError> {
+        self.flash
+            .blocking_read(Self::record_offset(index), record)
+            .map_err(|_| MirrorError::Read)
+    }
+}
@@ -1,6 +1,18 @@
 // 模拟其他硬件（投币器、马达等）
+use super::actuator::{ActuatorError, LightCommand, LightDriver, MotorCommand, MotorDriver, MotorSpeed};
+use crate::event::coinpusher::v1::MotorCommandType;
+use crate::net::packet::PacketType;
+use crate::net::transport::FrameDecoder;
+use byteorder::{BigEndian, ByteOrder};
+use core::cell::RefCell;
+use core::convert::Infallible;
 use defmt::info;
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
 use embassy_time::{Duration, Timer};
+use embedded_hal::digital::OutputPin;
+use embedded_io_async::{ErrorKind, Read, Write};
 
 /// 模拟投币事件
 pub async fn simulate_coin_insert(interval_secs: u64) -> ! {
@@ -82,3 +94,278 @@ impl MockLightDriver {
         }
     }
 }
+
+/// 模拟串口对端（网桥 / 上位机）
+///
+/// 每 5 秒向串口写入一个 Command 包（cmd=0x2001，请求状态），同时持续读出并解码
+/// 设备发回的应答与推送写入日志。配合 `net::pipe::DuplexPipe` 的主机端使用；
+/// 设备→主机方向必须有人读取，否则管道写满后设备侧发送阻塞，整条传输停住
+pub async fn mock_serial_host<R: Read, W: Write>(rx: R, tx: W) -> ! {
+    select(mock_host_send(tx), mock_host_receive(rx)).await;
+    unreachable!()
+}
+
+/// 模拟对端：读取并解码设备输出
+async fn mock_host_receive<R: Read>(mut rx: R) -> ! {
+    let mut decoder = FrameDecoder::new();
+    loop {
+        match decoder.recv(&mut rx).await {
+            Ok(frame) => {
+                let word = |i: usize| frame.payload.get(i..i + 2).map(BigEndian::read_u16);
+                match frame.packet_type {
+                    PacketType::Response => info!(
+                        "Mock host: response seq={} error_code={:?} cmd={:04X}",
+                        frame.seq,
+                        word(0),
+                        word(2)
+                    ),
+                    PacketType::Command => info!(
+                        "Mock host: push cmd={:04X} ({} bytes)",
+                        word(0),
+                        frame.payload.len().saturating_sub(2)
+                    ),
+                    other => info!("Mock host: {:?} seq={}", other, frame.seq),
+                }
+            }
+            Err(e) => {
+                crate::log_warn!("Mock host: read error: {:?}", e);
+                Timer::after(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+/// 模拟对端：周期写入请求
+async fn mock_host_send<W: Write>(mut tx: W) -> ! {
+    // 实际数据格式：
+    // - PacketHeader (8 bytes): magic + type + seq + len + checksum
+    // - Payload: [cmd: 0x2001][data: ...]
+    static MOCK_DATA: &[u8] = &[
+        // PacketHeader (8 bytes)
+        0xAA, 0x55,       // magic
+        0x20,             // PacketType::Command
+        0x01,             // seq
+        0x00, 0x02,       // payload_len = 2
+        0xAA, 0x99,       // checksum
+        // Payload (2 bytes)
+        0x20, 0x01,       // cmd = 0x2001 (Request Status)
+    ];
+
+    loop {
+        Timer::after(Duration::from_secs(5)).await;
+
+        info!("Mock: Simulating serial data reception");
+        if tx.write_all(MOCK_DATA).await.is_err() {
+            crate::log_warn!("Mock: serial pipe write failed");
+        }
+    }
+}
+
+/// 脚本步骤：期望收到的字节 → 应答字节
+pub struct ScriptStep {
+    pub expect: &'static [u8],
+    pub reply: &'static [u8],
+}
+
+struct ScriptState {
+    step: usize,
+    /// 当前步骤已收到的字节数
+    received: usize,
+    /// 待读出的应答
+    reply: &'static [u8],
+    mismatches: u32,
+}
+
+/// 脚本化串口设备（模拟 CH9120 等按命令应答的外设）
+///
+/// 按顺序比对写入的字节与脚本，匹配后将应答放入读缓冲；
+/// 不匹配时记录并跳到下一步（不应答，驱动侧表现为超时）。
+/// `&ScriptedDevice` 同时实现 Read / Write，可分别作为 rx / tx 传入驱动。
+pub struct ScriptedDevice {
+    script: &'static [ScriptStep],
+    state: Mutex<CriticalSectionRawMutex, RefCell<ScriptState>>,
+}
+
+impl ScriptedDevice {
+    pub const fn new(script: &'static [ScriptStep]) -> Self {
+        Self {
+            script,
+            state: Mutex::new(RefCell::new(ScriptState {
+                step: 0,
+                received: 0,
+                reply: &[],
+                mismatches: 0,
+            })),
+        }
+    }
+
+    /// 脚本是否全部执行且无不匹配
+    pub fn passed(&self) -> bool {
+        self.state
+            .lock(|s| {
+                let s = s.borrow();
+                s.step == self.script.len() && s.mismatches == 0
+            })
+    }
+}
+
+impl embedded_io_async::ErrorType for &ScriptedDevice {
+    type Error = ErrorKind;
+}
+
+impl embedded_io_async::Read for &ScriptedDevice {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            let n = self.state.lock(|s| {
+                let mut s = s.borrow_mut();
+                let n = s.reply.len().min(buf.len());
+                buf[..n].copy_from_slice(&s.reply[..n]);
+                s.reply = &s.reply[n..];
+                n
+            });
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            Timer::after(Duration::from_millis(1)).await;
+        }
+    }
+}
+
+impl Write for &ScriptedDevice {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.state.lock(|s| {
+            let mut s = s.borrow_mut();
+            for &byte in buf {
+                let Some(step) = self.script.get(s.step) else {
+                    crate::log_warn!("Mock: unexpected byte {:02X} after end of script", byte);
+                    s.mismatches += 1;
+                    continue;
+                };
+
+                if step.expect[s.received] != byte {
+                    crate::log_warn!(
+                        "Mock: step {} byte {} expected {:02X}, got {:02X}",
+                        s.step, s.received, step.expect[s.received], byte
+                    );
+                    s.mismatches += 1;
+                    s.step += 1;
+                    s.received = 0;
+                    continue;
+                }
+
+                s.received += 1;
+                if s.received == step.expect.len() {
+                    s.reply = step.reply;
+                    s.step += 1;
+                    s.received = 0;
+                }
+            }
+        });
+        Ok(buf.len())
+    }
+}
+
+/// 模拟输出引脚
+pub struct MockPin {
+    pub high: bool,
+}
+
+impl embedded_hal::digital::ErrorType for MockPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.high = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.high = true;
+        Ok(())
+    }
+}
+
+/// 最近一次马达 / 灯光命令
+type LastCommands = (Option<MotorCommand>, Option<LightCommand>);
+
+/// 记录型执行器（CAN 远端驱动板的本机侧）
+///
+/// 记录最近一次马达 / 灯光命令；`MotorCmdUnknown` 与灯光 ID 0 视为无效命令拒绝。
+/// `&MockActuators` 同时实现 MotorDriver / LightDriver。
+pub struct MockActuators {
+    last: Mutex<CriticalSectionRawMutex, RefCell<LastCommands>>,
+}
+
+impl MockActuators {
+    pub const fn new() -> Self {
+        Self {
+            last: Mutex::new(RefCell::new((None, None))),
+        }
+    }
+
+    /// 最近一次马达命令
+    pub fn last_motor(&self) -> Option<MotorCommand> {
+        self.last.lock(|l| l.borrow().0)
+    }
+
+    /// 最近一次灯光命令
+    pub fn last_light(&self) -> Option<LightCommand> {
+        self.last.lock(|l| l.borrow().1)
+    }
+}
+
+impl Default for MockActuators {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MotorDriver for &MockActuators {
+    async fn command(&mut self, cmd: &MotorCommand) -> Result<(), ActuatorError> {
+        if cmd.command == MotorCommandType::MotorCmdUnknown as i32 {
+            return Err(ActuatorError::Rejected);
+        }
+        info!("Mock: motor {} command {}", cmd.motor_type, cmd.command);
+        self.last.lock(|l| l.borrow_mut().0 = Some(*cmd));
+        Ok(())
+    }
+
+    async fn set_speed(&mut self, cmd: &MotorSpeed) -> Result<(), ActuatorError> {
+        info!("Mock: motor {} speed {}", cmd.motor_type, cmd.speed_level);
+        Ok(())
+    }
+}
+
+impl LightDriver for &MockActuators {
+    async fn set_light(&mut self, cmd: &LightCommand) -> Result<(), ActuatorError> {
+        if cmd.light_id == 0 {
+            return Err(ActuatorError::Rejected);
+        }
+        info!("Mock: light {} {}", cmd.light_id, if cmd.on { "ON" } else { "OFF" });
+        self.last.lock(|l| l.borrow_mut().1 = Some(*cmd));
+        Ok(())
+    }
+}
+
+/// CH9120 脚本：写入 `BridgeConfig::default()` 并读取状态
+pub static CH9120_SCRIPT: &[ScriptStep] = &[
+    // apply
+    ScriptStep { expect: &[0x57, 0xAB, 0x10, 0x00], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x33, 0x00], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x11, 192, 168, 1, 200], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x12, 255, 255, 255, 0], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x13, 192, 168, 1, 1], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x14, 0x90, 0x1F], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x15, 0, 0, 0, 0], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x16, 0x00, 0x00], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x21, 0x00, 0xC2, 0x01, 0x00], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x0D], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x0E], reply: &[0xAA] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x5E], reply: &[] },
+    // status
+    ScriptStep { expect: &[0x57, 0xAB, 0x01], reply: &[0x04] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x81], reply: &[0x84, 0xC2, 0xE4, 0x12, 0x34, 0x56] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x03], reply: &[0x01] },
+    ScriptStep { expect: &[0x57, 0xAB, 0x5E], reply: &[] },
+];
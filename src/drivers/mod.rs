@@ -2,6 +2,20 @@ pub mod button;
 pub mod led;
 pub mod sensor;
 pub mod hw_init;
+#[cfg(target_os = "none")]
+pub mod uart;
+pub mod ch9120;
+pub mod replay;
+pub mod actuator;
+pub mod coin_acceptor;
+#[cfg(target_os = "none")]
+pub mod can;
+#[cfg(target_os = "none")]
+pub mod usb;
+#[cfg(target_os = "none")]
+pub mod eth;
+#[cfg(target_os = "none")]
+pub mod journal_flash;
 
 // 模拟驱动（用于测试）
 pub mod mock_button;
@@ -0,0 +1,91 @@
+// UART 驱动（USART1 + DMA 环形缓冲接收）
+//
+// 引脚分配（STM32F407ZG）：
+//   PA9  = TX     PA10 = RX
+//   PA12 = RTS    PA11 = CTS（仅启用硬件流控时使用，与 USB OTG FS 复用）
+//   DMA2_CH7 = TX DMA, DMA2_CH5 = RX DMA
+//
+// 接收使用 DMA 环形缓冲：DMA 持续写入缓冲区，上层按需读取，
+// 处理期间到达的字节不会丢失（只要缓冲区未溢出）
+
+use embassy_stm32::mode::Async;
+use embassy_stm32::peripherals::{DMA2_CH5, DMA2_CH7, PA10, PA11, PA12, PA9, USART1};
+use embassy_stm32::usart::{self, ConfigError, RingBufferedUartRx, Uart, UartTx};
+use embassy_stm32::{bind_interrupts, Peri};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<USART1>;
+});
+
+/// DMA 环形接收缓冲区大小
+const RX_RING_SIZE: usize = 1024;
+
+static RX_RING: StaticCell<[u8; RX_RING_SIZE]> = StaticCell::new();
+
+/// 串口接收端（实现 embedded_io_async::Read）
+pub type SerialRx = RingBufferedUartRx<'static>;
+
+/// 串口发送端（实现 embedded_io_async::Write）
+pub type SerialTx = UartTx<'static, Async>;
+
+/// 串口配置
+#[derive(Clone, Copy)]
+pub struct UartConfig {
+    /// 波特率
+    pub baudrate: u32,
+    /// 是否启用 RTS/CTS 硬件流控
+    pub flow_control: bool,
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        Self {
+            baudrate: 115_200,
+            flow_control: false,
+        }
+    }
+}
+
+/// USART1 所需外设
+pub struct UartPeripherals {
+    pub usart: Peri<'static, USART1>,
+    pub rx: Peri<'static, PA10>,
+    pub tx: Peri<'static, PA9>,
+    /// RTS / CTS（启用 USB 时为 None）
+    pub rts: Option<Peri<'static, PA12>>,
+    pub cts: Option<Peri<'static, PA11>>,
+    pub tx_dma: Peri<'static, DMA2_CH7>,
+    pub rx_dma: Peri<'static, DMA2_CH5>,
+}
+
+/// 初始化 USART1，返回 (接收端, 发送端)
+///
+/// 只能调用一次（环形缓冲区为静态分配）
+pub fn init(p: UartPeripherals, config: UartConfig) -> Result<(SerialRx, SerialTx), ConfigError> {
+    let mut uart_config = usart::Config::default();
+    uart_config.baudrate = config.baudrate;
+
+    let uart = match (config.flow_control, p.rts, p.cts) {
+        (true, Some(rts), Some(cts)) => Uart::new_with_rtscts(
+            p.usart, p.rx, p.tx, Irqs, rts, cts, p.tx_dma, p.rx_dma, uart_config,
+        )?,
+        (flow_control, _, _) => {
+            if flow_control {
+                crate::log_warn!("UART: flow control requested without RTS/CTS pins");
+            }
+            Uart::new(p.usart, p.rx, p.tx, Irqs, p.tx_dma, p.rx_dma, uart_config)?
+        }
+    };
+
+    let (tx, rx) = uart.split();
+    let rx = rx.into_ring_buffered(RX_RING.init([0u8; RX_RING_SIZE]));
+
+    defmt::info!(
+        "UART initialized: baudrate={}, flow_control={}",
+        config.baudrate,
+        config.flow_control
+    );
+
+    Ok((rx, tx))
+}
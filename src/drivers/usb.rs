@@ -0,0 +1,129 @@
+// USB 设备驱动（OTG FS + CDC-ACM 虚拟串口）
+//
+// 引脚分配（STM32F407ZG）：
+//   PA12 = OTG_FS_DP    PA11 = OTG_FS_DM
+//   （与 USART1 RTS/CTS 复用，启用 USB 后串口不能使用硬件流控）
+//
+// OTG FS 需要精确的 48 MHz 时钟，由 PLL Q 输出：
+//   HSE 8 MHz / 4 × 168 = 336 MHz → P/2 = 168 MHz（SYSCLK），Q/7 = 48 MHz
+// 调用 `clock_config` 后再 `embassy_stm32::init`
+
+use crate::app::device;
+use embassy_stm32::peripherals::{PA11, PA12, USB_OTG_FS};
+use embassy_stm32::rcc::{
+    AHBPrescaler, APBPrescaler, Hse, HseMode, Pll, PllMul, PllPDiv, PllPreDiv, PllQDiv, PllSource, Sysclk,
+};
+use embassy_stm32::time::Hertz;
+use embassy_stm32::usb::{self, Driver};
+use embassy_stm32::{bind_interrupts, Peri};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::{Builder, UsbDevice};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    OTG_FS => usb::InterruptHandler<USB_OTG_FS>;
+});
+
+/// 全速 USB 最大包长
+pub const MAX_PACKET_SIZE: u16 = 64;
+
+/// USB 驱动类型
+pub type UsbDriver = Driver<'static, USB_OTG_FS>;
+
+/// CDC-ACM 虚拟串口
+pub type UsbSerialClass = CdcAcmClass<'static, UsbDriver>;
+
+/// USB 设备配置
+#[derive(Clone, Copy)]
+pub struct UsbConfig {
+    pub vid: u16,
+    pub pid: u16,
+    pub manufacturer: &'static str,
+    pub product: &'static str,
+}
+
+impl Default for UsbConfig {
+    fn default() -> Self {
+        Self {
+            vid: 0xC0DE,
+            pid: 0xCAFE,
+            manufacturer: "Coin Pusher",
+            product: "Coin Pusher Service Port",
+        }
+    }
+}
+
+/// USB OTG FS 所需外设
+pub struct UsbPeripherals {
+    pub otg: Peri<'static, USB_OTG_FS>,
+    pub dp: Peri<'static, PA12>,
+    pub dm: Peri<'static, PA11>,
+}
+
+/// 配置系统时钟（HSE 8 MHz → SYSCLK 168 MHz，USB 48 MHz）
+pub fn clock_config(config: &mut embassy_stm32::Config) {
+    config.rcc.hse = Some(Hse {
+        freq: Hertz(8_000_000),
+        mode: HseMode::Oscillator,
+    });
+    config.rcc.pll_src = PllSource::HSE;
+    config.rcc.pll = Some(Pll {
+        prediv: PllPreDiv::DIV4,
+        mul: PllMul::MUL168,
+        divp: Some(PllPDiv::DIV2),
+        divq: Some(PllQDiv::DIV7),
+        divr: None,
+    });
+    config.rcc.ahb_pre = AHBPrescaler::DIV1;
+    config.rcc.apb1_pre = APBPrescaler::DIV4;
+    config.rcc.apb2_pre = APBPrescaler::DIV2;
+    config.rcc.sys = Sysclk::PLL1_P;
+}
+
+/// 初始化 USB 设备，返回 (设备, CDC-ACM 虚拟串口)
+///
+/// 设备须在独立任务中 `run()` 以完成枚举与总线事件处理。
+/// 只能调用一次（描述符缓冲区为静态分配）
+pub fn init(p: UsbPeripherals, config: UsbConfig) -> (UsbDevice<'static, UsbDriver>, UsbSerialClass) {
+    static EP_OUT_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static STATE: StaticCell<State<'static>> = StaticCell::new();
+
+    let mut driver_config = usb::Config::default();
+    // 整机自供电但 VBUS 未接检测引脚，视为始终插入
+    driver_config.vbus_detection = false;
+
+    let driver = Driver::new_fs(
+        p.otg,
+        Irqs,
+        p.dp,
+        p.dm,
+        EP_OUT_BUFFER.init([0; 256]),
+        driver_config,
+    );
+
+    let mut usb_config = embassy_usb::Config::new(config.vid, config.pid);
+    usb_config.manufacturer = Some(config.manufacturer);
+    usb_config.product = Some(config.product);
+    usb_config.serial_number = Some(device::machine_id());
+    usb_config.max_power = 100;
+    usb_config.max_packet_size_0 = 64;
+
+    let mut builder = Builder::new(
+        driver,
+        usb_config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [], // 不使用 MS OS 描述符
+        CONTROL_BUF.init([0; 64]),
+    );
+
+    let class = CdcAcmClass::new(&mut builder, STATE.init(State::new()), MAX_PACKET_SIZE);
+    let usb = builder.build();
+
+    defmt::info!("USB initialized: {:04X}:{:04X}", config.vid, config.pid);
+
+    (usb, class)
+}
@@ -1,9 +1,20 @@
 // 事件系统
 //
-// 所有系统事件都通过这个枚举传递
+// 所有系统事件都通过这个枚举传递，经事件通道时包装为 EventEnvelope：
+// 单调递增 ID、产生时间（Instant）、来源，以及已校时后的墙上时间。
+// 分发任务处理某个事件期间，新产生的事件自动记录 cause_id，
+// 上行 protobuf 消息通过 `current_meta()` 携带触发事件的元数据。
 
+use crate::net::uplink::ConnectionId;
+use alloc::string::String;
 use alloc::vec::Vec;
 use coinpusher::v1::*;
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicU32, Ordering};
+use defmt::Format;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Instant;
 
 /// 系统事件
 #[derive(Debug, Clone)]
@@ -14,12 +25,49 @@ pub enum Event {
         duration_ms: Option<u32>,
     },
 
+    /// 按钮抬起事件
+    ButtonRelease {
+        button_id: u32,
+        held_ms: u32,
+    },
+
+    /// 按钮长按事件（按住超过阈值时触发，抬起时仍有 ButtonRelease）
+    ButtonLongPress {
+        button_id: u32,
+        held_ms: u32,
+    },
+
+    /// 按钮双击事件
+    ButtonDoubleClick {
+        button_id: u32,
+    },
+
     /// 投币事件
     CoinInsert {
         channel_id: u32,
         value: u32,
     },
 
+    /// 回币计数事件
+    PayoutCount {
+        delta: u32,
+    },
+
+    /// 退币事件（退币器出币传感器计数）
+    CoinOut {
+        count: u32,
+    },
+
+    /// 机门开关变化
+    DoorChanged {
+        open: bool,
+    },
+
+    /// 倾斜 / 震动传感器变化
+    TiltChanged {
+        active: bool,
+    },
+
     /// 网络接收到的消息
     NetworkIncoming {
         cmd: u16,
@@ -38,24 +86,179 @@ pub enum Event {
     /// 故障事件
     FaultDetected {
         hardware_type: i32,
+        hardware_id: Option<u32>,
+        /// 标准化故障码（FaultCode）
+        fault: i32,
         severity: i32,
+        /// 自定义故障码
+        fault_code: Option<u32>,
+        /// 简短说明
+        message: Option<String>,
+        /// 2005 注入的模拟故障
+        simulated: bool,
+        /// 模拟故障持续时间，到期自动清除（None = 直到 2004 清除）
+        duration_ms: Option<u32>,
     },
 }
 
+/// 事件来源（产生事件的任务 / 驱动）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Source {
+    Button,
+    Coin,
+    Motor,
+    Heartbeat,
+    Serial,
+    Network,
+    Usb,
+    System,
+    Input,
+}
+
+impl Source {
+    fn to_proto(self) -> EventSource {
+        match self {
+            Source::Button => EventSource::Button,
+            Source::Coin => EventSource::Coin,
+            Source::Motor => EventSource::Motor,
+            Source::Heartbeat => EventSource::Heartbeat,
+            Source::Serial => EventSource::Serial,
+            Source::Network => EventSource::Network,
+            Source::Usb => EventSource::Usb,
+            Source::System => EventSource::System,
+            Source::Input => EventSource::Input,
+        }
+    }
+}
+
+/// 事件信封
+#[derive(Debug, Clone)]
+pub struct EventEnvelope {
+    /// 单调递增 ID（开机从 1 开始）
+    pub id: u32,
+    /// 产生时间
+    pub timestamp: Instant,
+    pub source: Source,
+    /// Unix 时间（毫秒），未校时为 None
+    pub wall_clock_ms: Option<u64>,
+    /// 产生该事件时正在处理的事件 ID
+    pub cause_id: Option<u32>,
+    /// 发起连接（网络命令），处理器可据此只回复该连接
+    pub origin: Option<ConnectionId>,
+    pub event: Event,
+}
+
+/// 下一个事件 ID
+static NEXT_EVENT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// 墙上时间偏移：Unix 毫秒 - 开机毫秒
+static WALL_CLOCK_OFFSET: Mutex<CriticalSectionRawMutex, Cell<Option<u64>>> = Mutex::new(Cell::new(None));
+
+/// 分发中的事件：元数据与发起连接
+type Dispatching = (EventMeta, Option<ConnectionId>);
+
+/// 正在分发的事件元数据与发起连接
+static CURRENT: Mutex<CriticalSectionRawMutex, RefCell<Option<Dispatching>>> =
+    Mutex::new(RefCell::new(None));
+
+/// 校时：设置当前 Unix 时间（毫秒）
+pub fn set_wall_clock(unix_ms: u64) {
+    let offset = unix_ms.saturating_sub(Instant::now().as_millis());
+    WALL_CLOCK_OFFSET.lock(|o| o.set(Some(offset)));
+}
+
+/// 当前 Unix 时间（毫秒），未校时为 None
+pub fn wall_clock_ms() -> Option<u64> {
+    WALL_CLOCK_OFFSET.lock(|o| o.get()).map(|offset| offset + Instant::now().as_millis())
+}
+
+impl EventEnvelope {
+    /// 包装事件：分配 ID、记录时间与来源
+    pub fn new(source: Source, event: Event) -> Self {
+        Self {
+            id: NEXT_EVENT_ID.fetch_add(1, Ordering::Relaxed),
+            timestamp: Instant::now(),
+            source,
+            wall_clock_ms: wall_clock_ms(),
+            cause_id: CURRENT.lock(|c| c.borrow().as_ref().map(|(m, _)| m.event_id)),
+            origin: None,
+            event,
+        }
+    }
+
+    /// 标记发起连接
+    pub fn with_origin(mut self, conn: ConnectionId) -> Self {
+        self.origin = Some(conn);
+        self
+    }
+
+    /// 上行消息使用的元数据
+    pub fn meta(&self) -> EventMeta {
+        EventMeta {
+            event_id: self.id,
+            timestamp_ms: self.timestamp.as_millis() as u32,
+            source: self.source.to_proto() as i32,
+            wall_clock_ms: self.wall_clock_ms,
+            cause_id: self.cause_id,
+        }
+    }
+
+    /// 在该事件的分发上下文中执行 `f`（期间 `current_meta()` / `current_origin()` 返回本事件的）
+    pub fn dispatch<R>(self, f: impl FnOnce(Event) -> R) -> R {
+        CURRENT.lock(|c| *c.borrow_mut() = Some((self.meta(), self.origin)));
+        let result = f(self.event);
+        CURRENT.lock(|c| *c.borrow_mut() = None);
+        result
+    }
+}
+
+/// 正在分发的事件元数据（处理器填入上行 protobuf 消息）
+pub fn current_meta() -> Option<EventMeta> {
+    CURRENT.lock(|c| c.borrow().as_ref().map(|(meta, _)| *meta))
+}
+
+/// 不经事件总线的系统记录（安全策略决策等）的元数据：分配新 ID，因果关联到正在分发的事件
+pub fn system_meta() -> EventMeta {
+    EventMeta {
+        event_id: NEXT_EVENT_ID.fetch_add(1, Ordering::Relaxed),
+        timestamp_ms: Instant::now().as_millis() as u32,
+        source: Source::System.to_proto() as i32,
+        wall_clock_ms: wall_clock_ms(),
+        cause_id: CURRENT.lock(|c| c.borrow().as_ref().map(|(m, _)| m.event_id)),
+    }
+}
+
+/// 正在分发的事件的发起连接
+pub fn current_origin() -> Option<ConnectionId> {
+    CURRENT.lock(|c| c.borrow().as_ref().and_then(|(_, origin)| *origin))
+}
+
 impl Event {
     /// 将按钮事件转换为 protobuf 消息
     pub fn to_button_event_proto(&self) -> Option<M1003Toc> {
-        match self {
+        let (button_id, action, duration_ms) = match *self {
             Event::ButtonPress {
                 button_id,
                 duration_ms,
-            } => Some(M1003Toc {
-                button_id: *button_id,
-                action: ButtonAction::ButtonPressed as i32,
-                duration_ms: *duration_ms,
-            }),
-            _ => None,
-        }
+            } => (button_id, ButtonAction::ButtonPressed, duration_ms),
+            Event::ButtonRelease { button_id, held_ms } => {
+                (button_id, ButtonAction::ButtonReleased, Some(held_ms))
+            }
+            Event::ButtonLongPress { button_id, held_ms } => {
+                (button_id, ButtonAction::ButtonLongPress, Some(held_ms))
+            }
+            Event::ButtonDoubleClick { button_id } => {
+                (button_id, ButtonAction::ButtonDoubleClick, None)
+            }
+            _ => return None,
+        };
+
+        Some(M1003Toc {
+            button_id,
+            action: action as i32,
+            duration_ms,
+            meta: current_meta(),
+        })
     }
 
     /// 将投币事件转换为 protobuf 消息
@@ -66,6 +269,7 @@ impl Event {
                 coin_value: Some(*value),
                 quantity: 1,
                 total: None,
+                meta: current_meta(),
             }),
             _ => None,
         }
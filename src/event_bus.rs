@@ -0,0 +1,490 @@
+// 事件总线（发布 / 订阅）
+//
+// 替代原先的单消费者 Channel：所有生产者（按钮、心跳、各传输层）发布 EventEnvelope，
+// 消费者分两类：
+//   订阅者 `subscribe()`  共享总线队列，不丢事件（分发、状态记录、上行任务）
+//   观察者 `observe()`    各自独立的队列，发布者只尝试投递、从不等待（事件日志、灯光效果）；
+//                          队列满时该条对这个观察者丢失，`next()` 返回 Lagged(n)
+//   这样慢速的观察者（如 FATAL 时转储 Flash 的事件日志）不会拖住发布者。
+//
+// - 订阅时指定 KindFilter，只接收关心的事件种类，其余在 `next()` 内部跳过（观察者在投递时过滤）
+// - 总线队列满时按事件种类的 OverflowPolicy 处理，只有资金 / 命令 / 故障事件会让生产者等待：
+//   Block      投币 / 回币 / 退币、网络命令、故障：等待订阅者，绝不丢弃
+//   Coalesce   心跳、门开关 / 倾斜：不等待，进入待发槽（每种事件一条），同种事件再次发布时
+//              并入新的一条（只保留最新状态，计数）
+//   DropOldest 按钮、马达状态：进入暂存区，暂存区满时丢弃其中最旧的一条（计数）
+//   任一发布者下次发布前先冲刷待发槽与暂存区，因此它们可能晚于后续事件送达（以 id 为准）
+// - 溢出计数见 `overflow_totals()`，随心跳（1001 event_bus）上报
+// - 发布者从不覆盖总线上未读的消息，订阅者不会落后丢事件；Lagged 只在观察者队列满时出现，
+//   并累计到该观察者的计数
+// - 观察者只收到真正进入总线的事件（被合并 / 从暂存区丢弃的不投递）
+
+use crate::event::{Event, EventEnvelope};
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use defmt::Format;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
+use heapless::{Deque, Vec};
+
+/// 队列容量
+pub const EVENT_BUS_CAPACITY: usize = 32;
+
+/// 最大订阅者数量
+pub const EVENT_BUS_SUBSCRIBERS: usize = 6;
+
+/// 最大观察者数量
+pub const EVENT_BUS_OBSERVERS: usize = 2;
+
+/// 观察者队列深度
+pub const OBSERVER_QUEUE_DEPTH: usize = 16;
+
+/// 最大发布者数量（每个生产者任务一个）
+pub const EVENT_BUS_PUBLISHERS: usize = 8;
+
+/// DropOldest 事件暂存区容量
+pub const STAGING_CAPACITY: usize = 8;
+
+/// Coalesce 待发槽数量（不少于 Coalesce 策略的事件变体数：心跳、门开关、倾斜）
+pub const COALESCE_SLOTS: usize = 4;
+
+type Bus = PubSubChannel<
+    CriticalSectionRawMutex,
+    EventEnvelope,
+    EVENT_BUS_CAPACITY,
+    EVENT_BUS_SUBSCRIBERS,
+    EVENT_BUS_PUBLISHERS,
+>;
+
+static EVENT_BUS: Bus = PubSubChannel::new();
+
+/// 观察者槽位
+struct Observer {
+    used: AtomicBool,
+    /// KindFilter 位图
+    filter: AtomicU16,
+    queue: Channel<CriticalSectionRawMutex, EventEnvelope, OBSERVER_QUEUE_DEPTH>,
+    /// 队列满而未投递、尚未通过 `next()` 报告的事件数
+    lost: AtomicU32,
+}
+
+impl Observer {
+    const fn new() -> Self {
+        Self {
+            used: AtomicBool::new(false),
+            filter: AtomicU16::new(0),
+            queue: Channel::new(),
+            lost: AtomicU32::new(0),
+        }
+    }
+
+    fn accepts(&self, kind: EventKind) -> bool {
+        self.used.load(Ordering::Acquire) && KindFilter(self.filter.load(Ordering::Relaxed)).accepts(kind)
+    }
+}
+
+static OBSERVERS: [Observer; EVENT_BUS_OBSERVERS] = [const { Observer::new() }; EVENT_BUS_OBSERVERS];
+
+/// 总线满时暂存的 DropOldest 事件
+static STAGED: Mutex<CriticalSectionRawMutex, RefCell<Deque<EventEnvelope, STAGING_CAPACITY>>> =
+    Mutex::new(RefCell::new(Deque::new()));
+
+/// 总线满时待发的 Coalesce 事件（每种事件变体一条）
+static PENDING: Mutex<CriticalSectionRawMutex, RefCell<Vec<EventEnvelope, COALESCE_SLOTS>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// 各事件种类的溢出计数（按 EventKind 下标）
+static BLOCKED: [AtomicU32; EventKind::COUNT] = [const { AtomicU32::new(0) }; EventKind::COUNT];
+static COALESCED: [AtomicU32; EventKind::COUNT] = [const { AtomicU32::new(0) }; EventKind::COUNT];
+static DROPPED: [AtomicU32; EventKind::COUNT] = [const { AtomicU32::new(0) }; EventKind::COUNT];
+static LAGGED: [AtomicU32; EventKind::COUNT] = [const { AtomicU32::new(0) }; EventKind::COUNT];
+
+/// 事件种类（订阅过滤用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum EventKind {
+    Button,
+    Coin,
+    Payout,
+    CoinOut,
+    Network,
+    Heartbeat,
+    Motor,
+    Fault,
+    Input,
+}
+
+impl EventKind {
+    /// 种类数量
+    pub const COUNT: usize = 9;
+
+    /// 全部种类
+    pub const ALL: [EventKind; Self::COUNT] = [
+        EventKind::Button,
+        EventKind::Coin,
+        EventKind::Payout,
+        EventKind::CoinOut,
+        EventKind::Network,
+        EventKind::Heartbeat,
+        EventKind::Motor,
+        EventKind::Fault,
+        EventKind::Input,
+    ];
+
+    const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    /// 总线满时的处理策略
+    pub const fn policy(self) -> OverflowPolicy {
+        match self {
+            EventKind::Coin | EventKind::Payout | EventKind::CoinOut => OverflowPolicy::Block,
+            EventKind::Network | EventKind::Fault => OverflowPolicy::Block,
+            EventKind::Heartbeat | EventKind::Input => OverflowPolicy::Coalesce,
+            EventKind::Button | EventKind::Motor => OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// 总线满时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum OverflowPolicy {
+    /// 等待订阅者消费，不丢弃
+    Block,
+    /// 不等待，进入待发槽，并入下一次同种事件（保留最新的一条）
+    Coalesce,
+    /// 进入暂存区，暂存区满时丢弃最旧的一条
+    DropOldest,
+}
+
+/// 溢出计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Format)]
+pub struct OverflowStats {
+    /// 因总线满而等待的次数
+    pub blocked: u32,
+    /// 被同种新事件取代（合并）的次数
+    pub coalesced: u32,
+    /// 从暂存区 / 待发槽丢弃的条数
+    pub dropped: u32,
+    /// 观察者队列满而未投递的条数
+    pub lagged: u32,
+}
+
+/// 某一事件种类的溢出计数
+pub fn overflow_stats(kind: EventKind) -> OverflowStats {
+    let i = kind as usize;
+    OverflowStats {
+        blocked: BLOCKED[i].load(Ordering::Relaxed),
+        coalesced: COALESCED[i].load(Ordering::Relaxed),
+        dropped: DROPPED[i].load(Ordering::Relaxed),
+        lagged: LAGGED[i].load(Ordering::Relaxed),
+    }
+}
+
+/// 全部事件种类的溢出计数之和
+pub fn overflow_totals() -> OverflowStats {
+    EventKind::ALL
+        .iter()
+        .map(|&kind| overflow_stats(kind))
+        .fold(OverflowStats::default(), |acc, s| OverflowStats {
+            blocked: acc.blocked + s.blocked,
+            coalesced: acc.coalesced + s.coalesced,
+            dropped: acc.dropped + s.dropped,
+            lagged: acc.lagged + s.lagged,
+        })
+}
+
+/// 暂存区与待发槽中等待发布的事件数
+pub fn staged_len() -> usize {
+    STAGED.lock(|staged| staged.borrow().len()) + PENDING.lock(|pending| pending.borrow().len())
+}
+
+fn count(counters: &[AtomicU32; EventKind::COUNT], kind: EventKind) {
+    counters[kind as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+impl Event {
+    /// 事件种类
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::ButtonPress { .. }
+            | Event::ButtonRelease { .. }
+            | Event::ButtonLongPress { .. }
+            | Event::ButtonDoubleClick { .. } => EventKind::Button,
+            Event::CoinInsert { .. } => EventKind::Coin,
+            Event::PayoutCount { .. } => EventKind::Payout,
+            Event::CoinOut { .. } => EventKind::CoinOut,
+            Event::DoorChanged { .. } | Event::TiltChanged { .. } => EventKind::Input,
+            Event::NetworkIncoming { .. } => EventKind::Network,
+            Event::HeartbeatTick => EventKind::Heartbeat,
+            Event::MotorStateChanged { .. } => EventKind::Motor,
+            Event::FaultDetected { .. } => EventKind::Fault,
+        }
+    }
+}
+
+/// 订阅过滤器（事件种类位图）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct KindFilter(u16);
+
+impl KindFilter {
+    /// 接收全部事件
+    pub const ALL: Self = Self(u16::MAX);
+
+    /// 只接收一种事件
+    pub const fn only(kind: EventKind) -> Self {
+        Self(kind.bit())
+    }
+
+    /// 追加一种事件
+    pub const fn with(self, kind: EventKind) -> Self {
+        Self(self.0 | kind.bit())
+    }
+
+    /// 排除一种事件
+    pub const fn without(self, kind: EventKind) -> Self {
+        Self(self.0 & !kind.bit())
+    }
+
+    /// 是否接收该种类
+    pub const fn accepts(&self, kind: EventKind) -> bool {
+        self.0 & kind.bit() != 0
+    }
+}
+
+/// 观察者队列满，字段为未投递的事件数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct Lagged(pub u64);
+
+/// 事件发布者（每个生产者持有一个）
+pub struct EventPublisher {
+    inner: Publisher<
+        'static,
+        CriticalSectionRawMutex,
+        EventEnvelope,
+        EVENT_BUS_CAPACITY,
+        EVENT_BUS_SUBSCRIBERS,
+        EVENT_BUS_PUBLISHERS,
+    >,
+}
+
+impl EventPublisher {
+    /// 发布事件；队列满时按事件种类的 OverflowPolicy 处理
+    pub async fn publish(&self, envelope: EventEnvelope) {
+        self.flush_staged();
+
+        let kind = envelope.event.kind();
+        match kind.policy() {
+            OverflowPolicy::Block => {
+                let copy = observed(kind).then(|| envelope.clone());
+                if let Err(envelope) = self.inner.try_publish(envelope) {
+                    count(&BLOCKED, kind);
+                    self.inner.publish(envelope).await;
+                }
+                if let Some(copy) = copy {
+                    notify_observers(copy);
+                }
+            }
+            OverflowPolicy::Coalesce => {
+                if let Err(envelope) = self.try_send(envelope) {
+                    coalesce(envelope);
+                }
+            }
+            OverflowPolicy::DropOldest => {
+                if let Err(envelope) = self.try_send(envelope) {
+                    stage(envelope);
+                }
+            }
+        }
+    }
+
+    /// 不等待地发布到总线，成功后投递给观察者
+    fn try_send(&self, envelope: EventEnvelope) -> Result<(), EventEnvelope> {
+        let copy = observed(envelope.event.kind()).then(|| envelope.clone());
+        self.inner.try_publish(envelope)?;
+        if let Some(copy) = copy {
+            notify_observers(copy);
+        }
+        Ok(())
+    }
+
+    /// 把待发槽与暂存区中的事件尽量发布出去（总线再次满时停止）
+    fn flush_staged(&self) {
+        while let Some(envelope) = PENDING.lock(|pending| {
+            let mut pending = pending.borrow_mut();
+            (!pending.is_empty()).then(|| pending.remove(0))
+        }) {
+            if let Err(envelope) = self.try_send(envelope) {
+                // 刚取出一条，放回必有空位
+                PENDING.lock(|pending| pending.borrow_mut().insert(0, envelope).ok());
+                return;
+            }
+        }
+
+        while let Some(envelope) = STAGED.lock(|staged| staged.borrow_mut().pop_front()) {
+            if let Err(envelope) = self.try_send(envelope) {
+                // 刚取出一条，放回队首必有空位
+                STAGED.lock(|staged| staged.borrow_mut().push_front(envelope).ok());
+                break;
+            }
+        }
+    }
+}
+
+/// 是否有观察者接收该种类
+fn observed(kind: EventKind) -> bool {
+    OBSERVERS.iter().any(|o| o.accepts(kind))
+}
+
+/// 投递给接收该种类的观察者（不等待，队列满时计入该观察者的丢失数）
+fn notify_observers(envelope: EventEnvelope) {
+    let kind = envelope.event.kind();
+    for observer in OBSERVERS.iter().filter(|o| o.accepts(kind)) {
+        if observer.queue.try_send(envelope.clone()).is_err() {
+            observer.lost.fetch_add(1, Ordering::Relaxed);
+            count(&LAGGED, kind);
+        }
+    }
+}
+
+/// 放入待发槽：同种事件已在等待时由新的一条取代（合并计数）
+fn coalesce(envelope: EventEnvelope) {
+    let kind = envelope.event.kind();
+    let variant = core::mem::discriminant(&envelope.event);
+    PENDING.lock(|pending| {
+        let mut pending = pending.borrow_mut();
+        if let Some(slot) = pending.iter_mut().find(|p| core::mem::discriminant(&p.event) == variant) {
+            *slot = envelope;
+            count(&COALESCED, kind);
+        } else if pending.push(envelope).is_err() {
+            count(&DROPPED, kind);
+        }
+    });
+}
+
+/// 放入暂存区，满时丢弃最旧的一条
+fn stage(envelope: EventEnvelope) {
+    STAGED.lock(|staged| {
+        let mut staged = staged.borrow_mut();
+        if staged.is_full()
+            && let Some(oldest) = staged.pop_front()
+        {
+            count(&DROPPED, oldest.event.kind());
+        }
+        staged.push_back(envelope).ok();
+    });
+}
+
+/// 消费端：总线订阅者或观察者
+enum Feed {
+    Bus(
+        Subscriber<
+            'static,
+            CriticalSectionRawMutex,
+            EventEnvelope,
+            EVENT_BUS_CAPACITY,
+            EVENT_BUS_SUBSCRIBERS,
+            EVENT_BUS_PUBLISHERS,
+        >,
+    ),
+    Observer(&'static Observer),
+}
+
+/// 带过滤的事件订阅者（`subscribe()` / `observe()` 创建）
+pub struct EventSubscriber {
+    name: &'static str,
+    filter: KindFilter,
+    feed: Feed,
+    lagged: u64,
+}
+
+impl EventSubscriber {
+    /// 等待下一条匹配过滤器的事件
+    ///
+    /// 观察者返回 `Err(Lagged(n))` 表示自上次读取以来有 n 条事件因队列满未投递，
+    /// 之后可继续读取；总线订阅者不会丢事件
+    pub async fn next(&mut self) -> Result<EventEnvelope, Lagged> {
+        match &mut self.feed {
+            Feed::Bus(inner) => loop {
+                match inner.next_message().await {
+                    WaitResult::Message(envelope) if self.filter.accepts(envelope.event.kind()) => {
+                        return Ok(envelope);
+                    }
+                    WaitResult::Message(_) => continue,
+                    // 发布者只用 try_publish / publish，不会覆盖未读消息
+                    WaitResult::Lagged(n) => {
+                        self.lagged += n;
+                        crate::log_warn!("EventBus: subscriber '{}' lagged, {} events lost", self.name, n);
+                        return Err(Lagged(n));
+                    }
+                }
+            },
+            Feed::Observer(observer) => {
+                let lost = observer.lost.swap(0, Ordering::Relaxed);
+                if lost > 0 {
+                    self.lagged += lost as u64;
+                    crate::log_warn!("EventBus: observer '{}' lagged, {} events lost", self.name, lost);
+                    return Err(Lagged(lost as u64));
+                }
+                Ok(observer.queue.receive().await)
+            }
+        }
+    }
+
+    /// 订阅者名称
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 累计丢失的事件数
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+}
+
+/// 创建发布者（超过 EVENT_BUS_PUBLISHERS 时返回 None）
+pub fn publisher() -> Option<EventPublisher> {
+    match EVENT_BUS.publisher() {
+        Ok(inner) => Some(EventPublisher { inner }),
+        Err(_) => {
+            crate::log_warn!("EventBus: too many publishers");
+            None
+        }
+    }
+}
+
+/// 订阅事件（超过 EVENT_BUS_SUBSCRIBERS 时返回 None）
+///
+/// 订阅者不丢事件：总线满时 Block 类事件的发布者会等待它，只用于必须处理每条事件的消费者
+pub fn subscribe(name: &'static str, filter: KindFilter) -> Option<EventSubscriber> {
+    match EVENT_BUS.subscriber() {
+        Ok(inner) => Some(EventSubscriber {
+            name,
+            filter,
+            feed: Feed::Bus(inner),
+            lagged: 0,
+        }),
+        Err(_) => {
+            crate::log_warn!("EventBus: too many subscribers ('{}' rejected)", name);
+            None
+        }
+    }
+}
+
+/// 以观察者身份订阅（超过 EVENT_BUS_OBSERVERS 时返回 None）
+///
+/// 观察者有独立队列（OBSERVER_QUEUE_DEPTH），发布者从不等待它；处理慢时丢事件并报告 Lagged
+pub fn observe(name: &'static str, filter: KindFilter) -> Option<EventSubscriber> {
+    let Some(observer) = OBSERVERS.iter().find(|o| !o.used.swap(true, Ordering::AcqRel)) else {
+        crate::log_warn!("EventBus: too many observers ('{}' rejected)", name);
+        return None;
+    };
+    observer.filter.store(filter.0, Ordering::Relaxed);
+    Some(EventSubscriber {
+        name,
+        filter,
+        feed: Feed::Observer(observer),
+        lagged: 0,
+    })
+}
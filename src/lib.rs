@@ -0,0 +1,44 @@
+// 推币机固件库
+//
+// 固件（src/main.rs）与 examples/ 下的台架自检程序共用这里的全部模块：
+// 自检只在示例程序中运行，不进入生产启动流程。
+//
+// 与芯片外设相关的模块（drivers 中的 UART / CAN / USB / 以太网 / Flash 与 tasks）只在目标板上编译；
+// 其余逻辑可在主机上以 std 编译运行单元测试：`cargo test-host`
+
+#![cfg_attr(not(test), no_std)]
+// 嵌入式单线程执行器，trait 中的 async fn 不需要 Send 约束
+#![allow(async_fn_in_trait)]
+
+// 启用 alloc
+extern crate alloc;
+
+use embedded_alloc::LlffHeap as Heap;
+
+pub mod error;
+pub mod net;
+pub mod event;
+pub mod event_bus;
+pub mod drivers;
+#[cfg(target_os = "none")]
+pub mod tasks;
+pub mod app;
+pub mod utils;
+
+// 主机测试使用 std 的分配器
+#[cfg_attr(target_os = "none", global_allocator)]
+static HEAP: Heap = Heap::empty();
+
+/// 堆大小
+const HEAP_SIZE: usize = 32 * 1024;
+
+/// 初始化堆内存（32KB），须在任何分配之前调用一次
+pub fn init_heap() {
+    use core::mem::MaybeUninit;
+    use core::ptr::addr_of_mut;
+    static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+    unsafe {
+        let heap_ptr = addr_of_mut!(HEAP_MEM) as *mut u8;
+        HEAP.init(heap_ptr as usize, HEAP_SIZE)
+    }
+}
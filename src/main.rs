@@ -1,114 +1,457 @@
 #![no_std]
 #![no_main]
 
-// 启用 alloc
-extern crate alloc;
-
-use embedded_alloc::LlffHeap as Heap;
-
-#[global_allocator]
-static HEAP: Heap = Heap::empty();
-
-mod error;
-mod net;
-mod event;
-mod drivers;
-mod tasks;
-mod app;
-
 use defmt::info;
 use embassy_executor::Spawner;
 use embassy_stm32::Config;
 use embassy_time::Timer;
 use {defmt_rtt as _, panic_probe as _};
 
+use stm32::{app, drivers, event_bus, log_error, log_warn, net, tasks, utils};
+
 // 引入 Serial Transport
-use net::{SerialTransport, SerialTransportConfig};
+use drivers::can::{CanConfig, CanPeripherals};
+use drivers::ch9120::{BridgeConfig, Ch9120};
+use drivers::coin_acceptor::InhibitPin;
+use drivers::eth::{EthConfig, EthPeripherals};
+use drivers::uart::{self, SerialRx, SerialTx, UartConfig, UartPeripherals};
+use drivers::usb::{UsbConfig, UsbDriver, UsbPeripherals};
+use event_bus::{EventKind, EventPublisher, KindFilter};
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_net::{IpAddress, IpEndpoint, Stack};
+use net::{CanMaster, CanMasterConfig, DiscoveryConfig, ModbusRtu, ModbusRtuConfig, DuplexPipe, Gateway, GatewayConfig, HttpServerConfig, MqttConfig, Rs485Master, Rs485MasterConfig, Rs485Writer, SerialPipe, SerialTransport, SerialTransportConfig, TcpServerConfig, UsbSerial, UsbTransport, UsbTransportConfig};
 use static_cell::StaticCell;
+use tasks::actuator_task::CanBusMaster;
+use utils::log::SyslogConfig;
+
+/// 串口数据源
+#[derive(PartialEq, Eq)]
+enum SerialSource {
+    /// USART1 真实硬件
+    Uart,
+    /// 内存管道 + 模拟对端（每 5 秒一个请求）
+    MockHost,
+}
+
+// 抓包回放见 drivers/replay.rs 的主机测试，不进入生产固件
+const SERIAL_SOURCE: SerialSource = SerialSource::Uart;
+
+/// USART1 接 RS-485 总线时的从站地址（1 ~ 247，由总线主站轮询；DE/RE = PA8）；
+/// None = 经 CH9120 网桥连接上位机
+const RS485_NODE_ADDRESS: Option<u8> = None;
+
+/// USART1 作为 RS-485 总线主站，经以太网把总线上的从站接到后台（net/gateway.rs）；
+/// 需要 ETHERNET，RS485_NODE_ADDRESS 为 Some 时不生效
+const RS485_GATEWAY: bool = false;
+
+/// 网关轮询的从站地址
+const RS485_GATEWAY_NODES: &[u8] = &[1, 2, 3, 4];
+
+/// USART1 接 RS-485 总线作为 Modbus RTU 从站时的单元地址（1 ~ 247，DE/RE = PA8）；
+/// 优先于 RS485_NODE_ADDRESS / RS485_GATEWAY，None = 不启用
+const MODBUS_RTU_UNIT: Option<u8> = None;
+
+/// Modbus RTU 波特率
+const MODBUS_RTU_BAUD: u32 = 19_200;
+
+/// RS-485 发送换向时间（拉高 DE 后等待收发器切换）
+const RS485_TURNAROUND: embassy_time::Duration = embassy_time::Duration::from_micros(100);
+
+/// 是否启用 USB 维护口（CDC-ACM，需要 8 MHz HSE 提供 48 MHz USB 时钟；
+/// 默认关闭，没有外部晶振的板子启用后时钟配置会失败）
+const USB_SERVICE_PORT: bool = false;
 
+/// 是否启用以太网（RMII PHY，见 drivers/eth.rs；没有 PHY 的板子经 CH9120 串口网桥联网）
+const ETHERNET: bool = false;
+
+// 以太网上的网络服务（ETHERNET 关闭时都不启动）
+/// UDP 发现（应答查询）
+const DISCOVERY: bool = true;
+/// HTTP 状态页与控制接口
+const HTTP_SERVER: bool = true;
+/// HTTP POST 接口的 Bearer token（空串 = 拒绝全部 POST，部署时填写）
+const HTTP_AUTH_TOKEN: &str = "";
+/// WebSocket 传输（浏览器运维控制台）
+const WEBSOCKET: bool = true;
+/// Modbus TCP 从站（端口 502）
+const MODBUS_TCP: bool = true;
+/// MQTT 客户端（默认关闭，启用前填写 broker 地址）
+const MQTT: bool = false;
+/// MQTT broker
+const MQTT_BROKER: IpEndpoint = IpEndpoint::new(IpAddress::v4(192, 168, 1, 2), 1883);
+/// log_* 日志转发到 syslog 采集端（RFC 5424 over UDP）
+const SYSLOG: bool = true;
+/// syslog 采集端
+const SYSLOG_COLLECTOR: IpEndpoint = IpEndpoint::new(IpAddress::v4(192, 168, 1, 2), 514);
+
+/// 是否把事件日志镜像到片上 Flash 最后一个扇区（FATAL 冻结时转储）
+const JOURNAL_FLASH_MIRROR: bool = true;
+
+/// 投币器禁止线（PE2）高电平有效（按投币器型号设置）
+const COIN_INHIBIT_ACTIVE_HIGH: bool = true;
+
+/// 马达 / 灯光所在的 CAN 驱动板节点（None = 本机板载执行器）
+const CAN_ACTUATOR_NODE: Option<u8> = None;
+
+/// 台架调试模式（允许 2005 故障注入；营业机台保持 false，开门维护时仍可注入）
+const DEBUG_MODE: bool = false;
+
+/// 硬件串口传输
+type UartTransport = SerialTransport<SerialRx, SerialTx>;
+
+/// RS-485 从站传输（发送端驱动 DE/RE）
+type Rs485Transport = SerialTransport<SerialRx, Rs485Writer<SerialTx, Output<'static>>>;
+
+/// RS-485 网关（主站 + 以太网上行）
+type Rs485Gateway = Gateway<SerialRx, Rs485Writer<SerialTx, Output<'static>>>;
+
+/// 内存管道传输（Demo）
+type PipeTransport = SerialTransport<&'static SerialPipe, &'static SerialPipe>;
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) -> ! {
     // 初始化堆内存 (32KB)
-    {
-        use core::mem::MaybeUninit;
-        use core::ptr::addr_of_mut;
-        const HEAP_SIZE: usize = 32 * 1024;
-        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
-        unsafe {
-            let heap_ptr = addr_of_mut!(HEAP_MEM) as *mut u8;
-            HEAP.init(heap_ptr as usize, HEAP_SIZE)
-        }
-    }
+    stm32::init_heap();
 
-    let config = Config::default();
-    let _p = embassy_stm32::init(config);
+    let mut config = Config::default();
+    if USB_SERVICE_PORT {
+        drivers::usb::clock_config(&mut config);
+    } else if ETHERNET {
+        drivers::eth::clock_config(&mut config);
+    }
+    let p = embassy_stm32::init(config);
 
     info!("=== Coin Pusher System (Event-Driven Architecture) ===");
-    info!("Transport Mode: Serial (USB-to-Ethernet via External Chip)");
+    info!("Transport Mode: {}", serial_mode());
+    info!("  Ethernet: {}, USB service port: {}", ETHERNET, USB_SERVICE_PORT);
     info!("Initializing...");
     info!("");
 
-    // 创建事件通道
-    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-    use embassy_sync::channel::Channel;
-    use event::Event;
+    // 事件总线：各消费者先订阅，避免启动初期的事件在无订阅者时被丢弃
+    // - 分发 / 状态 / 上行为订阅者，不丢事件（投币计数与上行不能丢）
+    // - 事件日志与灯光效果为观察者，处理慢（如 FATAL 转储 Flash）时丢事件而不拖住发布者
+    let dispatch_events = event_bus::subscribe("dispatch", app::router::DISPATCH_KINDS).unwrap();
+    let state_events = event_bus::subscribe("state", app::state::STATE_KINDS).unwrap();
+    let uplink_events = event_bus::subscribe("uplink", app::router::UPLINK_KINDS).unwrap();
+    let journal_events =
+        event_bus::observe("journal", KindFilter::ALL.without(EventKind::Heartbeat)).unwrap();
+    let effects_events = event_bus::observe("effects", app::effects::EFFECT_KINDS).unwrap();
 
-    static EVENT_CHANNEL: StaticCell<Channel<CriticalSectionRawMutex, Event, 32>> =
-        StaticCell::new();
+    if JOURNAL_FLASH_MIRROR {
+        static JOURNAL_FLASH: StaticCell<drivers::journal_flash::JournalFlash> = StaticCell::new();
+        app::journal::set_mirror(JOURNAL_FLASH.init(drivers::journal_flash::JournalFlash::new(p.FLASH)));
+    }
 
-    let event_channel = EVENT_CHANNEL.init(Channel::new());
-    let event_tx = event_channel.sender();
-    let event_rx = event_channel.receiver();
+    app::safety::configure(app::safety::SafetyConfig::default());
+    {
+        static COIN_INHIBIT: StaticCell<InhibitPin<Output<'static>>> = StaticCell::new();
+        let pin = Output::new(p.PE2, Level::Low, Speed::Low);
+        app::safety::set_coin_inhibit(COIN_INHIBIT.init(InhibitPin::new(pin, COIN_INHIBIT_ACTIVE_HIGH)));
+    }
+    app::mode::set_debug(DEBUG_MODE);
 
     info!("Event system initialized");
 
     // 启动所有任务
     info!("Spawning tasks...");
 
-    spawner.spawn(tasks::button_task::button_task(event_tx.clone())).unwrap();
+    spawner.spawn(tasks::button_task::button_task(event_bus::publisher().unwrap())).unwrap();
     info!("  - Button task spawned");
 
-    spawner.spawn(tasks::heartbeat_task::heartbeat_task(event_tx.clone())).unwrap();
+    spawner.spawn(tasks::heartbeat_task::heartbeat_task(event_bus::publisher().unwrap())).unwrap();
     info!("  - Heartbeat task spawned");
 
-    spawner.spawn(tasks::dispatch_task::dispatch_task(event_rx)).unwrap();
+    spawner.spawn(tasks::dispatch_task::dispatch_task(dispatch_events)).unwrap();
     info!("  - Dispatch task spawned");
 
+    spawner.spawn(tasks::state_task::state_task(state_events)).unwrap();
+    info!("  - State task spawned");
+
+    spawner.spawn(tasks::uplink_task::uplink_task(uplink_events)).unwrap();
+    info!("  - Uplink task spawned");
+
+    spawner.spawn(tasks::journal_task::journal_task(journal_events)).unwrap();
+    info!("  - Journal task spawned");
+
+    spawner.spawn(tasks::effects_task::effects_task(effects_events)).unwrap();
+    info!("  - Light effects task spawned");
+
+    spawner.spawn(tasks::status_task::status_task()).unwrap();
+    info!("  - Status subscription task spawned");
+
+    spawner.spawn(tasks::fault_expiry_task::fault_expiry_task()).unwrap();
+    info!("  - Fault expiry task spawned");
+
+    spawner.spawn(tasks::fault_injection_task::fault_injection_task(event_bus::publisher().unwrap())).unwrap();
+    info!("  - Fault injection task spawned");
+
+    spawner.spawn(tasks::motor_timer_task::motor_timer_task()).unwrap();
+    info!("  - Motor timer task spawned");
+
+    // ========== 执行器（马达 / 灯光）==========
+
+    if let Some(node) = CAN_ACTUATOR_NODE {
+        // 执行器在 CAN1 驱动板上（PB8 / PB9）
+        static CAN_MASTER: StaticCell<CanBusMaster> = StaticCell::new();
+
+        let can_peripherals = CanPeripherals {
+            can: p.CAN1,
+            rx: p.PB8,
+            tx: p.PB9,
+        };
+        let (can_tx, can_rx) = drivers::can::init(can_peripherals, CanConfig::default()).await;
+        let master = CAN_MASTER.init(CanMaster::new(CanMasterConfig::default(), can_tx, can_rx));
+
+        spawner.spawn(tasks::actuator_task::can_master_task(master)).unwrap();
+        spawner.spawn(tasks::actuator_task::remote_actuator_task(master, node)).unwrap();
+        info!("  - Actuator task spawned (CAN node {})", node);
+    } else {
+        spawner.spawn(tasks::actuator_task::actuator_task()).unwrap();
+        info!("  - Actuator task spawned (local)");
+    }
+
+    // ========== 以太网网络服务 ==========
+
+    let stack = if ETHERNET {
+        static STACK: StaticCell<Stack<'static>> = StaticCell::new();
+
+        let eth_peripherals = EthPeripherals {
+            eth: p.ETH,
+            rng: p.RNG,
+            ref_clk: p.PA1,
+            mdio: p.PA2,
+            mdc: p.PC1,
+            crs_dv: p.PA7,
+            rx_d0: p.PC4,
+            rx_d1: p.PC5,
+            tx_en: p.PB11,
+            tx_d0: p.PB12,
+            tx_d1: p.PB13,
+        };
+        let (stack, runner) = drivers::eth::init(eth_peripherals, EthConfig::default());
+        let stack: &'static Stack<'static> = STACK.init(stack);
+
+        spawner.spawn(tasks::network_task::net_stack_task(runner)).unwrap();
+        spawner.spawn(tasks::network_task::tcp_server_task(stack, event_bus::publisher().unwrap())).unwrap();
+        info!("  - Ethernet stack and TCP server spawned");
+
+        if DISCOVERY {
+            let discovery = DiscoveryConfig {
+                tcp_port: TcpServerConfig::default().port,
+                ..Default::default()
+            };
+            spawner.spawn(tasks::network_task::discovery_task(stack, discovery)).unwrap();
+            info!("  - Discovery service spawned");
+        }
+
+        if HTTP_SERVER {
+            spawner.spawn(tasks::network_task::http_task(stack, HttpServerConfig::new(HTTP_AUTH_TOKEN))).unwrap();
+            info!("  - HTTP server spawned");
+        }
+
+        if WEBSOCKET {
+            spawner.spawn(tasks::network_task::websocket_task(stack, event_bus::publisher().unwrap())).unwrap();
+            info!("  - WebSocket server spawned");
+        }
+
+        if MODBUS_TCP {
+            spawner.spawn(tasks::network_task::modbus_tcp_task(stack)).unwrap();
+            info!("  - Modbus TCP server spawned");
+        }
+
+        if MQTT {
+            let mqtt = MqttConfig {
+                broker: MQTT_BROKER,
+                ..Default::default()
+            };
+            spawner.spawn(tasks::network_task::mqtt_task(stack, mqtt, event_bus::publisher().unwrap())).unwrap();
+            info!("  - MQTT client spawned");
+        }
+
+        if SYSLOG {
+            let syslog = SyslogConfig {
+                collector: SYSLOG_COLLECTOR,
+                ..Default::default()
+            };
+            spawner.spawn(tasks::network_task::syslog_task(stack, syslog)).unwrap();
+            info!("  - Syslog forwarding spawned");
+        }
+
+        Some(stack)
+    } else {
+        None
+    };
+
     // ========== 启动 Serial Transport（新增）==========
 
     // 创建 Serial Transport 配置
     let serial_config = SerialTransportConfig {
         read_timeout: embassy_time::Duration::from_secs(30),
-        mock_mode: true,  // Demo 模式，接入真实硬件时改为 false
+        node_address: None,
     };
 
-    // 使用 StaticCell 创建静态实例
-    static SERIAL_TRANSPORT: StaticCell<SerialTransport> = StaticCell::new();
-    let serial_transport = SERIAL_TRANSPORT.init(SerialTransport::new(serial_config));
+    // 定义 Serial Transport Task（embassy task 不支持泛型，按数据源分别定义）
+    #[embassy_executor::task]
+    async fn uart_transport_task(transport: &'static UartTransport, event_tx: EventPublisher) -> ! {
+        transport.start(event_tx).await
+    }
 
-    // 定义 Serial Transport Task
     #[embassy_executor::task]
-    async fn serial_transport_task(
-        transport: &'static SerialTransport,
-        event_tx: embassy_sync::channel::Sender<
-            'static,
-            CriticalSectionRawMutex,
-            Event,
-            32,
-        >,
+    async fn rs485_transport_task(transport: &'static Rs485Transport, event_tx: EventPublisher) -> ! {
+        transport.start(event_tx).await
+    }
+
+    #[embassy_executor::task]
+    async fn modbus_rtu_task(
+        slave: ModbusRtu,
+        mut rx: SerialRx,
+        mut tx: Rs485Writer<SerialTx, Output<'static>>,
     ) -> ! {
+        slave.run(&mut rx, &mut tx).await
+    }
+
+    #[embassy_executor::task]
+    async fn gateway_poller_task(gateway: &'static Rs485Gateway) -> ! {
+        gateway.run_poller().await
+    }
+
+    #[embassy_executor::task]
+    async fn gateway_uplink_task(gateway: &'static Rs485Gateway, stack: &'static Stack<'static>) -> ! {
+        gateway.start(stack).await
+    }
+
+    #[embassy_executor::task]
+    async fn pipe_transport_task(transport: &'static PipeTransport, event_tx: EventPublisher) -> ! {
         transport.start(event_tx).await
     }
 
-    // 启动 Serial Transport Task
-    spawner.spawn(serial_transport_task(serial_transport, event_tx.clone())).unwrap();
-    info!("  - Serial Transport task spawned (MOCK mode)");
+    #[embassy_executor::task]
+    async fn mock_serial_host_task(rx: &'static SerialPipe, tx: &'static SerialPipe) -> ! {
+        drivers::mock_serial_host(rx, tx).await
+    }
+
+    // ========== USB 维护口（与串口 / 网络传输并行）==========
+
+    #[embassy_executor::task]
+    async fn usb_device_task(mut usb: embassy_usb::UsbDevice<'static, UsbDriver>) -> ! {
+        usb.run().await
+    }
+
+    #[embassy_executor::task]
+    async fn usb_serial_task(transport: &'static mut UsbTransport<'static, UsbDriver>, event_tx: EventPublisher) -> ! {
+        UsbSerial::new(UsbTransportConfig::default()).start(transport, event_tx).await
+    }
+
+    if USB_SERVICE_PORT {
+        static USB_RX_BUF: StaticCell<[u8; 256]> = StaticCell::new();
+        static USB_TRANSPORT: StaticCell<UsbTransport<'static, UsbDriver>> = StaticCell::new();
+
+        let usb_peripherals = UsbPeripherals {
+            otg: p.USB_OTG_FS,
+            dp: p.PA12,
+            dm: p.PA11,
+        };
+        let (usb, class) = drivers::usb::init(usb_peripherals, UsbConfig::default());
+        let transport = USB_TRANSPORT.init(UsbTransport::new(class, USB_RX_BUF.init([0; 256])));
+
+        spawner.spawn(usb_device_task(usb)).unwrap();
+        spawner.spawn(usb_serial_task(transport, event_bus::publisher().unwrap())).unwrap();
+        info!("  - USB service port spawned (CDC-ACM)");
+    }
+
+    if SERIAL_SOURCE == SerialSource::MockHost {
+        // Demo：内存双工管道，主机端由模拟对端写入
+        static PIPE: DuplexPipe = DuplexPipe::new();
+        static SERIAL_TRANSPORT: StaticCell<PipeTransport> = StaticCell::new();
+
+        let (rx, tx) = PIPE.device_end();
+        let serial_transport = SERIAL_TRANSPORT.init(SerialTransport::new(serial_config, rx, tx));
+
+        let (host_rx, host_tx) = PIPE.host_end();
+        spawner.spawn(mock_serial_host_task(host_rx, host_tx)).unwrap();
+        spawner.spawn(pipe_transport_task(serial_transport, event_bus::publisher().unwrap())).unwrap();
+        info!("  - Serial Transport task spawned (MOCK mode)");
+    } else {
+        // 硬件：USART1 + DMA 环形缓冲
+        let uart_peripherals = UartPeripherals {
+            usart: p.USART1,
+            rx: p.PA10,
+            tx: p.PA9,
+            // PA12 / PA11 归 USB OTG FS，不使用硬件流控
+            rts: None,
+            cts: None,
+            tx_dma: p.DMA2_CH7,
+            rx_dma: p.DMA2_CH5,
+        };
+        let uart_config = match MODBUS_RTU_UNIT {
+            Some(_) => UartConfig {
+                baudrate: MODBUS_RTU_BAUD,
+                ..Default::default()
+            },
+            None => UartConfig::default(),
+        };
+        let (mut rx, mut tx) = uart::init(uart_peripherals, uart_config).unwrap();
+
+        if let Some(unit_id) = MODBUS_RTU_UNIT {
+            let de = Output::new(p.PA8, Level::Low, Speed::Low);
+            let tx = Rs485Writer::new(tx, de, RS485_TURNAROUND);
+            let slave = ModbusRtu::new(ModbusRtuConfig {
+                unit_id,
+                baud_rate: MODBUS_RTU_BAUD,
+            });
+
+            spawner.spawn(modbus_rtu_task(slave, rx, tx)).unwrap();
+            info!("  - Modbus RTU slave spawned (USART1, RS-485 unit {})", unit_id);
+        } else if let Some(address) = RS485_NODE_ADDRESS {
+            static RS485_TRANSPORT: StaticCell<Rs485Transport> = StaticCell::new();
+
+            let de = Output::new(p.PA8, Level::Low, Speed::Low);
+            let tx = Rs485Writer::new(tx, de, RS485_TURNAROUND);
+            let config = SerialTransportConfig {
+                node_address: Some(address),
+                ..serial_config
+            };
+            let transport = RS485_TRANSPORT.init(SerialTransport::new(config, rx, tx));
+
+            spawner.spawn(rs485_transport_task(transport, event_bus::publisher().unwrap())).unwrap();
+            info!("  - Serial Transport task spawned (USART1, RS-485 node {})", address);
+        } else if let (true, Some(stack)) = (RS485_GATEWAY, stack) {
+            static GATEWAY: StaticCell<Rs485Gateway> = StaticCell::new();
+
+            let de = Output::new(p.PA8, Level::Low, Speed::Low);
+            let tx = Rs485Writer::new(tx, de, RS485_TURNAROUND);
+            let master_config = Rs485MasterConfig {
+                nodes: RS485_GATEWAY_NODES,
+                ..Default::default()
+            };
+            let master = Rs485Master::new(master_config, rx, tx);
+            let gateway = GATEWAY.init(Gateway::new(GatewayConfig::default(), master));
+
+            spawner.spawn(gateway_poller_task(gateway)).unwrap();
+            spawner.spawn(gateway_uplink_task(gateway, stack)).unwrap();
+            info!("  - RS-485 gateway spawned (USART1 master, {} nodes)", RS485_GATEWAY_NODES.len());
+        } else {
+            if RS485_GATEWAY {
+                log_warn!("RS-485 gateway needs ETHERNET, USART1 falls back to the CH9120 bridge");
+            }
+
+            static SERIAL_TRANSPORT: StaticCell<UartTransport> = StaticCell::new();
+
+            // 网桥配置（CFG0 = PD3），须在 SerialTransport 接管串口之前完成
+            let mut bridge = Ch9120::new(Output::new(p.PD3, Level::High, Speed::Low));
+            configure_bridge(&mut bridge, &mut rx, &mut tx).await;
+
+            let serial_transport = SERIAL_TRANSPORT.init(SerialTransport::new(serial_config, rx, tx));
+
+            spawner.spawn(uart_transport_task(serial_transport, event_bus::publisher().unwrap())).unwrap();
+            info!("  - Serial Transport task spawned (USART1)");
+        }
+    }
 
     info!("");
     info!("=== System ready ===");
     info!("Event-driven architecture running with Serial Transport...");
-    info!("Waiting for serial data (mock: every 5 seconds)...");
+    info!("Waiting for serial data...");
     info!("");
 
     // 主任务空转
@@ -117,3 +460,39 @@ async fn main(spawner: Spawner) -> ! {
         info!("Main: System running...");
     }
 }
+
+/// USART1 的用途（与 main 中串口初始化的分支顺序一致）
+fn serial_mode() -> &'static str {
+    if SERIAL_SOURCE == SerialSource::MockHost {
+        "Serial (in-memory pipe, mock host)"
+    } else if MODBUS_RTU_UNIT.is_some() {
+        "Serial (USART1 RS-485, Modbus RTU slave)"
+    } else if RS485_NODE_ADDRESS.is_some() {
+        "Serial (USART1 RS-485 node)"
+    } else if RS485_GATEWAY && ETHERNET {
+        "Serial (USART1 RS-485 gateway master)"
+    } else {
+        "Serial (USART1 via CH9120 UART-to-Ethernet bridge)"
+    }
+}
+
+/// 启动时下发网桥网络配置并读取状态（失败不影响启动，仅记录日志）
+async fn configure_bridge<P, R, W>(bridge: &mut Ch9120<P>, rx: &mut R, tx: &mut W)
+where
+    P: embedded_hal::digital::OutputPin,
+    R: embedded_io_async::Read,
+    W: embedded_io_async::Write,
+{
+    if let Err(e) = bridge.apply(rx, tx, &BridgeConfig::default()).await {
+        log_error!("Bridge configuration failed: {:?}", e);
+        return;
+    }
+
+    match bridge.status(rx, tx).await {
+        Ok(status) => info!(
+            "Bridge: version={}, mac={:02X}, tcp_connected={}",
+            status.version, status.mac, status.tcp_connected
+        ),
+        Err(e) => log_warn!("Bridge status read failed: {:?}", e),
+    }
+}
@@ -0,0 +1,887 @@
+// CAN 总线外设协议（大机柜中的马达驱动板、灯光板）
+//
+// 标识符（11 位标准帧）：
+//
+//     [class: 3 bit][node: 8 bit]
+//
+// - class：0 命令（主控 → 节点）/ 1 应答（节点 → 主控）/ 2 事件（节点 → 主控），
+//   数值越小仲裁优先级越高
+// - node：命令帧为目标节点，应答 / 事件帧为来源节点；1 ~ 254，0xFF 为广播命令（不应答）
+//
+// 消息载荷（与数据包协议一致）：
+//
+//     命令  [cmd: 2B][body]
+//     应答  [error_code: 2B][cmd: 2B][data]
+//     事件  [cmd: 2B][body]
+//
+// 分段（每帧 data[0] 为分段头，无流控，发送方按顺序连续发出）：
+//
+//     单帧  0x0L            + L 字节     L = 0 ~ 7
+//     首帧  0x1H LL         + 6 字节     总长 = H << 8 | LL（≤ MAX_MESSAGE_LEN）
+//     续帧  0x2S            + 7 字节     S = 序号，从 1 开始，模 16
+//
+// 不同节点 / 类别的分段可以在总线上交错，接收端按标识符分别重组
+
+use crate::drivers::actuator::{ActuatorError, LightCommand, LightDriver, MotorCommand, MotorDriver, MotorSpeed};
+use byteorder::{BigEndian, ByteOrder};
+use defmt::{debug, info, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::{Channel, Receiver, Sender};
+use embassy_sync::mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{with_timeout, Duration};
+use heapless::Vec;
+
+/// 单条消息最大长度
+pub const MAX_MESSAGE_LEN: usize = 256;
+
+/// 广播节点号
+pub const BROADCAST_NODE: u8 = 0xFF;
+
+/// 马达命令：[motor_type][command][duration_ms: 4B][count: 4B][speed_level]
+pub const CMD_MOTOR: u16 = 0x0101;
+
+/// 灯光命令：[light_id][on][pattern: 4B]
+pub const CMD_LIGHT: u16 = 0x0102;
+
+/// 马达调速：[motor_type][speed_level]
+pub const CMD_MOTOR_SPEED: u16 = 0x0103;
+
+/// 应答错误码
+pub const ERR_OK: u16 = 0;
+pub const ERR_REJECTED: u16 = 1;
+pub const ERR_UNKNOWN_CMD: u16 = 2;
+pub const ERR_MALFORMED: u16 = 3;
+
+/// 可选字段的“无值”编码
+const NONE_U32: u32 = u32::MAX;
+const NONE_U8: u8 = u8::MAX;
+
+/// 同时重组中的消息数
+const MAX_PARTIALS: usize = 4;
+
+/// 主控事件队列深度
+const EVENT_QUEUE_DEPTH: usize = 8;
+
+/// 消息类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+#[repr(u8)]
+pub enum MessageClass {
+    Command = 0,
+    Response = 1,
+    Event = 2,
+}
+
+impl MessageClass {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Command),
+            1 => Some(Self::Response),
+            2 => Some(Self::Event),
+            _ => None,
+        }
+    }
+}
+
+/// 组合标识符
+pub const fn frame_id(class: MessageClass, node: u8) -> u16 {
+    ((class as u16) << 8) | node as u16
+}
+
+/// 经典 CAN 数据帧（标准标识符）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct CanFrame {
+    pub id: u16,
+    pub len: u8,
+    pub data: [u8; 8],
+}
+
+impl CanFrame {
+    /// 创建数据帧（超过 8 字节的部分截断）
+    pub fn new(id: u16, data: &[u8]) -> Self {
+        let len = data.len().min(8);
+        let mut buf = [0u8; 8];
+        buf[..len].copy_from_slice(&data[..len]);
+        Self {
+            id,
+            len: len as u8,
+            data: buf,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+
+    pub fn class(&self) -> Option<MessageClass> {
+        MessageClass::from_u8((self.id >> 8) as u8)
+    }
+
+    pub fn node(&self) -> u8 {
+        self.id as u8
+    }
+}
+
+/// CAN 协议错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum CanError {
+    /// 总线 / 控制器错误
+    Bus,
+    /// 等待应答超时
+    Timeout,
+    /// 消息超过 MAX_MESSAGE_LEN
+    TooLong,
+    /// 节点返回非零错误码
+    Rejected(u16),
+}
+
+/// 帧发送端（bxCAN 发送端或内存环回）
+pub trait CanSender {
+    async fn send(&mut self, frame: &CanFrame) -> Result<(), CanError>;
+}
+
+/// 帧接收端
+pub trait CanReceiver {
+    async fn recv(&mut self) -> Result<CanFrame, CanError>;
+}
+
+/// 消息分段迭代器
+pub struct Segments<'a> {
+    id: u16,
+    message: &'a [u8],
+    offset: usize,
+    seq: u8,
+    done: bool,
+}
+
+impl<'a> Segments<'a> {
+    pub fn new(id: u16, message: &'a [u8]) -> Result<Self, CanError> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(CanError::TooLong);
+        }
+        Ok(Self {
+            id,
+            message,
+            offset: 0,
+            seq: 0,
+            done: false,
+        })
+    }
+}
+
+impl Iterator for Segments<'_> {
+    type Item = CanFrame;
+
+    fn next(&mut self) -> Option<CanFrame> {
+        if self.done {
+            return None;
+        }
+        let mut buf = [0u8; 8];
+        let len = self.message.len();
+
+        // 单帧
+        if len <= 7 {
+            buf[0] = len as u8;
+            buf[1..1 + len].copy_from_slice(self.message);
+            self.done = true;
+            return Some(CanFrame::new(self.id, &buf[..1 + len]));
+        }
+
+        // 首帧
+        if self.offset == 0 {
+            buf[0] = 0x10 | ((len >> 8) as u8 & 0x0F);
+            buf[1] = len as u8;
+            buf[2..8].copy_from_slice(&self.message[..6]);
+            self.offset = 6;
+            self.seq = 1;
+            return Some(CanFrame::new(self.id, &buf));
+        }
+
+        // 续帧
+        let take = (len - self.offset).min(7);
+        buf[0] = 0x20 | (self.seq & 0x0F);
+        buf[1..1 + take].copy_from_slice(&self.message[self.offset..self.offset + take]);
+        self.offset += take;
+        self.seq = self.seq.wrapping_add(1);
+        self.done = self.offset == len;
+        Some(CanFrame::new(self.id, &buf[..1 + take]))
+    }
+}
+
+/// 重组中的消息
+struct Partial {
+    id: u16,
+    expected: usize,
+    next_seq: u8,
+    data: Vec<u8, MAX_MESSAGE_LEN>,
+}
+
+/// 分段重组（按标识符区分，最多同时重组 MAX_PARTIALS 条）
+pub struct Reassembler {
+    partials: Vec<Partial, MAX_PARTIALS>,
+}
+
+impl Reassembler {
+    pub const fn new() -> Self {
+        Self {
+            partials: Vec::new(),
+        }
+    }
+
+    /// 输入一帧，消息完整时返回
+    pub fn push(&mut self, frame: &CanFrame) -> Option<Vec<u8, MAX_MESSAGE_LEN>> {
+        let data = frame.data();
+        let pci = *data.first()?;
+
+        match pci >> 4 {
+            0 => {
+                let len = (pci & 0x0F) as usize;
+                self.discard(frame.id);
+                if len > data.len() - 1 {
+                    crate::log_warn!("CAN: short single frame from {:03X}", frame.id);
+                    return None;
+                }
+                Vec::from_slice(&data[1..1 + len]).ok()
+            }
+            1 => {
+                self.discard(frame.id);
+                let expected = (((pci & 0x0F) as usize) << 8) | *data.get(1)? as usize;
+                if data.len() != 8 || expected <= 7 || expected > MAX_MESSAGE_LEN {
+                    crate::log_warn!("CAN: invalid first frame from {:03X}", frame.id);
+                    return None;
+                }
+                if self.partials.is_full() {
+                    crate::log_warn!("CAN: reassembly slots full, dropping {:03X}", self.partials[0].id);
+                    self.partials.remove(0);
+                }
+                let _ = self.partials.push(Partial {
+                    id: frame.id,
+                    expected,
+                    next_seq: 1,
+                    data: Vec::from_slice(&data[2..]).ok()?,
+                });
+                None
+            }
+            2 => {
+                let index = self.partials.iter().position(|p| p.id == frame.id)?;
+                let partial = &mut self.partials[index];
+                let take = (partial.expected - partial.data.len()).min(7);
+
+                if pci & 0x0F != partial.next_seq & 0x0F || data.len() < 1 + take {
+                    crate::log_warn!("CAN: lost segment from {:03X}", frame.id);
+                    self.partials.remove(index);
+                    return None;
+                }
+
+                let _ = partial.data.extend_from_slice(&data[1..1 + take]);
+                partial.next_seq = partial.next_seq.wrapping_add(1);
+
+                if partial.data.len() == partial.expected {
+                    return Some(self.partials.remove(index).data);
+                }
+                None
+            }
+            _ => {
+                debug!("CAN: unknown PCI {:02X} from {:03X}", pci, frame.id);
+                None
+            }
+        }
+    }
+
+    /// 丢弃该标识符未完成的消息
+    fn discard(&mut self, id: u16) {
+        if let Some(index) = self.partials.iter().position(|p| p.id == id) {
+            crate::log_warn!("CAN: incomplete message from {:03X} discarded", id);
+            self.partials.remove(index);
+        }
+    }
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 分段发送一条消息
+async fn send_message<T: CanSender>(
+    tx: &mut T,
+    class: MessageClass,
+    node: u8,
+    message: &[u8],
+) -> Result<(), CanError> {
+    for frame in Segments::new(frame_id(class, node), message)? {
+        tx.send(&frame).await?;
+    }
+    Ok(())
+}
+
+/// 拼接 [cmd][body]
+fn command_message(cmd: u16, body: &[u8]) -> Result<Vec<u8, MAX_MESSAGE_LEN>, CanError> {
+    let mut message = Vec::new();
+    message
+        .extend_from_slice(&cmd.to_be_bytes())
+        .and_then(|_| message.extend_from_slice(body))
+        .map_err(|_| CanError::TooLong)?;
+    Ok(message)
+}
+
+/// 节点上行事件
+#[derive(Debug, Clone)]
+pub struct CanEvent {
+    pub node: u8,
+    pub cmd: u16,
+    pub body: Vec<u8, MAX_MESSAGE_LEN>,
+}
+
+/// 主控配置
+#[derive(Clone, Copy)]
+pub struct CanMasterConfig {
+    /// 单次请求应答超时
+    pub reply_timeout: Duration,
+}
+
+impl Default for CanMasterConfig {
+    fn default() -> Self {
+        Self {
+            reply_timeout: Duration::from_millis(50),
+        }
+    }
+}
+
+/// 主控应答：(节点, 应答载荷)
+type Reply = (u8, Vec<u8, MAX_MESSAGE_LEN>);
+
+/// CAN 主控：向节点下发命令、接收应答与事件
+///
+/// `run()` 须在独立任务中运行，负责接收与重组；请求串行化，同一时间只有一个未完成请求
+pub struct CanMaster<T, R> {
+    config: CanMasterConfig,
+    tx: Mutex<CriticalSectionRawMutex, T>,
+    rx: Mutex<CriticalSectionRawMutex, R>,
+    request_lock: Mutex<CriticalSectionRawMutex, ()>,
+    reply: Signal<CriticalSectionRawMutex, Reply>,
+    events: Channel<CriticalSectionRawMutex, CanEvent, EVENT_QUEUE_DEPTH>,
+}
+
+impl<T: CanSender, R: CanReceiver> CanMaster<T, R> {
+    pub fn new(config: CanMasterConfig, tx: T, rx: R) -> Self {
+        Self {
+            config,
+            tx: Mutex::new(tx),
+            rx: Mutex::new(rx),
+            request_lock: Mutex::new(()),
+            reply: Signal::new(),
+            events: Channel::new(),
+        }
+    }
+
+    /// 下发命令并等待应答，成功返回应答数据（去掉 error_code 与 cmd）
+    pub async fn request(&self, node: u8, cmd: u16, body: &[u8]) -> Result<Vec<u8, MAX_MESSAGE_LEN>, CanError> {
+        let message = command_message(cmd, body)?;
+        let _guard = self.request_lock.lock().await;
+
+        self.reply.reset();
+        send_message(&mut *self.tx.lock().await, MessageClass::Command, node, &message).await?;
+
+        loop {
+            let (from, reply) = with_timeout(self.config.reply_timeout, self.reply.wait())
+                .await
+                .map_err(|_| CanError::Timeout)?;
+
+            if from != node || reply.len() < 4 || BigEndian::read_u16(&reply[2..4]) != cmd {
+                debug!("CAN: stale reply from node {}", from);
+                continue;
+            }
+
+            return match BigEndian::read_u16(&reply[0..2]) {
+                ERR_OK => Ok(Vec::from_slice(&reply[4..]).unwrap_or_default()),
+                code => Err(CanError::Rejected(code)),
+            };
+        }
+    }
+
+    /// 广播命令（无应答）
+    pub async fn broadcast(&self, cmd: u16, body: &[u8]) -> Result<(), CanError> {
+        let message = command_message(cmd, body)?;
+        send_message(&mut *self.tx.lock().await, MessageClass::Command, BROADCAST_NODE, &message).await
+    }
+
+    /// 等待下一条节点事件
+    pub async fn next_event(&self) -> CanEvent {
+        self.events.receive().await
+    }
+
+    /// 接收循环
+    pub async fn run(&self) -> ! {
+        let mut rx = self.rx.lock().await;
+        let mut reassembler = Reassembler::new();
+        info!("CAN master running");
+
+        loop {
+            let frame = match rx.recv().await {
+                Ok(frame) => frame,
+                Err(e) => {
+                    crate::log_warn!("CAN: receive error: {:?}", e);
+                    continue;
+                }
+            };
+
+            let Some(class) = frame.class() else {
+                continue;
+            };
+            if class == MessageClass::Command {
+                continue;
+            }
+            let Some(message) = reassembler.push(&frame) else {
+                continue;
+            };
+
+            match class {
+                MessageClass::Response => self.reply.signal((frame.node(), message)),
+                MessageClass::Event if message.len() >= 2 => {
+                    let event = CanEvent {
+                        node: frame.node(),
+                        cmd: BigEndian::read_u16(&message[0..2]),
+                        body: Vec::from_slice(&message[2..]).unwrap_or_default(),
+                    };
+                    debug!("CAN: event cmd={:04X} from node {}", event.cmd, event.node);
+                    if self.events.try_send(event).is_err() {
+                        crate::log_warn!("CAN: event queue full, dropping event");
+                    }
+                }
+                _ => crate::log_warn!("CAN: malformed {:?} from node {}", class, frame.node()),
+            }
+        }
+    }
+}
+
+/// CAN 节点（远端驱动板）：执行主控下发的马达 / 灯光命令，上报事件
+pub struct CanNode<T, R> {
+    address: u8,
+    tx: Mutex<CriticalSectionRawMutex, T>,
+    rx: Mutex<CriticalSectionRawMutex, R>,
+}
+
+impl<T: CanSender, R: CanReceiver> CanNode<T, R> {
+    pub fn new(address: u8, tx: T, rx: R) -> Self {
+        Self {
+            address,
+            tx: Mutex::new(tx),
+            rx: Mutex::new(rx),
+        }
+    }
+
+    /// 上报事件
+    pub async fn send_event(&self, cmd: u16, body: &[u8]) -> Result<(), CanError> {
+        let message = command_message(cmd, body)?;
+        send_message(&mut *self.tx.lock().await, MessageClass::Event, self.address, &message).await
+    }
+
+    /// 命令处理循环，命令交给本机执行器
+    pub async fn run<M: MotorDriver, L: LightDriver>(&self, motors: &mut M, lights: &mut L) -> ! {
+        let mut rx = self.rx.lock().await;
+        let mut reassembler = Reassembler::new();
+        info!("CAN node {} running", self.address);
+
+        loop {
+            let frame = match rx.recv().await {
+                Ok(frame) => frame,
+                Err(e) => {
+                    crate::log_warn!("CAN: receive error: {:?}", e);
+                    continue;
+                }
+            };
+
+            let node = frame.node();
+            if frame.class() != Some(MessageClass::Command) || (node != self.address && node != BROADCAST_NODE) {
+                continue;
+            }
+            let Some(message) = reassembler.push(&frame) else {
+                continue;
+            };
+            if message.len() < 2 {
+                crate::log_warn!("CAN: short command");
+                continue;
+            }
+
+            let cmd = BigEndian::read_u16(&message[0..2]);
+            let body = &message[2..];
+            let code = match cmd {
+                CMD_MOTOR => match decode_motor(body) {
+                    Some(c) => driver_result(motors.command(&c).await),
+                    None => ERR_MALFORMED,
+                },
+                CMD_MOTOR_SPEED => match decode_speed(body) {
+                    Some(c) => driver_result(motors.set_speed(&c).await),
+                    None => ERR_MALFORMED,
+                },
+                CMD_LIGHT => match decode_light(body) {
+                    Some(c) => driver_result(lights.set_light(&c).await),
+                    None => ERR_MALFORMED,
+                },
+                _ => ERR_UNKNOWN_CMD,
+            };
+            debug!("CAN: cmd={:04X} -> {}", cmd, code);
+
+            if node == BROADCAST_NODE {
+                continue;
+            }
+
+            let mut reply = [0u8; 4];
+            BigEndian::write_u16(&mut reply[0..2], code);
+            BigEndian::write_u16(&mut reply[2..4], cmd);
+            if let Err(e) = send_message(&mut *self.tx.lock().await, MessageClass::Response, self.address, &reply).await {
+                crate::log_warn!("CAN: reply failed: {:?}", e);
+            }
+        }
+    }
+}
+
+fn driver_result(result: Result<(), ActuatorError>) -> u16 {
+    match result {
+        Ok(()) => ERR_OK,
+        Err(_) => ERR_REJECTED,
+    }
+}
+
+fn encode_optional(value: Option<u32>) -> [u8; 4] {
+    value.unwrap_or(NONE_U32).to_be_bytes()
+}
+
+fn decode_optional(bytes: &[u8]) -> Option<u32> {
+    Some(BigEndian::read_u32(bytes)).filter(|&v| v != NONE_U32)
+}
+
+fn encode_motor(cmd: &MotorCommand) -> [u8; 11] {
+    let mut buf = [0u8; 11];
+    buf[0] = cmd.motor_type as u8;
+    buf[1] = cmd.command as u8;
+    buf[2..6].copy_from_slice(&encode_optional(cmd.duration_ms));
+    buf[6..10].copy_from_slice(&encode_optional(cmd.count));
+    buf[10] = cmd.speed_level.map_or(NONE_U8, |v| v.min(0xFE) as u8);
+    buf
+}
+
+fn decode_motor(body: &[u8]) -> Option<MotorCommand> {
+    if body.len() < 11 {
+        return None;
+    }
+    Some(MotorCommand {
+        motor_type: body[0] as i32,
+        command: body[1] as i32,
+        duration_ms: decode_optional(&body[2..6]),
+        count: decode_optional(&body[6..10]),
+        speed_level: (body[10] != NONE_U8).then_some(body[10] as u32),
+    })
+}
+
+fn encode_speed(cmd: &MotorSpeed) -> [u8; 2] {
+    [cmd.motor_type as u8, cmd.speed_level.min(0xFF) as u8]
+}
+
+fn decode_speed(body: &[u8]) -> Option<MotorSpeed> {
+    if body.len() < 2 {
+        return None;
+    }
+    Some(MotorSpeed {
+        motor_type: body[0] as i32,
+        speed_level: body[1] as u32,
+    })
+}
+
+fn encode_light(cmd: &LightCommand) -> [u8; 6] {
+    let mut buf = [0u8; 6];
+    buf[0] = cmd.light_id as u8;
+    buf[1] = cmd.on as u8;
+    BigEndian::write_u32(&mut buf[2..6], cmd.pattern);
+    buf
+}
+
+fn decode_light(body: &[u8]) -> Option<LightCommand> {
+    if body.len() < 6 {
+        return None;
+    }
+    Some(LightCommand {
+        light_id: body[0] as u32,
+        on: body[1] != 0,
+        pattern: BigEndian::read_u32(&body[2..6]),
+    })
+}
+
+/// 远端驱动板：把 CAN 节点上的马达 / 灯光包装为本机驱动
+pub struct RemoteBoard<'a, T, R> {
+    master: &'a CanMaster<T, R>,
+    node: u8,
+}
+
+impl<'a, T: CanSender, R: CanReceiver> RemoteBoard<'a, T, R> {
+    pub fn new(master: &'a CanMaster<T, R>, node: u8) -> Self {
+        Self { master, node }
+    }
+
+    async fn call(&self, cmd: u16, body: &[u8]) -> Result<(), ActuatorError> {
+        match self.master.request(self.node, cmd, body).await {
+            Ok(_) => Ok(()),
+            Err(CanError::Rejected(_)) => Err(ActuatorError::Rejected),
+            Err(e) => {
+                crate::log_warn!("CAN: node {} unavailable: {:?}", self.node, e);
+                Err(ActuatorError::Unavailable)
+            }
+        }
+    }
+}
+
+impl<T: CanSender, R: CanReceiver> MotorDriver for RemoteBoard<'_, T, R> {
+    async fn command(&mut self, cmd: &MotorCommand) -> Result<(), ActuatorError> {
+        self.call(CMD_MOTOR, &encode_motor(cmd)).await
+    }
+
+    async fn set_speed(&mut self, cmd: &MotorSpeed) -> Result<(), ActuatorError> {
+        self.call(CMD_MOTOR_SPEED, &encode_speed(cmd)).await
+    }
+}
+
+impl<T: CanSender, R: CanReceiver> LightDriver for RemoteBoard<'_, T, R> {
+    async fn set_light(&mut self, cmd: &LightCommand) -> Result<(), ActuatorError> {
+        self.call(CMD_LIGHT, &encode_light(cmd)).await
+    }
+}
+
+/// 内存环回总线单向队列深度
+pub const LOOPBACK_DEPTH: usize = 16;
+
+type LoopbackChannel = Channel<CriticalSectionRawMutex, CanFrame, LOOPBACK_DEPTH>;
+
+/// 环回发送端
+pub type LoopbackTx<'a> = Sender<'a, CriticalSectionRawMutex, CanFrame, LOOPBACK_DEPTH>;
+
+/// 环回接收端
+pub type LoopbackRx<'a> = Receiver<'a, CriticalSectionRawMutex, CanFrame, LOOPBACK_DEPTH>;
+
+impl CanSender for LoopbackTx<'_> {
+    async fn send(&mut self, frame: &CanFrame) -> Result<(), CanError> {
+        Sender::send(self, *frame).await;
+        Ok(())
+    }
+}
+
+impl CanReceiver for LoopbackRx<'_> {
+    async fn recv(&mut self) -> Result<CanFrame, CanError> {
+        Ok(self.receive().await)
+    }
+}
+
+/// 环回端点：(发送端, 接收端)
+pub type LoopbackEnd<'a> = (LoopbackTx<'a>, LoopbackRx<'a>);
+
+/// 内存环回总线（两端点，无需收发器即可运行主控 + 节点）
+pub struct CanLoopback {
+    a_to_b: LoopbackChannel,
+    b_to_a: LoopbackChannel,
+}
+
+impl CanLoopback {
+    pub const fn new() -> Self {
+        Self {
+            a_to_b: Channel::new(),
+            b_to_a: Channel::new(),
+        }
+    }
+
+    /// (A 端, B 端)
+    pub fn split(&self) -> (LoopbackEnd<'_>, LoopbackEnd<'_>) {
+        (
+            (self.a_to_b.sender(), self.b_to_a.receiver()),
+            (self.b_to_a.sender(), self.a_to_b.receiver()),
+        )
+    }
+}
+
+impl Default for CanLoopback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::MockActuators;
+    use crate::event::coinpusher::v1::{MotorCommandType, MotorType};
+    use embassy_futures::block_on;
+    use embassy_futures::select::{select3, Either3};
+
+    const NODE: u8 = 0x11;
+
+    fn message() -> [u8; MAX_MESSAGE_LEN] {
+        core::array::from_fn(|i| i as u8)
+    }
+
+    fn frames(id: u16, message: &[u8]) -> std::vec::Vec<CanFrame> {
+        Segments::new(id, message).unwrap().collect()
+    }
+
+    /// 依次输入，返回最后一次输入的结果
+    fn reassemble<'a>(frames: impl IntoIterator<Item = &'a CanFrame>) -> Option<Vec<u8, MAX_MESSAGE_LEN>> {
+        let mut reassembler = Reassembler::new();
+        frames.into_iter().fold(None, |_, frame| reassembler.push(frame))
+    }
+
+    #[test]
+    fn segments_round_trip_up_to_max_length() {
+        let message = message();
+        for len in [0, 1, 7, 8, 13, 14, 100, MAX_MESSAGE_LEN] {
+            let frames = frames(0x123, &message[..len]);
+            assert_eq!(frames.len(), if len <= 7 { 1 } else { 1 + (len - 6).div_ceil(7) });
+            assert!(frames.iter().all(|f| f.id == 0x123 && f.len <= 8));
+            assert_eq!(reassemble(&frames).as_deref(), Some(&message[..len]), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn oversized_message_is_refused() {
+        let message = [0u8; MAX_MESSAGE_LEN + 1];
+        assert!(matches!(Segments::new(0x123, &message), Err(CanError::TooLong)));
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_modulo_16() {
+        let message = message();
+        let frames = frames(0x123, &message);
+        // 续帧序号 1..15, 0, 1, ...
+        assert_eq!(frames[15].data[0], 0x2F);
+        assert_eq!(frames[16].data[0], 0x20);
+        assert_eq!(frames[17].data[0], 0x21);
+    }
+
+    #[test]
+    fn interleaved_sources_reassemble_separately() {
+        let message = message();
+        let first = frames(frame_id(MessageClass::Event, 1), &message[..20]);
+        let second = frames(frame_id(MessageClass::Event, 2), &message[100..130]);
+
+        let mut reassembler = Reassembler::new();
+        let mut done = std::vec::Vec::new();
+        for i in 0..first.len().max(second.len()) {
+            for frame in [first.get(i), second.get(i)].into_iter().flatten() {
+                done.extend(reassembler.push(frame));
+            }
+        }
+        assert_eq!(done.len(), 2);
+        assert_eq!(done[0].as_slice(), &message[..20]);
+        assert_eq!(done[1].as_slice(), &message[100..130]);
+    }
+
+    #[test]
+    fn dropped_segment_discards_the_message() {
+        let message = message();
+        let mut frames = frames(0x123, &message[..30]);
+        frames.remove(2);
+        assert_eq!(reassemble(&frames), None);
+    }
+
+    #[test]
+    fn out_of_order_segments_discard_the_message() {
+        let message = message();
+        let mut frames = frames(0x123, &message[..30]);
+        frames.swap(1, 2);
+        assert_eq!(reassemble(&frames), None);
+    }
+
+    #[test]
+    fn sequence_gap_discards_the_message_and_recovers_on_the_next_one() {
+        let message = message();
+        let broken = frames(0x123, &message[..40]);
+        let intact = frames(0x123, &message[..40]);
+
+        let mut reassembler = Reassembler::new();
+        // 首帧 + 续帧 1，然后跳到续帧 3
+        assert_eq!(reassembler.push(&broken[0]), None);
+        assert_eq!(reassembler.push(&broken[1]), None);
+        assert_eq!(reassembler.push(&broken[3]), None);
+        // 之后的续帧不再属于任何消息
+        assert_eq!(reassembler.push(&broken[4]), None);
+
+        let result = intact.iter().fold(None, |_, frame| reassembler.push(frame));
+        assert_eq!(result.as_deref(), Some(&message[..40]));
+    }
+
+    #[test]
+    fn new_first_frame_replaces_an_incomplete_message() {
+        let message = message();
+        let stale = frames(0x123, &message[..30]);
+        let fresh = frames(0x123, &message[50..80]);
+
+        let mut reassembler = Reassembler::new();
+        reassembler.push(&stale[0]);
+        reassembler.push(&stale[1]);
+        let result = fresh.iter().fold(None, |_, frame| reassembler.push(frame));
+        assert_eq!(result.as_deref(), Some(&message[50..80]));
+    }
+
+    /// 在内存环回总线上运行主控与节点（节点挂 MockActuators），执行 `checks`
+    fn with_loopback_bus(
+        checks: impl AsyncFnOnce(&CanMaster<LoopbackTx<'_>, LoopbackRx<'_>>, &CanNode<LoopbackTx<'_>, LoopbackRx<'_>>, &MockActuators),
+    ) {
+        let bus = CanLoopback::new();
+        let actuators = MockActuators::new();
+        let ((master_tx, master_rx), (node_tx, node_rx)) = bus.split();
+        let master = CanMaster::new(CanMasterConfig::default(), master_tx, master_rx);
+        let node = CanNode::new(NODE, node_tx, node_rx);
+        let (mut motors, mut lights) = (&actuators, &actuators);
+
+        block_on(async {
+            match select3(master.run(), node.run(&mut motors, &mut lights), checks(&master, &node, &actuators)).await {
+                Either3::Third(()) => {}
+            }
+        });
+    }
+
+    #[test]
+    fn remote_board_applies_commands_on_the_node() {
+        with_loopback_bus(async |master, _, actuators| {
+            let mut board = RemoteBoard::new(master, NODE);
+
+            // 8 字节，首帧 + 续帧
+            let light = LightCommand { light_id: 3, on: true, pattern: 2 };
+            board.set_light(&light).await.unwrap();
+            assert_eq!(actuators.last_light(), Some(light));
+
+            // 可选字段往返
+            let motor = MotorCommand {
+                motor_type: MotorType::Payout as i32,
+                command: MotorCommandType::MotorCmdRunTime as i32,
+                duration_ms: Some(1500),
+                count: None,
+                speed_level: Some(3),
+            };
+            board.command(&motor).await.unwrap();
+            assert_eq!(actuators.last_motor(), Some(motor));
+
+            let invalid = MotorCommand { command: MotorCommandType::MotorCmdUnknown as i32, ..motor };
+            assert_eq!(board.command(&invalid).await, Err(ActuatorError::Rejected));
+        });
+    }
+
+    #[test]
+    fn absent_node_times_out() {
+        with_loopback_bus(async |master, _, _| {
+            let mut absent = RemoteBoard::new(master, NODE + 1);
+            let light = LightCommand { light_id: 1, on: false, pattern: 0 };
+            assert_eq!(absent.set_light(&light).await, Err(ActuatorError::Unavailable));
+        });
+    }
+
+    #[test]
+    fn multi_frame_events_reach_the_master() {
+        with_loopback_bus(async |master, node, _| {
+            let body = [0x5A; 20];
+            node.send_event(0x1004, &body).await.unwrap();
+            let event = with_timeout(Duration::from_millis(100), master.next_event()).await.unwrap();
+            assert_eq!(event.node, NODE);
+            assert_eq!(event.cmd, 0x1004);
+            assert_eq!(event.body.as_slice(), &body);
+        });
+    }
+}
@@ -1,6 +1,6 @@
 // 编解码（以后加 protobuf 放这里）
 use super::packet::{Packet, PacketError, PacketHeader, PacketType, HEADER_LEN, MAX_PAYLOAD_LEN};
-use defmt::{debug, warn, Format};
+use defmt::{debug, Format};
 use heapless::Vec;
 
 /// 编解码器状态
@@ -37,7 +37,7 @@ impl PacketCodec {
     pub fn feed(&mut self, data: &[u8]) -> Result<(), CodecError> {
         for &byte in data {
             if self.buffer.push(byte).is_err() {
-                warn!("Codec buffer overflow, resetting");
+                crate::log_warn!("Codec buffer overflow, resetting");
                 self.reset();
                 return Err(CodecError::BufferOverflow);
             }
@@ -63,7 +63,7 @@ impl PacketCodec {
                         Ok(header) => {
                             // 检查载荷长度是否合理
                             if header.payload_len as usize > MAX_PAYLOAD_LEN {
-                                warn!("Payload too large: {}", header.payload_len);
+                                crate::log_warn!("Payload too large: {}", header.payload_len);
                                 self.reset();
                                 return Err(CodecError::PayloadTooLarge);
                             }
@@ -79,7 +79,7 @@ impl PacketCodec {
                             self.state = CodecState::WaitingPayload { header };
                         }
                         Err(e) => {
-                            warn!("Invalid header: {:?}", e);
+                            crate::log_warn!("Invalid header: {:?}", e);
                             // 丢弃第一个字节，继续寻找有效头部
                             if self.buffer.len() > 1 {
                                 self.buffer.as_mut_slice().copy_within(1.., 0);
@@ -115,26 +115,21 @@ impl PacketCodec {
                         payload: &output_buf[..payload_len],
                     };
 
+                    // 移除载荷数据（校验失败时只丢弃这个包，后面已缓冲的数据包保留）
+                    self.buffer.as_mut_slice().copy_within(payload_len.., 0);
+                    self.buffer.truncate(self.buffer.len() - payload_len);
+
+                    // 重置状态
+                    self.state = CodecState::WaitingHeader;
+
                     if let Err(e) = packet.verify() {
-                        warn!("Packet verification failed: {:?}", e);
-                        self.reset();
+                        crate::log_warn!("Packet verification failed: {:?}", e);
                         return Err(CodecError::InvalidPacket(e));
                     }
 
                     debug!("Packet decoded successfully: type={:?}, seq={}",
                            header.packet_type, header.seq);
 
-                    // 移除载荷数据
-                    if self.buffer.len() > payload_len {
-                        self.buffer.as_mut_slice().copy_within(payload_len.., 0);
-                        self.buffer.truncate(self.buffer.len() - payload_len);
-                    } else {
-                        self.buffer.clear();
-                    }
-
-                    // 重置状态
-                    self.state = CodecState::WaitingHeader;
-
                     // 返回解码的数据包信息
                     return Ok(Some(DecodedPacket {
                         packet_type: header.packet_type,
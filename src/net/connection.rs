@@ -1,14 +1,28 @@
-// TCP 连接处理（单个连接，简化版）
+// 连接处理（请求/应答分发，TCP、串口、USB 等全部 Transport 共用）
+//
+// `serve` 负责收包、回写应答与推送上行事件，命令包的处理方式由 Dispatcher 决定：
+//   Router         同步调用路由表中的处理器，应答携带处理结果（TCP）
+//   EventInjector  注入事件总线（Event::NetworkIncoming），应答只表示已入队，
+//                  执行结果经上行推送返回（串口、USB）
 use super::{
-    codec::{CodecError, PacketCodec},
-    packet::PacketType,
+    codec::{CodecError, DecodedPacket, PacketCodec},
+    packet::{PacketType, HEADER_LEN, MAX_PAYLOAD_LEN},
     router::Router,
+    transport::{TcpTransport, Transport, TransportError},
+    uplink::{self, Connection, ConnectionId, UplinkMessage},
 };
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
 use byteorder::{BigEndian, ByteOrder};
-use defmt::{debug, error, info, warn, Format};
+use defmt::{debug, info, Format};
+use embassy_futures::select::{select, Either};
 use embassy_net::tcp::TcpSocket;
+use embassy_sync::pubsub::WaitResult;
 use heapless::Vec;
 
+/// 编码后数据包的最大长度
+pub const MAX_FRAME_LEN: usize = HEADER_LEN + MAX_PAYLOAD_LEN;
+
 /// TCP 错误
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
 pub enum TcpError {
@@ -25,111 +39,237 @@ impl From<CodecError> for TcpError {
 }
 
 /// 处理 TCP 连接（直接在这里处理消息）
-pub async fn handle_connection<'a>(
-    mut socket: TcpSocket<'a>,
-    router: &'static Router,
+pub async fn handle_connection<'a, D: Dispatcher>(
+    socket: TcpSocket<'a>,
+    dispatcher: &D,
 ) -> Result<(), TcpError> {
     info!("Handling connection");
 
-    let mut codec = PacketCodec::new();
-    let mut rx_buffer = [0u8; 512];
-    let mut decode_buffer = [0u8; 1024];
+    let conn = Connection::open();
+    let mut transport = TcpTransport::new(socket);
+    let result = serve(&mut transport, dispatcher, conn.id()).await;
+    transport.close().await;
+    result.map_err(|e| match e {
+        TransportError::Disconnected => TcpError::Disconnected,
+        TransportError::SendFailed => TcpError::SendFailed,
+        TransportError::ReadFailed => TcpError::Other,
+    })
+}
 
-    loop {
-        // 从 socket 读取数据
-        let n = match socket.read(&mut rx_buffer).await {
-            Ok(0) => {
-                info!("Connection closed by peer");
-                return Err(TcpError::Disconnected);
-            }
-            Ok(n) => n,
-            Err(e) => {
-                error!("Socket read error: {:?}", e);
-                return Err(TcpError::Other);
-            }
-        };
+/// 命令包的处理方式
+pub trait Dispatcher {
+    /// 处理一个已解码的数据包（含 Ping），应答编码到 `output` 并返回其长度；
+    /// 无需应答时返回 `None`。`conn` 为数据包所属连接
+    async fn dispatch(
+        &self,
+        conn: ConnectionId,
+        packet: &DecodedPacket<'_>,
+        output: &mut [u8],
+    ) -> Result<Option<usize>, TcpError>;
+}
 
-        debug!("Received {} bytes", n);
+impl Dispatcher for Router {
+    async fn dispatch(
+        &self,
+        _conn: ConnectionId,
+        packet: &DecodedPacket<'_>,
+        output: &mut [u8],
+    ) -> Result<Option<usize>, TcpError> {
+        dispatch_packet(self, packet, output)
+    }
+}
 
-        // 喂给编解码器
-        if let Err(e) = codec.feed(&rx_buffer[..n]) {
-            warn!("Codec feed error: {:?}", e);
-            continue;
+/// 把命令注入事件总线（Event::NetworkIncoming，携带发起连接）
+///
+/// 应答 error_code 0 表示已入队、1 表示格式错误，执行结果经上行推送返回
+pub struct EventInjector<'a> {
+    source: Source,
+    event_tx: &'a EventPublisher,
+}
+
+impl<'a> EventInjector<'a> {
+    pub fn new(source: Source, event_tx: &'a EventPublisher) -> Self {
+        Self { source, event_tx }
+    }
+}
+
+impl Dispatcher for EventInjector<'_> {
+    async fn dispatch(
+        &self,
+        conn: ConnectionId,
+        packet: &DecodedPacket<'_>,
+        output: &mut [u8],
+    ) -> Result<Option<usize>, TcpError> {
+        if packet.packet_type == PacketType::Ping {
+            debug!("Received Ping, sending Pong");
+            return PacketCodec::encode_simple(PacketType::Pong, packet.seq, output)
+                .map(Some)
+                .map_err(TcpError::from);
         }
 
-        // 尝试解码数据包
-        while let Ok(Some(packet)) = codec.decode(&mut decode_buffer) {
-            info!(
-                "Decoded packet: type={:?}, seq={}, len={}",
-                packet.packet_type,
-                packet.seq,
-                packet.payload.len()
-            );
-
-            // 处理 Ping（自动响应 Pong）
-            if packet.packet_type == PacketType::Ping {
-                debug!("Received Ping, sending Pong");
-                if let Err(e) = send_pong(&mut socket).await {
-                    warn!("Failed to send Pong: {:?}", e);
-                }
-                continue;
-            }
+        // 协议格式：[cmd: 2 bytes][payload: variable]
+        if packet.payload.len() < 2 {
+            crate::log_warn!("Packet payload too short");
+            return encode_ack(1, 0, packet.seq, output).map(Some);
+        }
 
-            // 解析命令（cmd 格式：2字节cmd + payload）
-            if packet.payload.len() >= 2 {
-                let cmd = BigEndian::read_u16(&packet.payload[0..2]);
-                let payload_data = &packet.payload[2..];
+        let cmd = BigEndian::read_u16(&packet.payload[0..2]);
+        let event = Event::NetworkIncoming {
+            cmd,
+            payload: packet.payload[2..].to_vec(),
+        };
+        debug!("Injecting NetworkIncoming event: cmd={:04X}", cmd);
+        self.event_tx
+            .publish(EventEnvelope::new(self.source, event).with_origin(conn))
+            .await;
 
-                // 创建 payload Vec
-                let mut payload_vec = Vec::new();
-                if payload_vec.extend_from_slice(payload_data).is_err() {
-                    warn!("Payload too large");
-                    continue;
-                }
+        encode_ack(0, cmd, packet.seq, output).map(Some)
+    }
+}
 
-                debug!("Processing cmd={}", cmd);
+/// 编码“已入队”应答：[error_code: 2B][cmd: 2B]
+fn encode_ack(error_code: u16, cmd: u16, seq: u8, output: &mut [u8]) -> Result<usize, TcpError> {
+    let mut body = [0u8; 4];
+    BigEndian::write_u16(&mut body[0..2], error_code);
+    BigEndian::write_u16(&mut body[2..4], cmd);
+    PacketCodec::encode(PacketType::Response, seq, &body, output).map_err(TcpError::from)
+}
 
-                // 路由处理消息
-                match router.handle_message(cmd, payload_vec) {
-                    Ok(response_data) => {
-                        // 发送响应
-                        if let Err(e) = send_response(&mut socket, 0, cmd, Some(response_data)).await {
-                            warn!("Failed to send response: {:?}", e);
-                        }
-                    }
-                    Err(_) => {
-                        // 发送错误响应
-                        if let Err(e) = send_response(&mut socket, 1, cmd, None).await {
-                            warn!("Failed to send error response: {:?}", e);
+/// 在任意传输上运行请求/应答分发，并推送发往连接 `conn` 的上行事件
+///
+/// 链路断开或读取失败时返回；连接的生命周期（Connection）由调用方持有
+pub async fn serve<T: Transport, D: Dispatcher>(
+    transport: &mut T,
+    dispatcher: &D,
+    conn: ConnectionId,
+) -> Result<(), TransportError> {
+    let mut tx_buffer = [0u8; MAX_FRAME_LEN];
+    let mut push_seq = 0u8;
+
+    // 订阅上行事件（返回时自动释放）
+    let mut uplink_rx = uplink::subscribe();
+
+    loop {
+        let incoming = match uplink_rx.as_mut() {
+            Some(sub) => match select(transport.recv_frame(), sub.next_message()).await {
+                Either::First(result) => result,
+                Either::Second(WaitResult::Message(msg)) if !msg.is_for(Some(conn)) => continue,
+                Either::Second(WaitResult::Message(msg)) => {
+                    match encode_push(&msg, push_seq, &mut tx_buffer) {
+                        Ok(len) => {
+                            push_seq = push_seq.wrapping_add(1);
+                            transport.send_frame(&tx_buffer[..len]).await?;
                         }
+                        Err(e) => crate::log_warn!("Failed to encode push: {:?}", e),
                     }
+                    continue;
+                }
+                Either::Second(WaitResult::Lagged(n)) => {
+                    crate::log_warn!("Uplink lagged, {} events dropped", n);
+                    continue;
+                }
+            },
+            None => transport.recv_frame().await,
+        };
+
+        let frame = match incoming {
+            Ok(frame) => frame,
+            Err(TransportError::Disconnected) => {
+                info!("Connection closed by peer");
+                return Err(TransportError::Disconnected);
+            }
+            Err(e) => {
+                crate::log_error!("Transport read error: {:?}", e);
+                return Err(e);
+            }
+        };
+
+        info!(
+            "Decoded packet: type={:?}, seq={}, len={}",
+            frame.packet_type,
+            frame.seq,
+            frame.payload.len()
+        );
+
+        match dispatcher.dispatch(conn, &frame.as_packet(), &mut tx_buffer).await {
+            Ok(Some(len)) => {
+                if let Err(e) = transport.send_frame(&tx_buffer[..len]).await {
+                    crate::log_warn!("Failed to send reply: {:?}", e);
                 }
-            } else {
-                warn!("Packet payload too short");
             }
+            Ok(None) => {}
+            Err(e) => crate::log_warn!("Failed to build reply: {:?}", e),
         }
     }
 }
 
-/// 发送 Pong 响应
-async fn send_pong(socket: &mut TcpSocket<'_>) -> Result<(), TcpError> {
-    let mut tx_buffer = [0u8; 8]; // 只需要头部
-    let len = PacketCodec::encode_simple(PacketType::Pong, 0, &mut tx_buffer)
-        .map_err(TcpError::from)?;
+/// 处理一个已解码的数据包（TCP、WebSocket 等流式传输共用）
+///
+/// 应答数据包编码到 `output`，返回其长度；无需应答时返回 `None`
+pub fn dispatch_packet(
+    router: &Router,
+    packet: &DecodedPacket<'_>,
+    output: &mut [u8],
+) -> Result<Option<usize>, TcpError> {
+    // 处理 Ping（自动响应 Pong）
+    if packet.packet_type == PacketType::Ping {
+        debug!("Received Ping, sending Pong");
+        return encode_pong(output).map(Some);
+    }
+
+    // 解析命令（cmd 格式：2字节cmd + payload）
+    if packet.payload.len() < 2 {
+        crate::log_warn!("Packet payload too short");
+        return Ok(None);
+    }
+
+    let cmd = BigEndian::read_u16(&packet.payload[0..2]);
+    let payload_data = &packet.payload[2..];
 
-    match socket.write(&tx_buffer[..len]).await {
-        Ok(n) if n == len => Ok(()),
-        _ => Err(TcpError::SendFailed),
+    // 创建 payload Vec
+    let mut payload_vec = Vec::new();
+    if payload_vec.extend_from_slice(payload_data).is_err() {
+        crate::log_warn!("Payload too large");
+        return Ok(None);
     }
+
+    debug!("Processing cmd={}", cmd);
+
+    // 路由处理消息
+    let len = match router.handle_message(cmd, payload_vec) {
+        Ok(response_data) => encode_response(0, cmd, Some(response_data), output)?,
+        // 发送错误响应
+        Err(_) => encode_response(1, cmd, None, output)?,
+    };
+
+    info!("Response built for cmd={}", cmd);
+    Ok(Some(len))
 }
 
-/// 发送响应
-async fn send_response(
-    socket: &mut TcpSocket<'_>,
+/// 将上行事件编码为推送数据包：Command 类型，载荷 [cmd: 2B][protobuf]
+pub fn encode_push(msg: &UplinkMessage, seq: u8, output: &mut [u8]) -> Result<usize, TcpError> {
+    let mut payload = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+    if payload.extend_from_slice(&msg.cmd.to_be_bytes()).is_err()
+        || payload.extend_from_slice(&msg.payload).is_err()
+    {
+        return Err(TcpError::CodecError(CodecError::PayloadTooLarge));
+    }
+
+    PacketCodec::encode(PacketType::Command, seq, &payload, output).map_err(TcpError::from)
+}
+
+/// 编码 Pong 响应
+pub fn encode_pong(output: &mut [u8]) -> Result<usize, TcpError> {
+    PacketCodec::encode_simple(PacketType::Pong, 0, output).map_err(TcpError::from)
+}
+
+/// 编码响应
+pub fn encode_response(
     error_code: u16,
     cmd: u16,
     payload: Option<Vec<u8, 512>>,
-) -> Result<(), TcpError> {
+    output: &mut [u8],
+) -> Result<usize, TcpError> {
     // 构建响应数据：error_code(2) + cmd(2) + payload
     let mut response = Vec::<u8, 1024>::new();
 
@@ -143,22 +283,106 @@ async fn send_response(
     }
 
     // 添加 payload
-    if let Some(data) = payload {
-        if response.extend_from_slice(&data).is_err() {
-            return Err(TcpError::Other);
-        }
+    if let Some(data) = payload
+        && response.extend_from_slice(&data).is_err()
+    {
+        return Err(TcpError::Other);
     }
 
     // 使用 Response 类型的数据包发送
-    let mut tx_buffer = [0u8; 1024 + 8];
-    let len = PacketCodec::encode(PacketType::Response, 0, &response, &mut tx_buffer)
-        .map_err(TcpError::from)?;
-
-    match socket.write(&tx_buffer[..len]).await {
-        Ok(n) if n == len => {
-            info!("Response sent: error_code={}, cmd={}", error_code, cmd);
-            Ok(())
-        }
-        _ => Err(TcpError::SendFailed),
+    PacketCodec::encode(PacketType::Response, 0, &response, output).map_err(TcpError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::router::example_handler;
+    use crate::net::transport::{Frame, LoopbackPair};
+    use embassy_futures::block_on;
+    use embassy_time::{with_timeout, Duration};
+
+    const ECHO_CMD: u16 = 0x0001;
+
+    /// 接收下一个类型不为 `skip` 的数据包（1 秒超时）
+    async fn recv_skipping<T: Transport>(host: &mut T, skip: Option<PacketType>) -> Frame {
+        with_timeout(Duration::from_secs(1), async {
+            loop {
+                let frame = host.recv_frame().await.unwrap();
+                if Some(frame.packet_type) != skip {
+                    return frame;
+                }
+            }
+        })
+        .await
+        .expect("no reply within 1 s")
+    }
+
+    /// 在环回对上运行 serve，主机端执行 `checks`
+    fn with_served_loopback(checks: impl AsyncFnOnce(&mut crate::net::transport::PipeTransport<'_>, ConnectionId)) {
+        let pair = LoopbackPair::new();
+        let (mut device, mut host) = pair.split();
+        let mut router = Router::new();
+        router.add_route(ECHO_CMD, example_handler);
+        let conn = Connection::open();
+
+        block_on(async {
+            match select(serve(&mut device, &router, conn.id()), checks(&mut host, conn.id())).await {
+                Either::First(result) => panic!("serve exited early: {:?}", result),
+                Either::Second(()) => {}
+            }
+        });
+    }
+
+    #[test]
+    fn serve_answers_ping_and_routes_commands() {
+        with_served_loopback(async |host, _| {
+            let mut frame = [0u8; 64];
+
+            let len = PacketCodec::encode_simple(PacketType::Ping, 1, &mut frame).unwrap();
+            host.send_frame(&frame[..len]).await.unwrap();
+            assert_eq!(recv_skipping(host, Some(PacketType::Command)).await.packet_type, PacketType::Pong);
+
+            // [cmd][数据] → [error_code=0][cmd][回显数据]，分两次写入
+            let payload = [0x00, 0x01, 0xDE, 0xAD];
+            let len = PacketCodec::encode(PacketType::Command, 2, &payload, &mut frame).unwrap();
+            host.send_frame(&frame[..3]).await.unwrap();
+            host.send_frame(&frame[3..len]).await.unwrap();
+
+            let reply = recv_skipping(host, Some(PacketType::Command)).await;
+            assert_eq!(reply.packet_type, PacketType::Response);
+            assert_eq!(reply.payload.as_slice(), &[0x00, 0x00, 0x00, 0x01, 0xDE, 0xAD]);
+        });
+    }
+
+    #[test]
+    fn serve_reports_unknown_commands_as_errors() {
+        with_served_loopback(async |host, _| {
+            let mut frame = [0u8; 64];
+            let len = PacketCodec::encode(PacketType::Command, 5, &[0x7F, 0xFE], &mut frame).unwrap();
+            host.send_frame(&frame[..len]).await.unwrap();
+
+            let reply = recv_skipping(host, Some(PacketType::Command)).await;
+            assert_eq!(reply.packet_type, PacketType::Response);
+            assert_eq!(reply.payload.as_slice(), &[0x00, 0x01, 0x7F, 0xFE]);
+        });
+    }
+
+    #[test]
+    fn serve_pushes_only_messages_for_its_connection() {
+        with_served_loopback(async |host, conn| {
+            // Ping 往返后 serve 已订阅上行
+            let mut frame = [0u8; 64];
+            let len = PacketCodec::encode_simple(PacketType::Ping, 1, &mut frame).unwrap();
+            host.send_frame(&frame[..len]).await.unwrap();
+            assert_eq!(recv_skipping(host, Some(PacketType::Command)).await.packet_type, PacketType::Pong);
+
+            let other = Connection::open();
+            uplink::publish_raw_to(Some(other.id()), 0x1002, alloc::vec![0x01]);
+            uplink::publish_raw_to(Some(conn), 0x1002, alloc::vec![0x02]);
+
+            let push = recv_skipping(host, None).await;
+            assert_eq!(push.packet_type, PacketType::Command);
+            assert_eq!(push.payload.as_slice(), &[0x10, 0x02, 0x02]);
+        });
     }
 }
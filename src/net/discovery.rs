@@ -0,0 +1,169 @@
+// UDP 发现服务 - 响应局域网广播查询，可选周期性广播信标
+//
+// 协议（纯文本，方便现场用任意工具调试）：
+// - 查询：主机向 255.255.255.255:DISCOVERY_PORT 广播 `CPDISCOVER`
+// - 应答：单播回查询方一行 JSON
+//   {"type":"reply","machine_id":"...","fw":"0.1.0","ip":"192.168.1.23",
+//    "tcp_port":8080,"faults":0,"max_severity":0}
+// - 信标：与应答格式相同，`type` 为 `beacon`，广播到 DISCOVERY_PORT
+
+use crate::app::{device, handlers::fault};
+use core::fmt::Write;
+use defmt::{debug, info};
+use embassy_futures::select::{select, Either};
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{IpAddress, IpEndpoint, Stack};
+use embassy_time::{Duration, Instant, Timer};
+use heapless::String;
+
+/// 默认发现端口
+pub const DISCOVERY_PORT: u16 = 48_899;
+
+/// 查询报文
+pub const DISCOVERY_QUERY: &[u8] = b"CPDISCOVER";
+
+/// 应答/信标报文最大长度
+const REPLY_MAX_LEN: usize = 256;
+
+/// UDP 发现服务配置
+#[derive(Clone, Copy)]
+pub struct DiscoveryConfig {
+    /// 监听端口
+    pub port: u16,
+    /// 应答中上报的 TCP 服务端口
+    pub tcp_port: u16,
+    /// 信标广播间隔（None=不广播，仅应答查询）
+    pub beacon_interval: Option<Duration>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            port: DISCOVERY_PORT,
+            tcp_port: 8080,
+            beacon_interval: None,
+        }
+    }
+}
+
+/// 报文类型
+#[derive(Clone, Copy)]
+enum ReplyKind {
+    Reply,
+    Beacon,
+}
+
+/// UDP 发现服务
+pub struct DiscoveryService {
+    config: DiscoveryConfig,
+}
+
+impl DiscoveryService {
+    /// 创建新的发现服务
+    pub const fn new(config: DiscoveryConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动发现服务
+    pub async fn start<'d>(&self, stack: &'static Stack<'d>) -> ! {
+        info!("Starting discovery service on UDP port {}", self.config.port);
+
+        let mut rx_meta = [PacketMetadata::EMPTY; 4];
+        let mut tx_meta = [PacketMetadata::EMPTY; 4];
+        let mut rx_buffer = [0u8; 128];
+        let mut tx_buffer = [0u8; 512];
+        let mut buf = [0u8; 64];
+
+        // 等待网络就绪（需要 IP 才能应答）
+        while !stack.is_config_up() {
+            Timer::after(Duration::from_secs(1)).await;
+        }
+
+        let mut socket = UdpSocket::new(
+            *stack,
+            &mut rx_meta,
+            &mut rx_buffer,
+            &mut tx_meta,
+            &mut tx_buffer,
+        );
+
+        if let Err(e) = socket.bind(self.config.port) {
+            crate::log_error!("Discovery bind error: {:?}", e);
+            loop {
+                Timer::after(Duration::from_secs(60)).await;
+            }
+        }
+
+        let broadcast = IpEndpoint::new(IpAddress::v4(255, 255, 255, 255), self.config.port);
+        let mut next_beacon = Instant::now();
+
+        loop {
+            let beacon_at = match self.config.beacon_interval {
+                Some(_) => next_beacon,
+                None => Instant::MAX,
+            };
+
+            match select(socket.recv_from(&mut buf), Timer::at(beacon_at)).await {
+                Either::First(Ok((n, meta))) => {
+                    if &buf[..n] != DISCOVERY_QUERY {
+                        debug!("Discovery: ignoring {} bytes from {:?}", n, meta.endpoint);
+                        continue;
+                    }
+
+                    info!("Discovery query from {:?}", meta.endpoint);
+                    let reply = self.build_reply(stack, ReplyKind::Reply);
+                    if let Err(e) = socket.send_to(reply.as_bytes(), meta.endpoint).await {
+                        crate::log_warn!("Discovery reply failed: {:?}", e);
+                    }
+                }
+                Either::First(Err(e)) => {
+                    crate::log_warn!("Discovery recv error: {:?}", e);
+                }
+                Either::Second(()) => {
+                    let beacon = self.build_reply(stack, ReplyKind::Beacon);
+                    if let Err(e) = socket.send_to(beacon.as_bytes(), broadcast).await {
+                        crate::log_warn!("Discovery beacon failed: {:?}", e);
+                    }
+                    if let Some(interval) = self.config.beacon_interval {
+                        next_beacon = Instant::now() + interval;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 构造应答/信标报文
+    fn build_reply(&self, stack: &Stack<'_>, kind: ReplyKind) -> String<REPLY_MAX_LEN> {
+        let faults = fault::fault_summary();
+        let mut out = String::new();
+
+        let kind = match kind {
+            ReplyKind::Reply => "reply",
+            ReplyKind::Beacon => "beacon",
+        };
+
+        // 缓冲区按最长报文预留，写入不会失败
+        let _ = write!(
+            out,
+            "{{\"type\":\"{}\",\"machine_id\":\"{}\",\"fw\":\"{}\",\"ip\":\"",
+            kind,
+            device::machine_id(),
+            device::FIRMWARE_VERSION,
+        );
+        match stack.config_v4() {
+            Some(config) => {
+                let _ = write!(out, "{}", config.address.address());
+            }
+            None => {
+                let _ = out.push_str("0.0.0.0");
+            }
+        }
+        let _ = write!(
+            out,
+            "\",\"tcp_port\":{},\"faults\":{},\"max_severity\":{}}}",
+            self.config.tcp_port, faults.active_count, faults.max_severity,
+        );
+
+        out
+    }
+}
@@ -0,0 +1,283 @@
+// 网关模式：一块联网 MCU 经 RS-485 前置多块无网口推币机
+//
+// 上行（TCP，单连接）数据包载荷均以 1 字节节点号开头：
+//
+//   后台 → 网关  Command   [node][cmd: 2B][protobuf]      node = 0xFF 广播
+//   网关 → 后台  Response  [node][error_code: 2B][cmd: 2B][data]
+//   网关 → 后台  Command   [node][cmd: 2B][protobuf]      节点上行事件
+//
+// error_code：0 成功 / 1 节点处理失败 / 2 节点超时 / 3 总线错误
+//
+// 网关轮询所有节点，收集上行事件转发给后台（后台未连接时缓存最近的事件，
+// 最多 EVENT_QUEUE_DEPTH 条，连接后补发；缓存满时丢弃新事件）；同时维护每个节点的健康状态
+// （最近应答时间、连续失败次数），连续失败达到阈值判为离线，上下线变化
+// 以 NODE_STATUS_CMD 事件推送：[node][0x10F0][online: 1B]
+
+use super::codec::PacketCodec;
+use super::connection::MAX_FRAME_LEN;
+use super::packet::{PacketType, MAX_PAYLOAD_LEN};
+use super::rs485::{NodeMessage, Rs485Error, Rs485Master, BROADCAST_ADDRESS};
+use super::transport::{TcpTransport, Transport, TransportError};
+use byteorder::{BigEndian, ByteOrder};
+use core::cell::RefCell;
+use defmt::{debug, info, Format};
+use embassy_futures::select::{select, Either};
+use embassy_net::{tcp::TcpSocket, Stack};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+
+/// 最大节点数
+pub const MAX_NODES: usize = 16;
+
+/// 节点上下线事件命令码
+pub const NODE_STATUS_CMD: u16 = 0x10F0;
+
+/// 应答错误码
+const ERR_OK: u16 = 0;
+const ERR_NODE_TIMEOUT: u16 = 2;
+const ERR_BUS: u16 = 3;
+
+/// 待转发事件队列深度
+const EVENT_QUEUE_DEPTH: usize = 16;
+
+/// 网关配置
+#[derive(Clone, Copy)]
+pub struct GatewayConfig {
+    /// 上行 TCP 端口
+    pub port: u16,
+    /// 上行连接接收超时
+    pub recv_timeout: Duration,
+    /// 连续失败多少次判为离线
+    pub offline_after: u8,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            port: 8090,
+            recv_timeout: Duration::from_secs(60),
+            offline_after: 3,
+        }
+    }
+}
+
+/// 节点健康状态
+#[derive(Debug, Clone, Copy, Default, Format)]
+pub struct NodeHealth {
+    pub address: u8,
+    pub online: bool,
+    /// 最近一次成功应答
+    pub last_seen: Option<Instant>,
+    /// 连续失败次数
+    pub consecutive_failures: u8,
+    /// 累计超时次数
+    pub timeouts: u32,
+}
+
+/// 网关
+pub struct Gateway<R, W> {
+    config: GatewayConfig,
+    master: Rs485Master<R, W>,
+    health: Mutex<CriticalSectionRawMutex, RefCell<Vec<NodeHealth, MAX_NODES>>>,
+    events: Channel<CriticalSectionRawMutex, NodeMessage, EVENT_QUEUE_DEPTH>,
+}
+
+impl<R: Read, W: Write> Gateway<R, W> {
+    pub fn new(config: GatewayConfig, master: Rs485Master<R, W>) -> Self {
+        let mut health = Vec::new();
+        for &address in master.nodes().iter().take(MAX_NODES) {
+            let _ = health.push(NodeHealth {
+                address,
+                ..Default::default()
+            });
+        }
+        Self {
+            config,
+            master,
+            health: Mutex::new(RefCell::new(health)),
+            events: Channel::new(),
+        }
+    }
+
+    /// 节点健康状态快照
+    pub fn health(&self) -> Vec<NodeHealth, MAX_NODES> {
+        self.health.lock(|h| h.borrow().clone())
+    }
+
+    /// 轮询任务主体：循环轮询所有节点，收集上行事件
+    pub async fn run_poller(&self) -> ! {
+        info!("Gateway polling {} nodes", self.master.nodes().len());
+
+        loop {
+            for &node in self.master.nodes() {
+                let result = self.master.poll(node).await;
+                self.record(node, result.as_ref().map(|_| ()).map_err(|e| *e));
+                if let Ok(Some(msg)) = result {
+                    self.forward(msg);
+                }
+            }
+            Timer::after(self.master.poll_interval()).await;
+        }
+    }
+
+    /// 上行 TCP 服务主体（单连接）
+    pub async fn start<'d>(&self, stack: &'static Stack<'d>) -> ! {
+        info!("Starting gateway uplink on port {}", self.config.port);
+
+        let mut rx_buf = [0u8; 2048];
+        let mut tx_buf = [0u8; 2048];
+
+        loop {
+            while !stack.is_link_up() {
+                Timer::after(Duration::from_secs(1)).await;
+            }
+
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(self.config.recv_timeout));
+
+            if let Err(e) = socket.accept(self.config.port).await {
+                crate::log_error!("Gateway accept error: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            info!("Gateway backend connected: {:?}", socket.remote_endpoint());
+
+            let mut transport = TcpTransport::new(socket);
+            if let Err(e) = self.serve(&mut transport).await {
+                crate::log_warn!("Gateway connection ended: {:?}", e);
+            }
+            transport.close().await;
+        }
+    }
+
+    /// 处理一个上行连接：后台命令转发给节点，节点事件推送给后台
+    async fn serve<T: Transport>(&self, transport: &mut T) -> Result<(), TransportError> {
+        let mut tx_buffer = [0u8; MAX_FRAME_LEN];
+        let mut push_seq = 0u8;
+
+        loop {
+            let frame = match select(transport.recv_frame(), self.events.receive()).await {
+                Either::First(frame) => frame?,
+                Either::Second(msg) => {
+                    let mut body = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+                    let ok = body.push(msg.node).is_ok()
+                        && body.extend_from_slice(&msg.cmd.to_be_bytes()).is_ok()
+                        && body.extend_from_slice(&msg.payload).is_ok();
+                    if ok
+                        && let Ok(len) = PacketCodec::encode(PacketType::Command, push_seq, &body, &mut tx_buffer)
+                    {
+                        push_seq = push_seq.wrapping_add(1);
+                        transport.send_frame(&tx_buffer[..len]).await?;
+                    }
+                    continue;
+                }
+            };
+
+            match frame.packet_type {
+                PacketType::Ping => {
+                    if let Ok(len) = PacketCodec::encode_simple(PacketType::Pong, frame.seq, &mut tx_buffer) {
+                        transport.send_frame(&tx_buffer[..len]).await?;
+                    }
+                }
+                PacketType::Command if frame.payload.len() >= 3 => {
+                    let node = frame.payload[0];
+                    let cmd = BigEndian::read_u16(&frame.payload[1..3]);
+                    let response = self.forward_command(node, cmd, &frame.payload[3..]).await;
+                    if let Ok(len) = PacketCodec::encode(PacketType::Response, frame.seq, &response, &mut tx_buffer) {
+                        transport.send_frame(&tx_buffer[..len]).await?;
+                    }
+                }
+                other => crate::log_warn!("Gateway: ignoring {:?} from backend", other),
+            }
+        }
+    }
+
+    /// 转发命令给节点，返回上行应答载荷 [node][error_code][cmd][data]
+    async fn forward_command(&self, node: u8, cmd: u16, body: &[u8]) -> Vec<u8, MAX_PAYLOAD_LEN> {
+        debug!("Gateway: cmd={:04X} -> node {}", cmd, node);
+
+        let mut response = Vec::new();
+        let _ = response.push(node);
+
+        let result = if node == BROADCAST_ADDRESS {
+            self.master.broadcast(cmd, body).await.map(|_| Vec::new())
+        } else {
+            let result = self.master.request(node, cmd, body).await;
+            self.record(node, result.as_ref().map(|_| ()).map_err(|e| *e));
+            result
+        };
+
+        match result {
+            // 广播无节点应答，直接确认
+            Ok(reply) if reply.is_empty() => {
+                let _ = response.extend_from_slice(&ERR_OK.to_be_bytes());
+                let _ = response.extend_from_slice(&cmd.to_be_bytes());
+            }
+            // 节点应答本身即 [error_code][cmd][data]
+            Ok(reply) => {
+                let _ = response.extend_from_slice(&reply);
+            }
+            Err(e) => {
+                crate::log_warn!("Gateway: node {} cmd={:04X} failed: {:?}", node, cmd, e);
+                let code = match e {
+                    Rs485Error::Timeout => ERR_NODE_TIMEOUT,
+                    _ => ERR_BUS,
+                };
+                let _ = response.extend_from_slice(&code.to_be_bytes());
+                let _ = response.extend_from_slice(&cmd.to_be_bytes());
+            }
+        }
+        response
+    }
+
+    /// 节点事件放入转发队列（后台未连接时留在队列中等待连接，队列满时丢弃）
+    fn forward(&self, msg: NodeMessage) {
+        if self.events.try_send(msg).is_err() {
+            crate::log_warn!("Gateway: event queue full, dropping node event");
+        }
+    }
+
+    /// 记录一次节点交互结果，上下线变化时推送状态事件
+    fn record(&self, node: u8, result: Result<(), Rs485Error>) {
+        let changed = self.health.lock(|h| {
+            let mut h = h.borrow_mut();
+            let entry = h.iter_mut().find(|n| n.address == node)?;
+            let was_online = entry.online;
+
+            match result {
+                Ok(()) => {
+                    entry.last_seen = Some(Instant::now());
+                    entry.consecutive_failures = 0;
+                    entry.online = true;
+                }
+                Err(e) => {
+                    if e == Rs485Error::Timeout {
+                        entry.timeouts += 1;
+                    }
+                    entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+                    if entry.consecutive_failures >= self.config.offline_after {
+                        entry.online = false;
+                    }
+                }
+            }
+
+            (entry.online != was_online).then_some(entry.online)
+        });
+
+        if let Some(online) = changed {
+            info!("Gateway: node {} {}", node, if online { "online" } else { "offline" });
+            let mut payload = Vec::new();
+            let _ = payload.push(online as u8);
+            self.forward(NodeMessage {
+                node,
+                cmd: NODE_STATUS_CMD,
+                payload,
+            });
+        }
+    }
+}
@@ -0,0 +1,417 @@
+// 简易 HTTP/1.1 状态与控制接口（现场用手机浏览器查看）
+//
+// 路由：
+// - GET  /、/status          JSON 状态文档（无需认证）
+// - POST /api/fault/clear    清除故障   [hardware_type, hardware_id]  -> cmd 0x2004
+// - POST /api/motor/test     测试马达   [motor, ms]                   -> cmd 0x2003
+// - POST /api/reboot         重启 MCU
+//
+// POST 需要 `Authorization: Bearer <token>`（token 由 HttpServerConfig 提供，没有缺省值）；
+// 参数可放在 query string 或 `application/x-www-form-urlencoded` 请求体中。
+// 控制类接口编码为与二进制协议相同的 protobuf 载荷，交给
+// handlers::network::on_network_message 处理，两条路径共用同一套处理器。
+
+use crate::app::handlers::{fault, network};
+use crate::app::state::{self, LIGHT_COUNT, MOTOR_TYPES};
+use crate::event::coinpusher::v1::{MotorCommandType, MotorType, M2003Tos, M2004Tos};
+use crate::event_bus;
+use core::fmt::Write;
+use defmt::{debug, info};
+use embassy_net::{tcp::TcpSocket, Stack};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::Write as _;
+use heapless::String;
+use prost::Message;
+
+/// 请求缓冲区大小（请求行 + 头部 + 请求体）
+const REQUEST_BUFFER_SIZE: usize = 1024;
+
+/// 状态文档最大长度
+const STATUS_MAX_LEN: usize = 2048;
+
+/// 测试马达默认运行时间
+const DEFAULT_MOTOR_TEST_MS: u32 = 1000;
+
+/// HTTP 服务器配置
+#[derive(Clone, Copy)]
+pub struct HttpServerConfig {
+    /// 监听端口
+    pub port: u16,
+    /// POST 接口的 Bearer token（必须由配置提供，空串视为未配置，拒绝全部 POST）
+    pub auth_token: &'static str,
+    /// 单个请求超时
+    pub recv_timeout: Duration,
+}
+
+impl HttpServerConfig {
+    /// 默认端口与超时，token 没有缺省值
+    pub const fn new(auth_token: &'static str) -> Self {
+        Self {
+            port: 80,
+            auth_token,
+            recv_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// HTTP 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+enum HttpError {
+    /// 请求格式错误
+    BadRequest,
+    /// 请求过大
+    TooLarge,
+    /// 连接断开
+    Disconnected,
+}
+
+/// 解析后的请求
+struct Request<'a> {
+    method: &'a str,
+    path: &'a str,
+    query: &'a str,
+    authorization: Option<&'a str>,
+    body: &'a str,
+}
+
+/// 请求处理结果
+enum Action {
+    /// 普通响应
+    Respond,
+    /// 响应后重启
+    Reboot,
+}
+
+/// HTTP 服务器（串行处理请求，每个请求一个连接）
+pub struct HttpServer {
+    config: HttpServerConfig,
+}
+
+impl HttpServer {
+    /// 创建新的 HTTP 服务器
+    pub const fn new(config: HttpServerConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动 HTTP 服务器
+    pub async fn start<'d>(&self, stack: &'static Stack<'d>) -> ! {
+        info!("Starting HTTP server on port {}", self.config.port);
+
+        let mut rx_buf = [0u8; REQUEST_BUFFER_SIZE];
+        let mut tx_buf = [0u8; STATUS_MAX_LEN];
+        let mut request_buf = [0u8; REQUEST_BUFFER_SIZE];
+
+        loop {
+            while !stack.is_link_up() {
+                Timer::after(Duration::from_secs(1)).await;
+            }
+
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(self.config.recv_timeout));
+
+            if let Err(e) = socket.accept(self.config.port).await {
+                crate::log_error!("HTTP accept error: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            debug!("HTTP client connected: {:?}", socket.remote_endpoint());
+
+            let action = match read_request(&mut socket, &mut request_buf).await {
+                Ok(len) => match core::str::from_utf8(&request_buf[..len]) {
+                    Ok(text) => match parse_request(text) {
+                        Some(request) => self.handle(&mut socket, stack, &request).await,
+                        None => respond_error(&mut socket, 400, "bad request").await,
+                    },
+                    Err(_) => respond_error(&mut socket, 400, "bad request").await,
+                },
+                Err(HttpError::TooLarge) => respond_error(&mut socket, 413, "request too large").await,
+                Err(HttpError::BadRequest) => respond_error(&mut socket, 400, "bad request").await,
+                Err(HttpError::Disconnected) => Action::Respond,
+            };
+
+            let _ = socket.flush().await;
+            socket.close();
+            Timer::after(Duration::from_millis(50)).await;
+            socket.abort();
+
+            if let Action::Reboot = action {
+                crate::log_warn!("Reboot requested over HTTP");
+                Timer::after(Duration::from_millis(200)).await;
+                crate::app::device::reboot();
+            }
+        }
+    }
+
+    /// 分发请求
+    async fn handle(
+        &self,
+        socket: &mut TcpSocket<'_>,
+        stack: &Stack<'_>,
+        request: &Request<'_>,
+    ) -> Action {
+        info!("HTTP {} {}", request.method, request.path);
+
+        match (request.method, request.path) {
+            ("GET", "/") | ("GET", "/status") => {
+                let body = render_status(stack);
+                respond(socket, 200, &body).await;
+                Action::Respond
+            }
+            ("POST", path) if path.starts_with("/api/") => {
+                if !self.authorized(request) {
+                    crate::log_warn!("HTTP unauthorized POST {}", path);
+                    return respond_error(socket, 401, "unauthorized").await;
+                }
+                self.handle_control(socket, request).await
+            }
+            ("GET", _) | ("POST", _) => respond_error(socket, 404, "not found").await,
+            _ => respond_error(socket, 405, "method not allowed").await,
+        }
+    }
+
+    /// 处理控制类请求
+    async fn handle_control(&self, socket: &mut TcpSocket<'_>, request: &Request<'_>) -> Action {
+        let result = match request.path {
+            "/api/fault/clear" => {
+                let cmd = M2004Tos {
+                    hardware_type: param_u32(request, "hardware_type").map(|v| v as i32),
+                    hardware_id: param_u32(request, "hardware_id"),
+                };
+                network::on_network_message(0x2004, cmd.encode_to_vec())
+            }
+            "/api/motor/test" => {
+                let cmd = M2003Tos {
+                    motor_type: param_u32(request, "motor")
+                        .map(|v| v as i32)
+                        .unwrap_or(MotorType::Pusher as i32),
+                    command: MotorCommandType::MotorCmdRunTime as i32,
+                    duration_ms: Some(param_u32(request, "ms").unwrap_or(DEFAULT_MOTOR_TEST_MS)),
+                    count: None,
+                    speed_level: None,
+                };
+                network::on_network_message(0x2003, cmd.encode_to_vec())
+            }
+            "/api/reboot" => {
+                respond(socket, 200, "{\"ok\":true}").await;
+                return Action::Reboot;
+            }
+            _ => return respond_error(socket, 404, "not found").await,
+        };
+
+        match result {
+            Ok(()) => {
+                respond(socket, 200, "{\"ok\":true}").await;
+                Action::Respond
+            }
+            Err(e) => {
+                crate::log_warn!("HTTP control failed: {:?}", e);
+                respond_error(socket, 400, "command rejected").await
+            }
+        }
+    }
+
+    /// 校验 Bearer token
+    fn authorized(&self, request: &Request<'_>) -> bool {
+        if self.config.auth_token.is_empty() {
+            return false;
+        }
+        match request.authorization {
+            Some(value) => value
+                .strip_prefix("Bearer ")
+                .map(|token| token.trim() == self.config.auth_token)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+}
+
+/// 读取完整请求（头部 + Content-Length 指定的请求体）
+async fn read_request(socket: &mut TcpSocket<'_>, buf: &mut [u8]) -> Result<usize, HttpError> {
+    let mut len = 0;
+
+    loop {
+        if len == buf.len() {
+            return Err(HttpError::TooLarge);
+        }
+
+        let n = match socket.read(&mut buf[len..]).await {
+            Ok(0) | Err(_) => return Err(HttpError::Disconnected),
+            Ok(n) => n,
+        };
+        len += n;
+
+        let Some(header_end) = find(&buf[..len], b"\r\n\r\n") else {
+            continue;
+        };
+
+        let head = core::str::from_utf8(&buf[..header_end]).map_err(|_| HttpError::BadRequest)?;
+        let content_length = head
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse::<usize>().map_err(|_| HttpError::BadRequest))
+            .transpose()?
+            .unwrap_or(0);
+
+        let total = header_end + 4 + content_length;
+        if total > buf.len() {
+            return Err(HttpError::TooLarge);
+        }
+        if len >= total {
+            return Ok(total);
+        }
+    }
+}
+
+/// 解析请求
+fn parse_request(text: &str) -> Option<Request<'_>> {
+    let (head, body) = text.split_once("\r\n\r\n")?;
+    let mut lines = head.split("\r\n");
+
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?;
+    let target = request_line.next()?;
+    if !request_line.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let authorization = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .map(|(_, value)| value.trim());
+
+    Some(Request {
+        method,
+        path,
+        query,
+        authorization,
+        body,
+    })
+}
+
+/// 查找参数（先 query string，后请求体）
+fn param_u32(request: &Request<'_>, name: &str) -> Option<u32> {
+    request
+        .query
+        .split('&')
+        .chain(request.body.split('&'))
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.trim() == name)
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+/// 子串查找
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// 渲染 JSON 状态文档
+fn render_status(stack: &Stack<'_>) -> String<STATUS_MAX_LEN> {
+    let now = Instant::now();
+    let machine = state::snapshot();
+    let faults = fault::fault_summary();
+    let mut out = String::new();
+
+    // 缓冲区按最长文档预留，写入不会失败
+    let _ = write!(
+        out,
+        "{{\"uptime_ms\":{},\"faults\":{{\"active\":{},\"max_severity\":{}}},\"motors\":[",
+        now.as_millis(),
+        faults.active_count,
+        faults.max_severity,
+    );
+
+    for (i, (motor_type, motor)) in MOTOR_TYPES.iter().zip(machine.motors.iter()).enumerate() {
+        let _ = write!(
+            out,
+            "{}{{\"type\":{},\"running\":{},\"speed\":{}",
+            if i > 0 { "," } else { "" },
+            *motor_type as i32,
+            motor.is_running(now),
+            motor.speed_level,
+        );
+        if let Some(ms) = motor.remaining_ms(now) {
+            let _ = write!(out, ",\"remaining_ms\":{}", ms);
+        }
+        if let Some(count) = motor.remaining_count {
+            let _ = write!(out, ",\"remaining_count\":{}", count);
+        }
+        let _ = out.push('}');
+    }
+
+    let _ = out.push_str("],\"lights\":[");
+    for id in 1..LIGHT_COUNT {
+        let light = machine.lights[id];
+        let _ = write!(
+            out,
+            "{}{{\"id\":{},\"on\":{},\"pattern\":{}}}",
+            if id > 1 { "," } else { "" },
+            id,
+            light.on,
+            light.pattern,
+        );
+    }
+
+    let overflow = event_bus::overflow_totals();
+    let _ = write!(
+        out,
+        "],\"counters\":{{\"coins_in\":{},\"payout\":{},\"coins_out\":{}}},\"events\":{{\"blocked\":{},\"coalesced\":{},\"dropped\":{},\"lagged\":{},\"staged\":{}}},\"link\":{{\"up\":{}",
+        machine.counters.coins_in,
+        machine.counters.payout,
+        machine.counters.coins_out,
+        overflow.blocked,
+        overflow.coalesced,
+        overflow.dropped,
+        overflow.lagged,
+        event_bus::staged_len(),
+        stack.is_link_up(),
+    );
+    if let Some(config) = stack.config_v4() {
+        let _ = write!(out, ",\"ip\":\"{}\"", config.address.address());
+        if let Some(gateway) = config.gateway {
+            let _ = write!(out, ",\"gateway\":\"{}\"", gateway);
+        }
+    }
+    let _ = out.push_str("}}");
+
+    out
+}
+
+/// 发送 JSON 响应
+async fn respond(socket: &mut TcpSocket<'_>, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Error",
+    };
+
+    let mut head: String<160> = String::new();
+    let _ = write!(
+        head,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len(),
+    );
+
+    if socket.write_all(head.as_bytes()).await.is_err()
+        || socket.write_all(body.as_bytes()).await.is_err()
+    {
+        crate::log_warn!("HTTP response write failed");
+    }
+}
+
+/// 发送错误响应
+async fn respond_error(socket: &mut TcpSocket<'_>, status: u16, message: &str) -> Action {
+    let mut body: String<96> = String::new();
+    let _ = write!(body, "{{\"ok\":false,\"error\":\"{}\"}}", message);
+    respond(socket, status, &body).await;
+    Action::Respond
+}
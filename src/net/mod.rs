@@ -4,6 +4,18 @@ pub mod packet;
 pub mod router;
 pub mod tcp_server;
 pub mod serial_transport;
+pub mod usb_transport;
+pub mod pipe;
+pub mod transport;
+pub mod rs485;
+pub mod gateway;
+pub mod can;
+pub mod discovery;
+pub mod http_server;
+pub mod uplink;
+pub mod mqtt;
+pub mod websocket;
+pub mod modbus;
 
 // 重新导出常用类型
 pub use codec::{CodecError, DecodedPacket, PacketCodec};
@@ -12,3 +24,14 @@ pub use packet::{Packet, PacketError, PacketHeader, PacketType};
 pub use router::{example_handler, Router};
 pub use tcp_server::{TcpServer, TcpServerConfig};
 pub use serial_transport::{SerialTransport, SerialTransportConfig};
+pub use usb_transport::{UsbSerial, UsbTransport, UsbTransportConfig};
+pub use pipe::{DuplexPipe, SerialPipe};
+pub use can::{CanEvent, CanFrame, CanLoopback, CanMaster, CanMasterConfig, CanNode, RemoteBoard};
+pub use gateway::{Gateway, GatewayConfig, NodeHealth};
+pub use rs485::{NodeMessage, Rs485Master, Rs485MasterConfig, Rs485Writer};
+pub use transport::{Frame, LinkState, LoopbackPair, StreamTransport, TcpTransport, Transport, TransportError};
+pub use discovery::{DiscoveryConfig, DiscoveryService};
+pub use http_server::{HttpServer, HttpServerConfig};
+pub use mqtt::{MqttClient, MqttConfig};
+pub use websocket::{WebSocketServer, WebSocketServerConfig};
+pub use modbus::{ModbusRtu, ModbusRtuConfig, ModbusTcpConfig, ModbusTcpServer};
@@ -0,0 +1,606 @@
+// Modbus 从站（TCP over embassy-net + RTU over UART）
+//
+// 寄存器映射：
+//
+// | 区域 | 地址 | 内容 | 读写 |
+// |------|------|------|------|
+// | 线圈 (0x) | 0~14 | 灯光 1~15 开关 | RW -> cmd 0x2002 |
+// | 线圈 (0x) | 100~104 | 马达运行（推盘/上币/回币/退币/彩票） | RW -> cmd 0x2003 START/STOP |
+// | 离散输入 (1x) | 0~15 | 按钮 0~15 | R |
+// | 离散输入 (1x) | 100 | 门开关（1=开） | R |
+// | 输入寄存器 (3x) | 0~1 | 运行时间 ms（u32，高字在前） | R |
+// | 输入寄存器 (3x) | 2 | 当前故障数量 | R |
+// | 输入寄存器 (3x) | 3 | 最高故障等级 | R |
+// | 输入寄存器 (3x) | 4~7 | 累计投币（u64，高字在前） | R |
+// | 输入寄存器 (3x) | 8~11 | 累计回币（u64，高字在前） | R |
+// | 保持寄存器 (4x) | 0~14 | 灯光 1~15 模式 | RW -> cmd 0x2002 |
+// | 保持寄存器 (4x) | 100~104 | 马达速度等级 | RW -> 调速（不改变运行状态） |
+// | 保持寄存器 (4x) | 1000~1011 | 输入寄存器 0~11 镜像 | R |
+//
+// 灯光与马达线圈写操作编码为 m_2002_tos / m_2003_tos 交给 handlers::network，
+// 与二进制协议走同一套命令逻辑；马达速度寄存器只调速（app::actuators 调速命令），
+// 不改变运行状态，定时 / 计数运行不受影响。
+//
+// 0x0F / 0x10 批量写入先校验全部地址、安全策略与执行器队列空间，
+// 全部通过后才写入，不会只生效一部分。
+
+use crate::app::actuators;
+use crate::app::handlers::{fault, network};
+use crate::app::safety;
+use crate::app::state::{self, MachineState, BUTTON_COUNT, MOTOR_COUNT, MOTOR_TYPES};
+use crate::event::coinpusher::v1::{
+    BoolFlag, M2002Tos, M2003Tos, MotorCommandType, SingleLightCommand,
+};
+use crate::drivers::actuator::MotorSpeed;
+use alloc::vec;
+use defmt::{debug, info, Format};
+use embassy_net::{tcp::TcpSocket, Stack};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+use prost::Message;
+
+/// PDU 最大长度
+const MAX_PDU_LEN: usize = 253;
+
+/// MBAP 头长度
+const MBAP_LEN: usize = 7;
+
+/// RTU 帧最大长度（地址 + PDU + CRC）
+const MAX_RTU_LEN: usize = MAX_PDU_LEN + 3;
+
+/// 灯光线圈 / 模式寄存器数量（灯光 1~15）
+const LIGHT_REGS: u16 = 15;
+
+/// 马达线圈 / 速度寄存器起始地址
+const MOTOR_BASE: u16 = 100;
+
+/// 门开关离散输入地址
+const DOOR_INPUT: u16 = 100;
+
+/// 输入寄存器数量
+const INPUT_REGS: u16 = 12;
+
+/// 输入寄存器镜像起始地址（保持寄存器区）
+const INPUT_MIRROR_BASE: u16 = 1000;
+
+/// 功能码
+const FC_READ_COILS: u8 = 0x01;
+const FC_READ_DISCRETE_INPUTS: u8 = 0x02;
+const FC_READ_HOLDING_REGISTERS: u8 = 0x03;
+const FC_READ_INPUT_REGISTERS: u8 = 0x04;
+const FC_WRITE_SINGLE_COIL: u8 = 0x05;
+const FC_WRITE_SINGLE_REGISTER: u8 = 0x06;
+const FC_WRITE_MULTIPLE_COILS: u8 = 0x0F;
+const FC_WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+/// Modbus 异常码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+#[repr(u8)]
+pub enum Exception {
+    IllegalFunction = 0x01,
+    IllegalDataAddress = 0x02,
+    IllegalDataValue = 0x03,
+    SlaveDeviceFailure = 0x04,
+}
+
+// ========== PDU 处理（TCP / RTU 共用） ==========
+
+/// 处理请求 PDU，将响应 PDU 写入 `resp`
+pub fn process_pdu(req: &[u8], resp: &mut Vec<u8, MAX_PDU_LEN>) {
+    resp.clear();
+    let Some(&function) = req.first() else {
+        return;
+    };
+
+    if let Err(e) = handle_function(function, &req[1..], resp) {
+        debug!("Modbus exception: fc={:02X}, {:?}", function, e);
+        resp.clear();
+        let _ = resp.push(function | 0x80);
+        let _ = resp.push(e as u8);
+    }
+}
+
+fn handle_function(
+    function: u8,
+    data: &[u8],
+    resp: &mut Vec<u8, MAX_PDU_LEN>,
+) -> Result<(), Exception> {
+    let word = |i: usize| -> Result<u16, Exception> {
+        data.get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+
+    match function {
+        FC_READ_COILS | FC_READ_DISCRETE_INPUTS => {
+            let (addr, qty) = (word(0)?, word(2)?);
+            if qty == 0 || qty > 2000 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let machine = state::snapshot();
+            let now = Instant::now();
+            let byte_count = qty.div_ceil(8) as usize;
+            push(resp, &[function, byte_count as u8])?;
+            let mut bytes = [0u8; 250];
+            for i in 0..qty {
+                let bit = if function == FC_READ_COILS {
+                    read_coil(&machine, now, addr.wrapping_add(i))
+                } else {
+                    read_discrete_input(&machine, addr.wrapping_add(i))
+                }
+                .ok_or(Exception::IllegalDataAddress)?;
+                if bit {
+                    bytes[i as usize / 8] |= 1 << (i % 8);
+                }
+            }
+            push(resp, &bytes[..byte_count])
+        }
+
+        FC_READ_HOLDING_REGISTERS | FC_READ_INPUT_REGISTERS => {
+            let (addr, qty) = (word(0)?, word(2)?);
+            if qty == 0 || qty > 125 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let machine = state::snapshot();
+            push(resp, &[function, (qty * 2) as u8])?;
+            for i in 0..qty {
+                let value = if function == FC_READ_HOLDING_REGISTERS {
+                    read_holding_register(&machine, addr.wrapping_add(i))
+                } else {
+                    read_input_register(&machine, addr.wrapping_add(i))
+                }
+                .ok_or(Exception::IllegalDataAddress)?;
+                push(resp, &value.to_be_bytes())?;
+            }
+            Ok(())
+        }
+
+        FC_WRITE_SINGLE_COIL => {
+            let (addr, value) = (word(0)?, word(2)?);
+            let on = match value {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue),
+            };
+            write_coil(addr, on)?;
+            // 响应为请求原样回显
+            push(resp, &[function])?;
+            push(resp, &data[..4])
+        }
+
+        FC_WRITE_SINGLE_REGISTER => {
+            let (addr, value) = (word(0)?, word(2)?);
+            write_holding_register(addr, value)?;
+            push(resp, &[function])?;
+            push(resp, &data[..4])
+        }
+
+        FC_WRITE_MULTIPLE_COILS => {
+            let (addr, qty) = (word(0)?, word(2)?);
+            let byte_count = *data.get(4).ok_or(Exception::IllegalDataValue)? as usize;
+            let values = data.get(5..5 + byte_count).ok_or(Exception::IllegalDataValue)?;
+            if qty == 0 || qty > 0x07B0 || byte_count != qty.div_ceil(8) as usize {
+                return Err(Exception::IllegalDataValue);
+            }
+            let coil = |i: u16| values[i as usize / 8] & (1 << (i % 8)) != 0;
+            // 先校验全部线圈，避免部分写入
+            for i in 0..qty {
+                check_coil(addr.wrapping_add(i), coil(i))?;
+            }
+            check_queue(qty)?;
+            for i in 0..qty {
+                write_coil(addr.wrapping_add(i), coil(i))?;
+            }
+            push(resp, &[function])?;
+            push(resp, &data[..4])
+        }
+
+        FC_WRITE_MULTIPLE_REGISTERS => {
+            let (addr, qty) = (word(0)?, word(2)?);
+            let byte_count = *data.get(4).ok_or(Exception::IllegalDataValue)? as usize;
+            if qty == 0 || qty > 123 || byte_count != qty as usize * 2 {
+                return Err(Exception::IllegalDataValue);
+            }
+            for i in 0..qty {
+                if !holding_writable(addr.wrapping_add(i)) {
+                    return Err(Exception::IllegalDataAddress);
+                }
+            }
+            check_queue(qty)?;
+            for i in 0..qty {
+                let value = word(5 + i as usize * 2)?;
+                write_holding_register(addr.wrapping_add(i), value)?;
+            }
+            push(resp, &[function])?;
+            push(resp, &data[..4])
+        }
+
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+fn push(resp: &mut Vec<u8, MAX_PDU_LEN>, data: &[u8]) -> Result<(), Exception> {
+    resp.extend_from_slice(data)
+        .map_err(|_| Exception::IllegalDataValue)
+}
+
+// ========== 寄存器映射 ==========
+
+fn read_coil(machine: &MachineState, now: Instant, addr: u16) -> Option<bool> {
+    match addr {
+        0..LIGHT_REGS => Some(machine.lights[addr as usize + 1].on),
+        a if motor_slot(a).is_some() => {
+            Some(machine.motors[motor_slot(a)?].is_running(now))
+        }
+        _ => None,
+    }
+}
+
+fn read_discrete_input(machine: &MachineState, addr: u16) -> Option<bool> {
+    match addr {
+        a if (a as usize) < BUTTON_COUNT => Some(machine.inputs.buttons[a as usize]),
+        DOOR_INPUT => Some(machine.inputs.door_open),
+        _ => None,
+    }
+}
+
+fn read_input_register(machine: &MachineState, addr: u16) -> Option<u16> {
+    let uptime_ms = Instant::now().as_millis() as u32;
+    let faults = fault::fault_summary();
+    let word64 = |value: u64, i: u16| (value >> (48 - 16 * i as u32)) as u16;
+
+    match addr {
+        0 => Some((uptime_ms >> 16) as u16),
+        1 => Some(uptime_ms as u16),
+        2 => Some(faults.active_count.min(u16::MAX as u32) as u16),
+        3 => Some(faults.max_severity as u16),
+        4..=7 => Some(word64(machine.counters.coins_in, addr - 4)),
+        8..=11 => Some(word64(machine.counters.payout, addr - 8)),
+        _ => None,
+    }
+}
+
+fn read_holding_register(machine: &MachineState, addr: u16) -> Option<u16> {
+    match addr {
+        0..LIGHT_REGS => Some(machine.lights[addr as usize + 1].pattern as u16),
+        a if motor_slot(a).is_some() => Some(machine.motors[motor_slot(a)?].speed_level as u16),
+        a if (INPUT_MIRROR_BASE..INPUT_MIRROR_BASE + INPUT_REGS).contains(&a) => {
+            read_input_register(machine, a - INPUT_MIRROR_BASE)
+        }
+        _ => None,
+    }
+}
+
+fn motor_slot(addr: u16) -> Option<usize> {
+    addr.checked_sub(MOTOR_BASE)
+        .map(|i| i as usize)
+        .filter(|i| *i < MOTOR_COUNT)
+}
+
+/// 写入前校验线圈：地址可写，马达启动须安全策略允许
+fn check_coil(addr: u16, on: bool) -> Result<(), Exception> {
+    if addr < LIGHT_REGS {
+        return Ok(());
+    }
+    let slot = motor_slot(addr).ok_or(Exception::IllegalDataAddress)?;
+    let command = if on {
+        MotorCommandType::MotorCmdStart
+    } else {
+        MotorCommandType::MotorCmdStop
+    };
+    if !safety::motor_allowed(MOTOR_TYPES[slot] as i32, command as i32) {
+        return Err(Exception::SlaveDeviceFailure);
+    }
+    Ok(())
+}
+
+/// 执行器队列能否容纳 `qty` 条命令（每个地址一条）
+fn check_queue(qty: u16) -> Result<(), Exception> {
+    if actuators::free_slots() < qty as usize {
+        crate::log_warn!("Modbus: actuator queue busy, {} writes refused", qty);
+        return Err(Exception::SlaveDeviceFailure);
+    }
+    Ok(())
+}
+
+fn holding_writable(addr: u16) -> bool {
+    addr < LIGHT_REGS || motor_slot(addr).is_some()
+}
+
+fn write_coil(addr: u16, on: bool) -> Result<(), Exception> {
+    let machine = state::snapshot();
+    if addr < LIGHT_REGS {
+        let light_id = addr as u32 + 1;
+        let pattern = machine.lights[light_id as usize].pattern;
+        return send_light_command(light_id, on, pattern);
+    }
+
+    let slot = motor_slot(addr).ok_or(Exception::IllegalDataAddress)?;
+    let command = if on {
+        MotorCommandType::MotorCmdStart
+    } else {
+        MotorCommandType::MotorCmdStop
+    };
+    send_motor_command(slot, command)
+}
+
+fn write_holding_register(addr: u16, value: u16) -> Result<(), Exception> {
+    let machine = state::snapshot();
+    if addr < LIGHT_REGS {
+        let light_id = addr as u32 + 1;
+        let on = machine.lights[light_id as usize].on;
+        return send_light_command(light_id, on, value as u32);
+    }
+
+    // 只改速度：运行状态（含 RUN_TIME / RUN_COUNT 进度）保持不变
+    let slot = motor_slot(addr).ok_or(Exception::IllegalDataAddress)?;
+    actuators::motor_speed(MotorSpeed {
+        motor_type: MOTOR_TYPES[slot] as i32,
+        speed_level: value as u32,
+    })
+    .map_err(|_| Exception::SlaveDeviceFailure)
+}
+
+fn send_light_command(light_id: u32, on: bool, pattern: u32) -> Result<(), Exception> {
+    let cmd = M2002Tos {
+        lights: vec![SingleLightCommand {
+            light_id,
+            on: if on { BoolFlag::BoolTrue } else { BoolFlag::BoolFalse } as i32,
+            pattern: Some(pattern),
+        }],
+    };
+    network::on_network_message(0x2002, cmd.encode_to_vec())
+        .map_err(|_| Exception::SlaveDeviceFailure)
+}
+
+fn send_motor_command(slot: usize, command: MotorCommandType) -> Result<(), Exception> {
+    let cmd = M2003Tos {
+        motor_type: MOTOR_TYPES[slot] as i32,
+        command: command as i32,
+        duration_ms: None,
+        count: None,
+        speed_level: None,
+    };
+    network::on_network_message(0x2003, cmd.encode_to_vec())
+        .map_err(|_| Exception::SlaveDeviceFailure)
+}
+
+// ========== Modbus TCP ==========
+
+/// Modbus TCP 服务器配置
+#[derive(Clone, Copy)]
+pub struct ModbusTcpConfig {
+    /// 监听端口
+    pub port: u16,
+    /// 空闲超时
+    pub recv_timeout: Duration,
+}
+
+impl Default for ModbusTcpConfig {
+    fn default() -> Self {
+        Self {
+            port: 502,
+            recv_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Modbus TCP 服务器（只处理单个连接）
+pub struct ModbusTcpServer {
+    config: ModbusTcpConfig,
+}
+
+impl ModbusTcpServer {
+    /// 创建新的 Modbus TCP 服务器
+    pub const fn new(config: ModbusTcpConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动 Modbus TCP 服务器
+    pub async fn start<'d>(&self, stack: &'static Stack<'d>) -> ! {
+        info!("Starting Modbus TCP server on port {}", self.config.port);
+
+        let mut rx_buf = [0u8; 512];
+        let mut tx_buf = [0u8; 512];
+
+        loop {
+            while !stack.is_link_up() {
+                Timer::after(Duration::from_secs(1)).await;
+            }
+
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(self.config.recv_timeout));
+
+            if let Err(e) = socket.accept(self.config.port).await {
+                crate::log_error!("Modbus accept error: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            info!("Modbus client connected: {:?}", socket.remote_endpoint());
+            serve_tcp(&mut socket).await;
+            info!("Modbus client disconnected");
+
+            socket.abort();
+            let _ = socket.flush().await;
+        }
+    }
+}
+
+/// 处理 Modbus TCP 连接直到断开
+async fn serve_tcp(socket: &mut TcpSocket<'_>) {
+    let mut buffer: Vec<u8, { MBAP_LEN + MAX_PDU_LEN }> = Vec::new();
+    let mut read_buf = [0u8; 260];
+    let mut resp: Vec<u8, MAX_PDU_LEN> = Vec::new();
+
+    loop {
+        // 缓冲区正好容纳一个最长的帧，只读取剩余空间，流水线 / 分段到达的请求不会溢出
+        let free = (buffer.capacity() - buffer.len()).min(read_buf.len());
+        let n = match socket.read(&mut read_buf[..free]).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        let _ = buffer.extend_from_slice(&read_buf[..n]);
+
+        while buffer.len() >= MBAP_LEN {
+            let protocol = u16::from_be_bytes([buffer[2], buffer[3]]);
+            let length = u16::from_be_bytes([buffer[4], buffer[5]]) as usize;
+            if protocol != 0 || !(2..=MAX_PDU_LEN + 1).contains(&length) {
+                crate::log_warn!("Modbus TCP invalid MBAP header");
+                return;
+            }
+
+            let total = MBAP_LEN - 1 + length;
+            if buffer.len() < total {
+                break;
+            }
+
+            process_pdu(&buffer[MBAP_LEN..total], &mut resp);
+
+            // MBAP：事务号、协议号、单元号原样返回
+            let mut header = [0u8; MBAP_LEN];
+            header[..4].copy_from_slice(&buffer[..4]);
+            header[4..6].copy_from_slice(&((resp.len() + 1) as u16).to_be_bytes());
+            header[6] = buffer[6];
+
+            if socket.write_all(&header).await.is_err() || socket.write_all(&resp).await.is_err() {
+                return;
+            }
+
+            buffer.as_mut_slice().copy_within(total.., 0);
+            buffer.truncate(buffer.len() - total);
+        }
+    }
+}
+
+// ========== Modbus RTU ==========
+
+/// Modbus RTU 从站配置
+#[derive(Clone, Copy)]
+pub struct ModbusRtuConfig {
+    /// 从站地址（1~247）
+    pub unit_id: u8,
+    /// 波特率（用于计算 3.5 字符帧间隔）
+    pub baud_rate: u32,
+}
+
+impl Default for ModbusRtuConfig {
+    fn default() -> Self {
+        Self {
+            unit_id: 1,
+            baud_rate: 9600,
+        }
+    }
+}
+
+/// Modbus RTU 从站（运行在任意 embedded-io-async 串口上）
+pub struct ModbusRtu {
+    config: ModbusRtuConfig,
+}
+
+impl ModbusRtu {
+    /// 创建新的 Modbus RTU 从站
+    pub const fn new(config: ModbusRtuConfig) -> Self {
+        Self { config }
+    }
+
+    /// 3.5 字符帧间隔（19200 以上固定 1.75ms）
+    fn frame_gap(&self) -> Duration {
+        if self.config.baud_rate > 19_200 {
+            Duration::from_micros(1750)
+        } else {
+            // 每字符 11 位
+            Duration::from_micros(35 * 11 * 100_000 / self.config.baud_rate as u64)
+        }
+    }
+
+    /// 启动 RTU 从站
+    pub async fn run<R: Read, W: Write>(&self, rx: &mut R, tx: &mut W) -> ! {
+        info!(
+            "Starting Modbus RTU slave: unit={}, baud={}",
+            self.config.unit_id, self.config.baud_rate
+        );
+
+        let gap = self.frame_gap();
+        let mut frame: Vec<u8, MAX_RTU_LEN> = Vec::new();
+        let mut read_buf = [0u8; 64];
+        let mut resp: Vec<u8, MAX_PDU_LEN> = Vec::new();
+
+        loop {
+            // 帧首字节无超时；之后静默超过帧间隔即视为帧结束
+            let result = if frame.is_empty() {
+                Ok(rx.read(&mut read_buf).await)
+            } else {
+                with_timeout(gap, rx.read(&mut read_buf)).await
+            };
+
+            match result {
+                Ok(Ok(n)) => {
+                    if frame.extend_from_slice(&read_buf[..n]).is_err() {
+                        crate::log_warn!("Modbus RTU frame too long, discarding");
+                        frame.clear();
+                    }
+                    continue;
+                }
+                Ok(Err(_)) => {
+                    crate::log_warn!("Modbus RTU read error");
+                    frame.clear();
+                    continue;
+                }
+                Err(_) => {
+                    // 帧间隔到达
+                }
+            }
+
+            if frame.len() < 4 || crc16(&frame[..frame.len() - 2]) != crc_from(&frame) {
+                debug!("Modbus RTU bad frame ({} bytes)", frame.len());
+                frame.clear();
+                continue;
+            }
+
+            let address = frame[0];
+            if address != self.config.unit_id && address != 0 {
+                frame.clear();
+                continue;
+            }
+
+            process_pdu(&frame[1..frame.len() - 2], &mut resp);
+
+            // 广播地址只执行不应答
+            if address != 0 {
+                let mut out: Vec<u8, MAX_RTU_LEN> = Vec::new();
+                let _ = out.push(address);
+                let _ = out.extend_from_slice(&resp);
+                let crc = crc16(&out);
+                let _ = out.extend_from_slice(&crc.to_le_bytes());
+                if tx.write_all(&out).await.is_err() {
+                    crate::log_warn!("Modbus RTU write error");
+                }
+            }
+
+            frame.clear();
+        }
+    }
+}
+
+/// 读取帧尾 CRC（低字节在前）
+fn crc_from(frame: &[u8]) -> u16 {
+    u16::from_le_bytes([frame[frame.len() - 2], frame[frame.len() - 1]])
+}
+
+/// Modbus CRC-16（多项式 0xA001，初值 0xFFFF）
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
@@ -0,0 +1,958 @@
+// MQTT 3.1.1 客户端传输层
+//
+// 与自定义 0xAA55 协议并列的另一种上行/下行通道：
+// - 上行：订阅 uplink 总线，按命令码发布到每台机器独立的事件主题
+//     {prefix}/{machine_id}/event/heartbeat   (1001, QoS 0)
+//     {prefix}/{machine_id}/event/button      (1003, QoS 1)
+//     {prefix}/{machine_id}/event/coin        (1004, QoS 1)
+//     {prefix}/{machine_id}/event/payout      (1005, QoS 1)
+//     {prefix}/{machine_id}/event/fault       (1006, QoS 1)
+//     ...
+// - 下行：订阅 {prefix}/{machine_id}/cmd，载荷格式与数据包载荷一致
+//   [cmd: 2 bytes BE][protobuf]，转换为 Event::NetworkIncoming 注入事件系统
+// - 在线状态：{prefix}/{machine_id}/status，连接后发布 retained `online`，
+//   遗嘱消息为 retained `offline`
+//
+// 会话：clean_session=0，断线重连后由 broker 恢复订阅和未投递的 QoS 1 消息；
+// 本地未确认的 QoS 1 发布在重连后带 DUP 标志重发；未确认数达到 MAX_INFLIGHT 时
+// 暂停读取上行总线，直到 PUBACK 空出位置（不丢弃未确认的消息）。
+// 命令主题以 QoS 1 订阅，收到 QoS 2 PUBLISH 视为协议错误并断开重连。
+// 超出接收缓冲区的报文按剩余长度跳过（QoS 1 PUBLISH 仍然 PUBACK，避免重连后 broker 反复重投）。
+
+use crate::app::device;
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
+use crate::net::uplink::{self, Connection, ConnectionId, UplinkMessage};
+use alloc::vec::Vec as AllocVec;
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt::Write as _;
+use defmt::{debug, info, Format};
+use embassy_futures::select::{select3, Either3};
+use embassy_net::{tcp::TcpSocket, IpEndpoint, Stack};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_io_async::Write as _;
+use heapless::{Deque, String, Vec};
+
+/// 单个 MQTT 报文最大长度（载荷 + 主题 + 头部）
+const MQTT_BUFFER_SIZE: usize = 1280;
+
+/// 主题最大长度
+const TOPIC_MAX_LEN: usize = 96;
+
+/// 最大未确认 QoS 1 发布数量
+const MAX_INFLIGHT: usize = 8;
+
+/// MQTT 报文类型（固定头部高 4 位）
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 0xD0;
+
+/// MQTT 客户端配置
+#[derive(Clone, Copy)]
+pub struct MqttConfig {
+    /// Broker 地址
+    pub broker: IpEndpoint,
+    /// 主题前缀
+    pub topic_prefix: &'static str,
+    /// 用户名
+    pub username: Option<&'static str>,
+    /// 密码
+    pub password: Option<&'static str>,
+    /// Keep Alive（秒）
+    pub keep_alive_secs: u16,
+    /// 重连间隔
+    pub reconnect_delay: Duration,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: IpEndpoint::new(embassy_net::IpAddress::v4(192, 168, 1, 2), 1883),
+            topic_prefix: "coinpusher",
+            username: None,
+            password: None,
+            keep_alive_secs: 30,
+            reconnect_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// MQTT 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum MqttError {
+    /// 连接断开
+    Disconnected,
+    /// 超时（CONNACK / PINGRESP）
+    Timeout,
+    /// Broker 拒绝连接（CONNACK 返回码）
+    Refused(u8),
+    /// 协议错误
+    Protocol,
+    /// 报文超出缓冲区
+    BufferTooSmall,
+}
+
+/// 未确认的 QoS 1 发布
+struct Inflight {
+    packet_id: u16,
+    cmd: u16,
+    payload: AllocVec<u8>,
+}
+
+/// 跨连接保留的会话状态
+struct Session {
+    next_packet_id: u16,
+    inflight: Deque<Inflight, MAX_INFLIGHT>,
+}
+
+impl Session {
+    fn next_id(&mut self) -> u16 {
+        self.next_packet_id = self.next_packet_id.wrapping_add(1);
+        if self.next_packet_id == 0 {
+            self.next_packet_id = 1;
+        }
+        self.next_packet_id
+    }
+}
+
+/// MQTT 客户端
+pub struct MqttClient {
+    config: MqttConfig,
+}
+
+impl MqttClient {
+    /// 创建新的 MQTT 客户端
+    pub const fn new(config: MqttConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动 MQTT 客户端（断线自动重连）
+    pub async fn start<'d>(
+        &self,
+        stack: &'static Stack<'d>,
+        event_tx: EventPublisher,
+    ) -> ! {
+        info!("Starting MQTT client, broker {:?}", self.config.broker);
+
+        let Some(mut uplink_rx) = uplink::subscribe() else {
+            crate::log_error!("MQTT: no uplink subscriber slot");
+            loop {
+                Timer::after(Duration::from_secs(60)).await;
+            }
+        };
+
+        let mut session = Session {
+            next_packet_id: 0,
+            inflight: Deque::new(),
+        };
+
+        let mut rx_buf = [0u8; MQTT_BUFFER_SIZE * 2];
+        let mut tx_buf = [0u8; MQTT_BUFFER_SIZE * 2];
+
+        loop {
+            while !stack.is_config_up() {
+                Timer::after(Duration::from_secs(1)).await;
+            }
+
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(Duration::from_secs(self.config.keep_alive_secs as u64 * 2)));
+
+            if let Err(e) = socket.connect(self.config.broker).await {
+                crate::log_warn!("MQTT connect error: {:?}", e);
+            } else if let Err(e) = self
+                .run_connection(&mut socket, &mut session, &mut uplink_rx, &event_tx)
+                .await
+            {
+                crate::log_warn!("MQTT connection lost: {:?}", e);
+            }
+
+            socket.abort();
+            let _ = socket.flush().await;
+
+            info!(
+                "MQTT reconnecting in {} ms ({} unacked)",
+                self.config.reconnect_delay.as_millis(),
+                session.inflight.len()
+            );
+            Timer::after(self.config.reconnect_delay).await;
+        }
+    }
+
+    /// 处理一次连接，直到断开
+    async fn run_connection(
+        &self,
+        socket: &mut TcpSocket<'_>,
+        session: &mut Session,
+        uplink_rx: &mut uplink::UplinkSubscriber,
+        event_tx: &EventPublisher,
+    ) -> Result<(), MqttError> {
+        let (mut reader, mut writer) = socket.split();
+        let mut out: Vec<u8, MQTT_BUFFER_SIZE> = Vec::new();
+        let mut frame = FrameReader::new();
+        let mut read_buf = [0u8; 256];
+        // 断开（返回）时释放，清理本次连接的状态订阅
+        let conn = Connection::open();
+
+        // ========== CONNECT / CONNACK ==========
+        self.encode_connect(&mut out)?;
+        send(&mut writer, &out).await?;
+
+        let keep_alive = Duration::from_secs(self.config.keep_alive_secs as u64);
+        let session_present = with_timeout(keep_alive, async {
+            loop {
+                let n = read_some(&mut reader, frame.read_window(&mut read_buf)).await?;
+                frame.feed(&read_buf[..n])?;
+                if let Some(packet) = frame.next_packet() {
+                    let RxPacket::Complete(header, body) = packet else {
+                        return Err(MqttError::Protocol);
+                    };
+                    if header & 0xF0 != CONNACK || body.len() < 2 {
+                        return Err(MqttError::Protocol);
+                    }
+                    if body[1] != 0 {
+                        return Err(MqttError::Refused(body[1]));
+                    }
+                    let present = body[0] & 0x01 != 0;
+                    frame.consume();
+                    return Ok(present);
+                }
+            }
+        })
+        .await
+        .map_err(|_| MqttError::Timeout)??;
+
+        info!("MQTT connected (session_present={})", session_present);
+
+        // 会话不存在时需要重新订阅命令主题
+        if !session_present {
+            let topic = self.topic("cmd");
+            let packet_id = session.next_id();
+            encode_subscribe(&mut out, packet_id, &topic)?;
+            send(&mut writer, &out).await?;
+        }
+
+        // 在线状态
+        encode_publish(&mut out, &self.topic("status"), b"online", false, None, true)?;
+        send(&mut writer, &out).await?;
+
+        // 重发未确认的 QoS 1 消息
+        for msg in session.inflight.iter() {
+            debug!("MQTT resend packet_id={}", msg.packet_id);
+            let topic = self.event_topic(msg.cmd);
+            encode_publish(&mut out, &topic, &msg.payload, true, Some(msg.packet_id), false)?;
+            send(&mut writer, &out).await?;
+        }
+
+        // ========== 主循环 ==========
+        let mut next_ping = Instant::now() + keep_alive;
+        let mut ping_sent_at: Option<Instant> = None;
+
+        loop {
+            // 未确认数已满时不再读取上行总线（背压），等待 PUBACK
+            let inflight_full = session.inflight.is_full();
+            let uplink = async {
+                if inflight_full {
+                    core::future::pending().await
+                } else {
+                    uplink_rx.next_message().await
+                }
+            };
+
+            match select3(
+                reader.read(frame.read_window(&mut read_buf)),
+                uplink,
+                Timer::at(next_ping),
+            )
+            .await
+            {
+                Either3::First(result) => {
+                    let n = match result {
+                        Ok(0) | Err(_) => return Err(MqttError::Disconnected),
+                        Ok(n) => n,
+                    };
+                    frame.feed(&read_buf[..n])?;
+
+                    while let Some(packet) = frame.next_packet() {
+                        let (header, body) = match packet {
+                            RxPacket::Complete(header, body) => (header, body),
+                            RxPacket::Skipped { header, packet_id } => {
+                                crate::log_warn!(
+                                    "MQTT packet {:02X} exceeds {} bytes, skipped",
+                                    header,
+                                    MQTT_BUFFER_SIZE
+                                );
+                                if header & 0xF0 == PUBLISH {
+                                    check_publish_qos(header)?;
+                                    if let Some(packet_id) = packet_id {
+                                        encode_ack(&mut out, PUBACK, packet_id)?;
+                                        send(&mut writer, &out).await?;
+                                    }
+                                }
+                                continue;
+                            }
+                        };
+                        match header & 0xF0 {
+                            PUBLISH => {
+                                let puback = self.handle_publish(header, body, conn.id(), event_tx).await?;
+                                frame.consume();
+                                if let Some(packet_id) = puback {
+                                    encode_ack(&mut out, PUBACK, packet_id)?;
+                                    send(&mut writer, &out).await?;
+                                }
+                                continue;
+                            }
+                            PUBACK if body.len() >= 2 => {
+                                let packet_id = BigEndian::read_u16(&body[0..2]);
+                                ack_inflight(session, packet_id);
+                            }
+                            SUBACK if body.len() >= 3 => {
+                                if body[2] == 0x80 {
+                                    crate::log_error!("MQTT subscribe rejected by broker");
+                                } else {
+                                    info!("MQTT subscribed (granted QoS {})", body[2]);
+                                }
+                            }
+                            PINGRESP => {
+                                ping_sent_at = None;
+                            }
+                            other => {
+                                debug!("MQTT ignoring packet type {:02X}", other);
+                            }
+                        }
+                        frame.consume();
+                    }
+                }
+
+                Either3::Second(WaitResult::Lagged(n)) => {
+                    crate::log_warn!("MQTT uplink lagged, {} messages dropped", n);
+                }
+
+                Either3::Second(WaitResult::Message(msg)) if !msg.is_for(Some(conn.id())) => {}
+
+                Either3::Second(WaitResult::Message(msg)) => {
+                    self.publish_event(&mut writer, &mut out, session, msg).await?;
+                }
+
+                Either3::Third(()) => {
+                    if let Some(sent) = ping_sent_at {
+                        if Instant::now() - sent >= keep_alive {
+                            return Err(MqttError::Timeout);
+                        }
+                    } else {
+                        out.clear();
+                        let _ = out.extend_from_slice(&[PINGREQ, 0]);
+                        send(&mut writer, &out).await?;
+                        ping_sent_at = Some(Instant::now());
+                    }
+                    next_ping = Instant::now() + keep_alive / 2;
+                }
+            }
+        }
+    }
+
+    /// 处理下行 PUBLISH，返回需要确认的 packet id
+    async fn handle_publish(
+        &self,
+        header: u8,
+        body: &[u8],
+        conn: ConnectionId,
+        event_tx: &EventPublisher,
+    ) -> Result<Option<u16>, MqttError> {
+        let Publish { topic, packet_id, payload } = decode_publish(header, body)?;
+
+        if topic != self.topic("cmd").as_bytes() {
+            debug!("MQTT ignoring publish on foreign topic");
+            return Ok(packet_id);
+        }
+
+        if payload.len() < 2 {
+            crate::log_warn!("MQTT command payload too short");
+            return Ok(packet_id);
+        }
+
+        let cmd = BigEndian::read_u16(&payload[0..2]);
+        let mut data = AllocVec::new();
+        data.extend_from_slice(&payload[2..]);
+
+        debug!("MQTT command cmd={:04X}, {} bytes", cmd, data.len());
+        event_tx
+            .publish(
+                EventEnvelope::new(Source::Network, Event::NetworkIncoming { cmd, payload: data })
+                    .with_origin(conn),
+            )
+            .await;
+
+        Ok(packet_id)
+    }
+
+    /// 发布一条上行事件
+    async fn publish_event(
+        &self,
+        writer: &mut embassy_net::tcp::TcpWriter<'_>,
+        out: &mut Vec<u8, MQTT_BUFFER_SIZE>,
+        session: &mut Session,
+        msg: UplinkMessage,
+    ) -> Result<(), MqttError> {
+        let topic = self.event_topic(msg.cmd);
+
+        // 心跳频繁且可替代，使用 QoS 0；其余事件 QoS 1
+        if msg.cmd == 0x1001 {
+            encode_publish(out, &topic, &msg.payload, false, None, false)?;
+            return send(writer, out).await;
+        }
+
+        let packet_id = session.next_id();
+        encode_publish(out, &topic, &msg.payload, false, Some(packet_id), false)?;
+
+        // 主循环在未确认数已满时不读取上行总线，这里总有空位
+        let _ = session.inflight.push_back(Inflight {
+            packet_id,
+            cmd: msg.cmd,
+            payload: msg.payload,
+        });
+
+        send(writer, out).await
+    }
+
+    /// 编码 CONNECT 报文
+    fn encode_connect(&self, out: &mut Vec<u8, MQTT_BUFFER_SIZE>) -> Result<(), MqttError> {
+        let will_topic = self.topic("status");
+        let client_id = device::machine_id();
+
+        // 标志位：clean_session=0，will QoS 1 + retain
+        let mut flags = 0x04 | 0x08 | 0x20;
+        if self.config.username.is_some() {
+            flags |= 0x80;
+        }
+        if self.config.password.is_some() {
+            flags |= 0x40;
+        }
+
+        let mut body: Vec<u8, MQTT_BUFFER_SIZE> = Vec::new();
+        put_str(&mut body, b"MQTT")?;
+        put(&mut body, &[0x04, flags])?;
+        put(&mut body, &self.config.keep_alive_secs.to_be_bytes())?;
+        put_str(&mut body, client_id.as_bytes())?;
+        put_str(&mut body, will_topic.as_bytes())?;
+        put_str(&mut body, b"offline")?;
+        if let Some(username) = self.config.username {
+            put_str(&mut body, username.as_bytes())?;
+        }
+        if let Some(password) = self.config.password {
+            put_str(&mut body, password.as_bytes())?;
+        }
+
+        finish(out, CONNECT, &body)
+    }
+
+    /// 机器主题：{prefix}/{machine_id}/{suffix}
+    fn topic(&self, suffix: &str) -> String<TOPIC_MAX_LEN> {
+        let mut topic = String::new();
+        let _ = write!(topic, "{}/{}/{}", self.config.topic_prefix, device::machine_id(), suffix);
+        topic
+    }
+
+    /// 事件主题
+    fn event_topic(&self, cmd: u16) -> String<TOPIC_MAX_LEN> {
+        let name = match cmd {
+            0x1001 => "heartbeat",
+            0x1002 => "status",
+            0x1003 => "button",
+            0x1004 => "coin",
+            0x1005 => "payout",
+            0x1006 => "fault",
+            0x1007 => "result",
+            _ => "other",
+        };
+        let mut topic = String::new();
+        let _ = write!(
+            topic,
+            "{}/{}/event/{}",
+            self.config.topic_prefix,
+            device::machine_id(),
+            name
+        );
+        topic
+    }
+}
+
+/// 确认 QoS 1 发布
+fn ack_inflight(session: &mut Session, packet_id: u16) {
+    let before = session.inflight.len();
+    let mut kept: Deque<Inflight, MAX_INFLIGHT> = Deque::new();
+    while let Some(msg) = session.inflight.pop_front() {
+        if msg.packet_id != packet_id {
+            let _ = kept.push_back(msg);
+        }
+    }
+    session.inflight = kept;
+    if session.inflight.len() == before {
+        debug!("MQTT PUBACK for unknown packet_id={}", packet_id);
+    }
+}
+
+/// 读取数据
+async fn read_some(
+    reader: &mut embassy_net::tcp::TcpReader<'_>,
+    buf: &mut [u8],
+) -> Result<usize, MqttError> {
+    match reader.read(buf).await {
+        Ok(0) | Err(_) => Err(MqttError::Disconnected),
+        Ok(n) => Ok(n),
+    }
+}
+
+/// 发送报文
+async fn send(
+    writer: &mut embassy_net::tcp::TcpWriter<'_>,
+    out: &[u8],
+) -> Result<(), MqttError> {
+    writer.write_all(out).await.map_err(|_| MqttError::Disconnected)
+}
+
+// ========== 报文编码 ==========
+
+fn put<const N: usize>(buf: &mut Vec<u8, N>, data: &[u8]) -> Result<(), MqttError> {
+    buf.extend_from_slice(data).map_err(|_| MqttError::BufferTooSmall)
+}
+
+fn put_str<const N: usize>(buf: &mut Vec<u8, N>, s: &[u8]) -> Result<(), MqttError> {
+    put(buf, &(s.len() as u16).to_be_bytes())?;
+    put(buf, s)
+}
+
+/// 写入固定头部 + 剩余长度 + 报文体
+fn finish<const N: usize>(out: &mut Vec<u8, N>, header: u8, body: &[u8]) -> Result<(), MqttError> {
+    out.clear();
+    put(out, &[header])?;
+
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        put(out, &[byte])?;
+        if len == 0 {
+            break;
+        }
+    }
+
+    put(out, body)
+}
+
+/// 编码 PUBLISH（packet_id 为 None 时使用 QoS 0）
+fn encode_publish<const N: usize>(
+    out: &mut Vec<u8, N>,
+    topic: &str,
+    payload: &[u8],
+    dup: bool,
+    packet_id: Option<u16>,
+    retain: bool,
+) -> Result<(), MqttError> {
+    let mut header = PUBLISH;
+    if dup {
+        header |= 0x08;
+    }
+    if packet_id.is_some() {
+        header |= 0x02;
+    }
+    if retain {
+        header |= 0x01;
+    }
+
+    let mut body: Vec<u8, MQTT_BUFFER_SIZE> = Vec::new();
+    put_str(&mut body, topic.as_bytes())?;
+    if let Some(id) = packet_id {
+        put(&mut body, &id.to_be_bytes())?;
+    }
+    put(&mut body, payload)?;
+
+    finish(out, header, &body)
+}
+
+/// 编码 SUBSCRIBE（请求 QoS 1）
+fn encode_subscribe<const N: usize>(
+    out: &mut Vec<u8, N>,
+    packet_id: u16,
+    topic: &str,
+) -> Result<(), MqttError> {
+    let mut body: Vec<u8, { TOPIC_MAX_LEN + 8 }> = Vec::new();
+    put(&mut body, &packet_id.to_be_bytes())?;
+    put_str(&mut body, topic.as_bytes())?;
+    put(&mut body, &[0x01])?;
+    finish(out, SUBSCRIBE, &body)
+}
+
+/// 编码 PUBACK 等仅含 packet id 的报文
+fn encode_ack<const N: usize>(out: &mut Vec<u8, N>, header: u8, packet_id: u16) -> Result<(), MqttError> {
+    finish(out, header, &packet_id.to_be_bytes())
+}
+
+// ========== 报文解码 ==========
+
+/// 解码后的 PUBLISH
+struct Publish<'a> {
+    topic: &'a [u8],
+    /// QoS 1 的 packet id
+    packet_id: Option<u16>,
+    payload: &'a [u8],
+}
+
+/// 只以 QoS 1 订阅，broker 不应下发 QoS 2（未实现 PUBREC/PUBREL/PUBCOMP）；
+/// 按协议错误断开，不能用 PUBACK 应答
+fn check_publish_qos(header: u8) -> Result<u8, MqttError> {
+    let qos = (header >> 1) & 0x03;
+    if qos > 1 {
+        crate::log_warn!("MQTT unexpected QoS {} publish, disconnecting", qos);
+        return Err(MqttError::Protocol);
+    }
+    Ok(qos)
+}
+
+/// 解码 PUBLISH 报文体
+fn decode_publish(header: u8, body: &[u8]) -> Result<Publish<'_>, MqttError> {
+    let qos = check_publish_qos(header)?;
+    if body.len() < 2 {
+        return Err(MqttError::Protocol);
+    }
+    let topic_len = BigEndian::read_u16(&body[0..2]) as usize;
+    let mut pos = 2 + topic_len;
+    if body.len() < pos {
+        return Err(MqttError::Protocol);
+    }
+    let topic = &body[2..pos];
+
+    let packet_id = if qos > 0 {
+        if body.len() < pos + 2 {
+            return Err(MqttError::Protocol);
+        }
+        let id = BigEndian::read_u16(&body[pos..pos + 2]);
+        pos += 2;
+        Some(id)
+    } else {
+        None
+    };
+
+    Ok(Publish {
+        topic,
+        packet_id,
+        payload: &body[pos..],
+    })
+}
+
+// ========== 报文分帧 ==========
+
+/// 分帧结果
+enum RxPacket<'a> {
+    /// 完整报文：(固定头部, 报文体)
+    Complete(u8, &'a [u8]),
+    /// 超出缓冲区的报文，按剩余长度跳过；QoS 1 / 2 的 PUBLISH 带 packet id 以便确认
+    Skipped { header: u8, packet_id: Option<u16> },
+}
+
+/// 从 TCP 字节流中切分 MQTT 报文
+struct FrameReader {
+    buffer: Vec<u8, MQTT_BUFFER_SIZE>,
+    /// 当前报文总长度（next_packet 成功后有效）
+    current_len: usize,
+    /// 被跳过的超长报文尚未收到的字节数
+    skip: usize,
+}
+
+impl FrameReader {
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            current_len: 0,
+            skip: 0,
+        }
+    }
+
+    /// 本次读取可用的缓冲区（不超过剩余空间，feed 不会溢出）
+    fn read_window<'b>(&self, read_buf: &'b mut [u8]) -> &'b mut [u8] {
+        let len = (self.buffer.capacity() - self.buffer.len()).min(read_buf.len());
+        &mut read_buf[..len]
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<(), MqttError> {
+        let skipped = self.skip.min(data.len());
+        self.skip -= skipped;
+        self.buffer
+            .extend_from_slice(&data[skipped..])
+            .map_err(|_| MqttError::BufferTooSmall)
+    }
+
+    /// 取出下一个报文
+    fn next_packet(&mut self) -> Option<RxPacket<'_>> {
+        if self.buffer.len() < 2 {
+            return None;
+        }
+
+        let mut len = 0usize;
+        let mut multiplier = 1usize;
+        let mut pos = 1;
+        loop {
+            let byte = *self.buffer.get(pos)?;
+            len += (byte & 0x7F) as usize * multiplier;
+            pos += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            multiplier *= 128;
+            if pos > 4 {
+                // 剩余长度最多 4 字节，数据已损坏
+                self.buffer.clear();
+                return None;
+            }
+        }
+
+        let header = self.buffer[0];
+        let total = pos + len;
+        if total > self.buffer.capacity() {
+            // 超长报文：PUBLISH 先等到 packet id 到达，再丢弃已缓冲的部分并跳过剩余字节
+            let packet_id = if header & 0xF0 == PUBLISH && (header >> 1) & 0x03 > 0 {
+                let topic_len = BigEndian::read_u16(self.buffer.get(pos..pos + 2)?) as usize;
+                let id_at = pos + 2 + topic_len;
+                if id_at + 2 > self.buffer.capacity() {
+                    // 主题本身超出缓冲区，无法取得 packet id
+                    None
+                } else {
+                    Some(BigEndian::read_u16(self.buffer.get(id_at..id_at + 2)?))
+                }
+            } else {
+                None
+            };
+            self.skip = total - self.buffer.len();
+            self.buffer.clear();
+            return Some(RxPacket::Skipped { header, packet_id });
+        }
+
+        if self.buffer.len() < total {
+            return None;
+        }
+
+        self.current_len = total;
+        Some(RxPacket::Complete(header, &self.buffer[pos..total]))
+    }
+
+    /// 丢弃 next_packet 返回的报文
+    fn consume(&mut self) {
+        let n = self.current_len.min(self.buffer.len());
+        self.buffer.as_mut_slice().copy_within(n.., 0);
+        self.buffer.truncate(self.buffer.len() - n);
+        self.current_len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> MqttClient {
+        MqttClient::new(MqttConfig {
+            username: Some("user"),
+            password: Some("secret"),
+            ..MqttConfig::default()
+        })
+    }
+
+    fn publish_packet(topic: &str, payload: &[u8], packet_id: Option<u16>) -> Vec<u8, { MQTT_BUFFER_SIZE * 4 }> {
+        let mut out = Vec::new();
+        encode_publish(&mut out, topic, payload, false, packet_id, false).unwrap();
+        out
+    }
+
+    /// 按剩余长度编码一个超长 PUBLISH（载荷大于 MQTT_BUFFER_SIZE）
+    fn oversized_publish(packet_id: u16) -> std::vec::Vec<u8> {
+        let payload = std::vec![0xEE; MQTT_BUFFER_SIZE + 100];
+        let mut body = std::vec::Vec::new();
+        body.extend_from_slice(&3u16.to_be_bytes());
+        body.extend_from_slice(b"a/b");
+        body.extend_from_slice(&packet_id.to_be_bytes());
+        body.extend_from_slice(&payload);
+
+        let mut out = std::vec![PUBLISH | 0x02];
+        let mut len = body.len();
+        loop {
+            let byte = (len % 128) as u8;
+            len /= 128;
+            out.push(if len > 0 { byte | 0x80 } else { byte });
+            if len == 0 {
+                break;
+            }
+        }
+        out.extend_from_slice(&body);
+        out
+    }
+
+    /// 按 read_window 分块输入，收集完整报文与跳过的报文
+    fn drain(reader: &mut FrameReader, stream: &[u8]) -> std::vec::Vec<(u8, std::vec::Vec<u8>, Option<u16>)> {
+        let mut packets = std::vec::Vec::new();
+        let mut read_buf = [0u8; 256];
+        let mut rest = stream;
+        while !rest.is_empty() {
+            let window = reader.read_window(&mut read_buf);
+            let n = window.len().min(rest.len());
+            assert!(n > 0, "reader stalled with a full buffer");
+            window[..n].copy_from_slice(&rest[..n]);
+            rest = &rest[n..];
+            reader.feed(&read_buf[..n]).unwrap();
+            while let Some(packet) = reader.next_packet() {
+                match packet {
+                    RxPacket::Complete(header, body) => {
+                        packets.push((header, body.to_vec(), None));
+                        reader.consume();
+                    }
+                    RxPacket::Skipped { header, packet_id } => packets.push((header, std::vec::Vec::new(), packet_id)),
+                }
+            }
+        }
+        packets
+    }
+
+    #[test]
+    fn connect_carries_session_will_and_credentials() {
+        let mut out = Vec::new();
+        client().encode_connect(&mut out).unwrap();
+
+        let mut reader = FrameReader::new();
+        reader.feed(&out).unwrap();
+        let Some(RxPacket::Complete(header, body)) = reader.next_packet() else {
+            panic!("CONNECT not framed");
+        };
+        assert_eq!(header, CONNECT);
+        assert_eq!(&body[..7], &[0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04]);
+        // username + password + will retain + will QoS 1 + will flag，clean_session = 0
+        assert_eq!(body[7], 0x80 | 0x40 | 0x20 | 0x08 | 0x04);
+        assert_eq!(BigEndian::read_u16(&body[8..10]), 30);
+
+        let client_id = device::machine_id().as_bytes();
+        assert_eq!(BigEndian::read_u16(&body[10..12]) as usize, client_id.len());
+        assert_eq!(&body[12..12 + client_id.len()], client_id);
+        assert!(body.ends_with(b"\x00\x04user\x00\x06secret"));
+    }
+
+    #[test]
+    fn publish_round_trips_through_the_frame_reader() {
+        let payload = [0x5Au8; 300];
+        let out = publish_packet("coinpusher/x/cmd", &payload, Some(0x1234));
+
+        let mut reader = FrameReader::new();
+        let packets = drain(&mut reader, &out);
+        assert_eq!(packets.len(), 1);
+        let (header, body, _) = &packets[0];
+        assert_eq!(*header, PUBLISH | 0x02);
+
+        let publish = decode_publish(*header, body).unwrap();
+        assert_eq!(publish.topic, b"coinpusher/x/cmd");
+        assert_eq!(publish.packet_id, Some(0x1234));
+        assert_eq!(publish.payload, &payload);
+    }
+
+    #[test]
+    fn qos0_publish_has_no_packet_id_and_qos2_is_refused() {
+        let out = publish_packet("t", b"hi", None);
+        let mut reader = FrameReader::new();
+        let packets = drain(&mut reader, &out);
+        let publish = decode_publish(packets[0].0, &packets[0].1).unwrap();
+        assert_eq!(publish.packet_id, None);
+        assert_eq!(publish.payload, b"hi");
+
+        assert_eq!(decode_publish(PUBLISH | 0x04, &packets[0].1).err(), Some(MqttError::Protocol));
+    }
+
+    #[test]
+    fn puback_encodes_packet_id() {
+        let mut out: Vec<u8, 8> = Vec::new();
+        encode_ack(&mut out, PUBACK, 0xBEEF).unwrap();
+        assert_eq!(out.as_slice(), &[PUBACK, 0x02, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn reader_splits_back_to_back_packets_fed_byte_by_byte() {
+        let mut stream = std::vec::Vec::new();
+        stream.extend_from_slice(&[CONNACK, 0x02, 0x01, 0x00]);
+        stream.extend_from_slice(&[PUBACK, 0x02, 0x00, 0x07]);
+        stream.extend_from_slice(&[PINGRESP, 0x00]);
+
+        let mut reader = FrameReader::new();
+        let mut packets = std::vec::Vec::new();
+        for byte in &stream {
+            reader.feed(core::slice::from_ref(byte)).unwrap();
+            while let Some(RxPacket::Complete(header, body)) = reader.next_packet() {
+                packets.push((header, body.to_vec()));
+                reader.consume();
+            }
+        }
+        assert_eq!(
+            packets,
+            std::vec![
+                (CONNACK, std::vec![0x01, 0x00]),
+                (PUBACK, std::vec![0x00, 0x07]),
+                (PINGRESP, std::vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_publish_is_skipped_with_its_packet_id() {
+        let mut stream = oversized_publish(0x0042);
+        stream.extend_from_slice(&publish_packet("a/b", b"next", Some(0x0043)));
+
+        let mut reader = FrameReader::new();
+        let packets = drain(&mut reader, &stream);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].0, PUBLISH | 0x02);
+        assert_eq!(packets[0].2, Some(0x0042));
+
+        // 跳过之后的报文照常分帧
+        let publish = decode_publish(packets[1].0, &packets[1].1).unwrap();
+        assert_eq!(publish.packet_id, Some(0x0043));
+        assert_eq!(publish.payload, b"next");
+    }
+
+    #[test]
+    fn read_window_never_exceeds_the_free_space() {
+        let mut reader = FrameReader::new();
+        // 一个未完成的报文占满大半缓冲区
+        let out = publish_packet("t", &[0u8; MQTT_BUFFER_SIZE - 20], Some(1));
+        reader.feed(&out[..MQTT_BUFFER_SIZE - 50]).unwrap();
+        assert!(reader.next_packet().is_none());
+
+        let mut read_buf = [0u8; 256];
+        assert_eq!(reader.read_window(&mut read_buf).len(), 50);
+    }
+
+    #[test]
+    fn puback_releases_only_the_matching_inflight_message() {
+        let mut session = Session {
+            next_packet_id: 0,
+            inflight: Deque::new(),
+        };
+        for _ in 0..MAX_INFLIGHT {
+            let packet_id = session.next_id();
+            session
+                .inflight
+                .push_back(Inflight { packet_id, cmd: 0x1004, payload: AllocVec::new() })
+                .ok()
+                .unwrap();
+        }
+        assert!(session.inflight.is_full());
+
+        ack_inflight(&mut session, 3);
+        assert_eq!(session.inflight.len(), MAX_INFLIGHT - 1);
+        assert!(session.inflight.iter().all(|m| m.packet_id != 3));
+
+        // 未知 id 不影响队列
+        ack_inflight(&mut session, 99);
+        assert_eq!(session.inflight.len(), MAX_INFLIGHT - 1);
+    }
+}
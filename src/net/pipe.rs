@@ -0,0 +1,168 @@
+// 内存双工管道（替代真实串口，用于 Demo 与主机侧测试）
+//
+// 两个方向各一个 embassy_sync::pipe::Pipe：
+//   device 端：读 host→device，写 device→host
+//   host 端：  读 device→host，写 host→device
+// &Pipe 同时实现 embedded_io_async::Read / Write，可直接交给 SerialTransport
+//
+// 与真实串口一致：缓冲满时写入方等待（背压），对端不写时读取方一直等待，没有 EOF；
+// `disconnect()` 模拟网桥掉线重连，丢弃两个方向未读的数据
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pipe::Pipe;
+
+/// 单方向缓冲容量
+pub const PIPE_CAPACITY: usize = 512;
+
+/// 单方向管道
+pub type SerialPipe = Pipe<CriticalSectionRawMutex, PIPE_CAPACITY>;
+
+/// 管道一端：(读, 写)
+pub type PipeEnd<'a> = (&'a SerialPipe, &'a SerialPipe);
+
+/// 双工管道
+pub struct DuplexPipe {
+    to_device: SerialPipe,
+    to_host: SerialPipe,
+}
+
+impl DuplexPipe {
+    pub const fn new() -> Self {
+        Self {
+            to_device: Pipe::new(),
+            to_host: Pipe::new(),
+        }
+    }
+
+    /// 设备端（交给 SerialTransport）
+    pub fn device_end(&self) -> PipeEnd<'_> {
+        (&self.to_device, &self.to_host)
+    }
+
+    /// 主机端（模拟网桥 / 上位机）
+    pub fn host_end(&self) -> PipeEnd<'_> {
+        (&self.to_host, &self.to_device)
+    }
+
+    /// 断开：丢弃两个方向未读的数据，因缓冲满而等待的写入方随即继续
+    pub fn disconnect(&self) {
+        self.to_device.clear();
+        self.to_host.clear();
+    }
+}
+
+impl Default for DuplexPipe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::codec::PacketCodec;
+    use crate::net::packet::PacketType;
+    use crate::net::transport::FrameDecoder;
+    use embassy_futures::block_on;
+    use embassy_futures::join::join;
+    use embassy_futures::select::{select, Either};
+    use embassy_time::Timer;
+
+    /// `fut` 在 20ms 内是否仍未完成
+    async fn stays_pending(fut: impl core::future::Future) -> bool {
+        matches!(select(fut, Timer::after_millis(20)).await, Either::Second(()))
+    }
+
+    #[test]
+    fn full_pipe_holds_the_writer_until_the_reader_drains() {
+        let pipe = DuplexPipe::new();
+        let (_, device_tx) = pipe.device_end();
+        let (host_rx, _) = pipe.host_end();
+        let data: std::vec::Vec<u8> = (0..PIPE_CAPACITY + 8).map(|i| i as u8).collect();
+
+        block_on(async {
+            // 填满后写入方等待，数据不丢
+            device_tx.write_all(&data[..PIPE_CAPACITY]).await;
+            assert!(stays_pending(device_tx.write_all(&data[PIPE_CAPACITY..])).await);
+
+            let mut received = std::vec::Vec::new();
+            let mut buf = [0u8; 64];
+            let write = device_tx.write_all(&data[PIPE_CAPACITY..]);
+            let read = async {
+                while received.len() < data.len() {
+                    let n = host_rx.read(&mut buf).await;
+                    received.extend_from_slice(&buf[..n]);
+                }
+            };
+            join(write, read).await;
+            assert_eq!(received, data);
+        });
+    }
+
+    #[test]
+    fn each_end_reads_only_what_the_other_end_wrote() {
+        let pipe = DuplexPipe::new();
+        let (device_rx, device_tx) = pipe.device_end();
+        let (host_rx, host_tx) = pipe.host_end();
+        let mut buf = [0u8; 8];
+
+        block_on(async {
+            host_tx.write_all(&[1, 2, 3]).await;
+            assert!(stays_pending(host_rx.read(&mut buf)).await);
+            assert_eq!(device_rx.read(&mut buf).await, 3);
+            assert_eq!(&buf[..3], &[1, 2, 3]);
+
+            device_tx.write_all(&[4]).await;
+            assert_eq!(host_rx.read(&mut buf).await, 1);
+            assert_eq!(buf[0], 4);
+        });
+    }
+
+    #[test]
+    fn quiet_peer_is_an_idle_line_not_end_of_stream() {
+        let pipe = DuplexPipe::new();
+        let (mut device_rx, _) = pipe.device_end();
+        let (_, host_tx) = pipe.host_end();
+        let mut frame = [0u8; 16];
+        let len = PacketCodec::encode(PacketType::Command, 5, &[0x20, 0x01], &mut frame).unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        block_on(async {
+            // 对端发出半包后不再发送：读取一直等待，不会报告断开
+            host_tx.write_all(&frame[..len / 2]).await;
+            assert!(stays_pending(decoder.recv(&mut device_rx)).await);
+
+            // 重新同步（SerialLink 读超时的做法）后，下一个完整的包正常解出
+            decoder.reset();
+            host_tx.write_all(&frame[..len]).await;
+            let received = decoder.recv(&mut device_rx).await.unwrap();
+            assert_eq!(received.seq, 5);
+            assert_eq!(received.payload.as_slice(), &[0x20, 0x01]);
+        });
+    }
+
+    #[test]
+    fn disconnect_drops_unread_bytes_and_releases_a_blocked_writer() {
+        let pipe = DuplexPipe::new();
+        let (device_rx, device_tx) = pipe.device_end();
+        let (_, host_tx) = pipe.host_end();
+        let mut buf = [0u8; 8];
+
+        block_on(async {
+            host_tx.write_all(&[0xAA; PIPE_CAPACITY]).await;
+            device_tx.write_all(&[0x55; 4]).await;
+
+            let write = host_tx.write_all(&[7, 8]);
+            let disconnect = async {
+                Timer::after_millis(10).await;
+                pipe.disconnect();
+            };
+            join(write, disconnect).await;
+
+            // 断开前未读的数据被丢弃，只剩断开后写入的
+            assert_eq!(device_rx.read(&mut buf).await, 2);
+            assert_eq!(&buf[..2], &[7, 8]);
+            assert!(pipe.host_end().0.is_empty());
+        });
+    }
+}
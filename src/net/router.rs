@@ -1,6 +1,6 @@
 // 命令路由器（简化版）
 use crate::error::Result;
-use defmt::{info, warn};
+use defmt::info;
 use heapless::Vec;
 
 /// 路由器最大路由数量
@@ -50,7 +50,7 @@ impl Router {
             }
         }
 
-        warn!("No handler found for cmd {}", cmd);
+        crate::log_warn!("No handler found for cmd {}", cmd);
         Err(crate::error::Error::NotFound)
     }
 }
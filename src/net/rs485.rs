@@ -0,0 +1,310 @@
+// RS-485 多机寻址（同一总线串接多台推币机）
+//
+// 帧格式：标准数据包，载荷前加 2 字节地址头（受 checksum 保护）
+//
+//     [AA55 | type | seq | len | checksum] [dst: 1B][src: 1B][原载荷...]
+//
+// - 主站地址 0x00，从站地址 1 ~ 247，0xFF 为广播（从站执行但不应答）
+// - 从站只处理 dst 为本机或广播的帧，其余忽略
+// - 半双工：只有主站可以主动发起；从站的上行事件缓存在本机，
+//   主站轮询（Ping）时每次取走一条，无事件时回 Pong
+// - DE/RE 由 Rs485Writer 控制：写出前拉高并等待换向时间，flush 完成后释放
+
+use super::codec::{CodecError, PacketCodec};
+use super::packet::{PacketType, MAX_PAYLOAD_LEN};
+use super::transport::{Frame, FrameDecoder};
+use byteorder::{BigEndian, ByteOrder};
+use defmt::{debug, info, Format};
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, RawMutex};
+use embassy_sync::channel::Sender;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_hal::digital::OutputPin;
+use embedded_io_async::{ErrorType, Read, Write};
+use heapless::Vec;
+
+/// 主站地址
+pub const MASTER_ADDRESS: u8 = 0x00;
+
+/// 广播地址
+pub const BROADCAST_ADDRESS: u8 = 0xFF;
+
+/// 地址头长度
+pub const ADDRESS_HEADER_LEN: usize = 2;
+
+/// 编码带地址头的数据包
+pub fn encode_addressed(
+    packet_type: PacketType,
+    seq: u8,
+    dst: u8,
+    src: u8,
+    body: &[u8],
+    output: &mut [u8],
+) -> Result<usize, CodecError> {
+    let mut payload = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+    if payload.extend_from_slice(&[dst, src]).is_err() || payload.extend_from_slice(body).is_err() {
+        return Err(CodecError::PayloadTooLarge);
+    }
+    PacketCodec::encode(packet_type, seq, &payload, output)
+}
+
+/// 拆分地址头，返回 (dst, src, 原载荷)
+pub fn split_address(payload: &[u8]) -> Option<(u8, u8, &[u8])> {
+    if payload.len() < ADDRESS_HEADER_LEN {
+        return None;
+    }
+    Some((payload[0], payload[1], &payload[ADDRESS_HEADER_LEN..]))
+}
+
+/// 半双工发送端：写出期间驱动 DE/RE
+///
+/// 第一次 write 前拉高 DE 并等待换向时间，flush（发送完成）后拉低 DE 释放总线
+pub struct Rs485Writer<W, P> {
+    inner: W,
+    de: P,
+    turnaround: Duration,
+    driving: bool,
+}
+
+impl<W: Write, P: OutputPin> Rs485Writer<W, P> {
+    pub fn new(inner: W, mut de: P, turnaround: Duration) -> Self {
+        let _ = de.set_low();
+        Self {
+            inner,
+            de,
+            turnaround,
+            driving: false,
+        }
+    }
+}
+
+impl<W: Write, P> ErrorType for Rs485Writer<W, P> {
+    type Error = W::Error;
+}
+
+impl<W: Write, P: OutputPin> Write for Rs485Writer<W, P> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if !self.driving {
+            let _ = self.de.set_high();
+            self.driving = true;
+            Timer::after(self.turnaround).await;
+        }
+        self.inner.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let result = self.inner.flush().await;
+        let _ = self.de.set_low();
+        self.driving = false;
+        result
+    }
+}
+
+/// 主站错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Rs485Error {
+    /// 等待应答超时
+    Timeout,
+    /// 串口读写失败
+    Io,
+    /// 编码失败（载荷过长）
+    Encode,
+}
+
+/// 从站上行消息（主站轮询得到）
+#[derive(Debug, Clone)]
+pub struct NodeMessage {
+    /// 从站地址
+    pub node: u8,
+    pub cmd: u16,
+    pub payload: Vec<u8, MAX_PAYLOAD_LEN>,
+}
+
+/// 主站配置
+#[derive(Clone, Copy)]
+pub struct Rs485MasterConfig {
+    /// 轮询的从站地址
+    pub nodes: &'static [u8],
+    /// 单次请求应答超时
+    pub reply_timeout: Duration,
+    /// 一轮轮询结束后的间隔
+    pub poll_interval: Duration,
+    /// 广播后的静默时间（给从站处理）
+    pub broadcast_gap: Duration,
+}
+
+impl Default for Rs485MasterConfig {
+    fn default() -> Self {
+        Self {
+            nodes: &[1, 2, 3, 4],
+            reply_timeout: Duration::from_millis(100),
+            poll_interval: Duration::from_millis(50),
+            broadcast_gap: Duration::from_millis(20),
+        }
+    }
+}
+
+/// 总线（收发 + 解码状态）
+struct Bus<R, W> {
+    rx: R,
+    tx: W,
+    decoder: FrameDecoder,
+    seq: u8,
+}
+
+impl<R: Read, W: Write> Bus<R, W> {
+    async fn send(&mut self, packet_type: PacketType, dst: u8, body: &[u8]) -> Result<u8, Rs485Error> {
+        let mut frame = [0u8; super::connection::MAX_FRAME_LEN];
+        self.seq = self.seq.wrapping_add(1);
+        let len = encode_addressed(packet_type, self.seq, dst, MASTER_ADDRESS, body, &mut frame)
+            .map_err(|_| Rs485Error::Encode)?;
+
+        // 丢弃上一轮残留的半包 / 迟到应答
+        self.decoder.reset();
+        self.tx.write_all(&frame[..len]).await.map_err(|_| Rs485Error::Io)?;
+        self.tx.flush().await.map_err(|_| Rs485Error::Io)?;
+        Ok(self.seq)
+    }
+
+    /// 等待来自 `node`、发给主站的帧（载荷仍含地址头）
+    async fn recv_from(&mut self, node: u8, timeout: Duration) -> Result<Frame, Rs485Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let frame = match with_timeout(remaining, self.decoder.recv(&mut self.rx)).await {
+                Ok(Ok(frame)) => frame,
+                Ok(Err(_)) => return Err(Rs485Error::Io),
+                Err(_) => return Err(Rs485Error::Timeout),
+            };
+            match split_address(&frame.payload) {
+                Some((MASTER_ADDRESS, src, _)) if src == node => return Ok(frame),
+                _ => debug!("RS-485: ignoring frame not from node {}", node),
+            }
+        }
+    }
+}
+
+/// RS-485 主站：轮询从站、下发命令、广播
+pub struct Rs485Master<R, W> {
+    config: Rs485MasterConfig,
+    bus: Mutex<CriticalSectionRawMutex, Bus<R, W>>,
+}
+
+impl<R: Read, W: Write> Rs485Master<R, W> {
+    pub fn new(config: Rs485MasterConfig, rx: R, tx: W) -> Self {
+        Self {
+            config,
+            bus: Mutex::new(Bus {
+                rx,
+                tx,
+                decoder: FrameDecoder::new(),
+                seq: 0,
+            }),
+        }
+    }
+
+    /// 轮询的从站地址
+    pub fn nodes(&self) -> &'static [u8] {
+        self.config.nodes
+    }
+
+    /// 一轮轮询结束后的间隔
+    pub fn poll_interval(&self) -> Duration {
+        self.config.poll_interval
+    }
+
+    /// 向从站发送命令并等待应答
+    ///
+    /// 返回应答载荷（去掉地址头）：[error_code: 2B][cmd: 2B][data]
+    pub async fn request(
+        &self,
+        node: u8,
+        cmd: u16,
+        body: &[u8],
+    ) -> Result<Vec<u8, MAX_PAYLOAD_LEN>, Rs485Error> {
+        let mut payload = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+        if payload.extend_from_slice(&cmd.to_be_bytes()).is_err()
+            || payload.extend_from_slice(body).is_err()
+        {
+            return Err(Rs485Error::Encode);
+        }
+
+        let mut bus = self.bus.lock().await;
+        bus.send(PacketType::Command, node, &payload).await?;
+        loop {
+            let frame = bus.recv_from(node, self.config.reply_timeout).await?;
+            if frame.packet_type == PacketType::Response {
+                let mut response = Vec::new();
+                let _ = response.extend_from_slice(&frame.payload[ADDRESS_HEADER_LEN..]);
+                return Ok(response);
+            }
+        }
+    }
+
+    /// 广播命令（所有从站执行，无应答）
+    pub async fn broadcast(&self, cmd: u16, body: &[u8]) -> Result<(), Rs485Error> {
+        let mut payload = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+        if payload.extend_from_slice(&cmd.to_be_bytes()).is_err()
+            || payload.extend_from_slice(body).is_err()
+        {
+            return Err(Rs485Error::Encode);
+        }
+
+        let mut bus = self.bus.lock().await;
+        bus.send(PacketType::Command, BROADCAST_ADDRESS, &payload).await?;
+        Timer::after(self.config.broadcast_gap).await;
+        info!("RS-485: broadcast cmd={:04X}", cmd);
+        Ok(())
+    }
+
+    /// 轮询一个从站：取回一条缓存的上行消息（无消息时为 None）
+    pub async fn poll(&self, node: u8) -> Result<Option<NodeMessage>, Rs485Error> {
+        let mut bus = self.bus.lock().await;
+        bus.send(PacketType::Ping, node, &[]).await?;
+        let frame = bus.recv_from(node, self.config.reply_timeout).await?;
+
+        match frame.packet_type {
+            PacketType::Pong => Ok(None),
+            PacketType::Command => {
+                let body = &frame.payload[ADDRESS_HEADER_LEN..];
+                if body.len() < 2 {
+                    crate::log_warn!("RS-485: short push from node {}", node);
+                    return Ok(None);
+                }
+                let mut payload = Vec::new();
+                let _ = payload.extend_from_slice(&body[2..]);
+                Ok(Some(NodeMessage {
+                    node,
+                    cmd: BigEndian::read_u16(&body[0..2]),
+                    payload,
+                }))
+            }
+            other => {
+                crate::log_warn!("RS-485: unexpected {:?} from node {}", other, node);
+                Ok(None)
+            }
+        }
+    }
+
+    /// 轮询循环：按顺序轮询所有从站，上行消息发送到 `events`
+    pub async fn run_poller<M: RawMutex, const N: usize>(
+        &self,
+        events: Sender<'_, M, NodeMessage, N>,
+    ) -> ! {
+        info!("RS-485 master polling {} nodes", self.config.nodes.len());
+
+        loop {
+            for &node in self.config.nodes {
+                match self.poll(node).await {
+                    Ok(Some(msg)) => {
+                        debug!("RS-485: node {} event cmd={:04X}", node, msg.cmd);
+                        events.send(msg).await;
+                    }
+                    Ok(None) => {}
+                    Err(e) => debug!("RS-485: poll node {} failed: {:?}", node, e),
+                }
+            }
+            Timer::after(self.config.poll_interval).await;
+        }
+    }
+}
@@ -2,55 +2,103 @@
 //
 // 职责：
 // 1. 从串口读取已完整的应用层字节流（硬件已完成 TCP 重组、校验）
-// 2. 使用 PacketCodec 解码应用层协议包
+// 2. 使用 FrameDecoder（PacketCodec）解码应用层协议包
 // 3. 将解码结果封装为 Event::NetworkIncoming
-// 4. 通过 Event Channel 注入事件系统
+// 4. 通过事件总线（event_bus）注入事件系统
+// 5. 经 TX 半部回写：Ping → Pong、命令应答、上行事件推送
+//
+// 点对点模式与 TCP / USB 一样由 `connection::serve` 驱动（EventInjector 分发），
+// 串口本身只实现 Transport（SerialLink）：读超时丢弃半包、读错误后重新同步，
+// 不会让 serve 因串口噪声退出。
+// RS-485 从站模式的帧带地址头且只在主站轮询时推送，单独由 `node_loop` 处理。
+//
+// 命令应答表示命令是否已被接收并注入事件系统（error_code 0 = 已入队，
+// 1 = 格式错误），执行结果通过上行事件推送体现。
 //
 // ⚠️  不做：CRC/校验、丢包处理、重传、确认、窗口控制（硬件已完成）
 
-use super::codec::PacketCodec;
+use super::connection::{serve, EventInjector, MAX_FRAME_LEN};
 use super::packet::PacketType;
-use crate::event::Event;
+use super::transport::{Frame, FrameDecoder, LinkState, Transport, TransportError};
+use super::rs485::{self, BROADCAST_ADDRESS};
+use super::uplink::{self, Connection, ConnectionId, UplinkMessage, UplinkSubscriber};
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
 use alloc::vec::Vec;
 use byteorder::{BigEndian, ByteOrder};
-use defmt::{debug, error, info, warn};
+use defmt::{debug, info};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Sender;
-use embassy_time::{Duration, Timer};
+use embassy_sync::pubsub::WaitResult;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{with_timeout, Duration, Timer};
+use embedded_io_async::{Read, Write};
 
 /// Serial Transport 配置
 #[derive(Clone, Copy)]
 pub struct SerialTransportConfig {
-    /// 读取超时
+    /// 读取空闲超时（超时后丢弃未完成的半包）
     pub read_timeout: Duration,
-    /// 是否启用 mock 模式（用于 Demo）
-    pub mock_mode: bool,
+    /// RS-485 从站地址（None = 点对点串口）
+    ///
+    /// 设置后帧带地址头（见 rs485.rs），只处理发给本机或广播的帧，
+    /// 上行事件不再主动推送，改为在主站轮询时逐条应答
+    pub node_address: Option<u8>,
 }
 
 impl Default for SerialTransportConfig {
     fn default() -> Self {
         Self {
             read_timeout: Duration::from_secs(30),
-            mock_mode: true, // Demo 模式默认开启
+            node_address: None,
         }
     }
 }
 
+/// RS-485 从站模式的应答去向
+#[derive(Clone, Copy)]
+enum Route {
+    /// 带地址头发回 `dst`
+    Node { dst: u8, src: u8 },
+    /// 广播：不应答
+    Silent,
+}
+
+/// 串口发送错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct SerialWriteError;
+
 /// Serial Transport（串口传输层）
-pub struct SerialTransport {
+///
+/// 对 embedded-io-async 的 Read + Write 泛型：
+/// - 硬件：`drivers::uart`（USART + DMA 环形缓冲）
+/// - Demo / 测试：`net::pipe::DuplexPipe`
+pub struct SerialTransport<R, W> {
     config: SerialTransportConfig,
+    rx: Mutex<CriticalSectionRawMutex, R>,
+    tx: Mutex<CriticalSectionRawMutex, W>,
 }
 
-impl SerialTransport {
+impl<R: Read, W: Write> SerialTransport<R, W> {
     /// 创建新的 Serial Transport
-    pub const fn new(config: SerialTransportConfig) -> Self {
-        Self { config }
+    pub const fn new(config: SerialTransportConfig, rx: R, tx: W) -> Self {
+        Self {
+            config,
+            rx: Mutex::new(rx),
+            tx: Mutex::new(tx),
+        }
+    }
+
+    /// 写出完整数据包（多个写入方通过互斥锁串行化）
+    pub async fn send_frame(&self, frame: &[u8]) -> Result<(), SerialWriteError> {
+        let mut tx = self.tx.lock().await;
+        tx.write_all(frame).await.map_err(|_| SerialWriteError)?;
+        tx.flush().await.map_err(|_| SerialWriteError)
     }
 
     /// 启动 Serial Transport（Event Producer）
     ///
     /// # 参数
-    /// - `event_tx`: Event Channel 的发送端，用于注入 NetworkIncoming 事件
+    /// - `event_tx`: 事件总线发布者，用于注入 NetworkIncoming 事件
     ///
     /// # 架构说明
     /// 这是一个 Event Producer，与 tcp_server 并列：
@@ -60,148 +108,205 @@ impl SerialTransport {
     /// 上层系统（dispatch_task + router + handlers）对传输方式完全无感
     pub async fn start(
         &self,
-        event_tx: Sender<'static, CriticalSectionRawMutex, Event, 32>,
+        event_tx: EventPublisher,
     ) -> ! {
         info!("Starting Serial Transport (Event Producer mode)");
 
-        if self.config.mock_mode {
-            info!("⚠️  Running in MOCK mode (for Demo)");
+        // 串口是常驻链路，整个运行期间算作一条连接
+        let conn = Connection::open();
+
+        // RS-485 从站：总线由主站调度，上行事件只在轮询时应答
+        if let Some(address) = self.config.node_address {
+            info!("Serial: RS-485 node address {}", address);
+            let uplink_rx = uplink::subscribe();
+            if uplink_rx.is_none() {
+                crate::log_warn!("Serial: no uplink subscriber slot, event push disabled");
+            }
+            self.node_loop(conn.id(), address, &event_tx, uplink_rx).await
+        }
+
+        let mut rx = self.rx.lock().await;
+        let mut tx = self.tx.lock().await;
+        let mut link = SerialLink {
+            rx: &mut *rx,
+            tx: &mut *tx,
+            decoder: FrameDecoder::new(),
+            read_timeout: self.config.read_timeout,
+        };
+        let dispatcher = EventInjector::new(Source::Serial, &event_tx);
+
+        loop {
+            // SerialLink 的读错误已在内部恢复，这里只会因写失败返回
+            if let Err(e) = serve(&mut link, &dispatcher, conn.id()).await {
+                crate::log_warn!("Serial: link error: {:?}", e);
+                Timer::after(Duration::from_millis(100)).await;
+            }
         }
+    }
 
-        let mut codec = PacketCodec::new();
-        let mut decode_buffer = [0u8; 1024];
+    /// RS-485 从站接收循环：读串口 → 解码 → 按地址过滤 → 注入事件 / 回写应答
+    ///
+    /// `polled_uplink`：Ping 轮询时从中取一条发往本连接的上行事件应答
+    async fn node_loop(
+        &self,
+        conn: ConnectionId,
+        own: u8,
+        event_tx: &EventPublisher,
+        mut polled_uplink: Option<UplinkSubscriber>,
+    ) -> ! {
+        let mut rx = self.rx.lock().await;
+        let mut decoder = FrameDecoder::new();
+        let mut tx_buffer = [0u8; MAX_FRAME_LEN];
 
         loop {
-            // ========== 第一步：从串口读取字节流 ==========
-            //
-            // 📌 硬件语义：
-            // - USB 转网口芯片已完成 TCP/IP 协议栈
-            // - 串口收到的数据 = TCP socket 中的应用层 payload
-            // - 已保证：顺序、完整性、可靠性
-            //
-            // 🔧 接入真实硬件时，替换以下两个函数：
-            // - mock_serial_read() → 真实的 uart.read()
-            // - mock_serial_available() → 真实的 uart.poll() / interrupt
-
-            let rx_data = if self.config.mock_mode {
-                // Demo: 模拟串口读取
-                match self.mock_serial_read().await {
-                    Some(data) => data,
+            let packet = match recv_frame(&mut decoder, &mut *rx, self.config.read_timeout).await {
+                Some(frame) => frame,
+                None => continue,
+            };
+
+            // 按地址过滤并剥离地址头
+            let (route, body) = match rs485::split_address(&packet.payload) {
+                Some((dst, src, body)) if dst == own => (Route::Node { dst: src, src: own }, body),
+                Some((BROADCAST_ADDRESS, _, body)) => (Route::Silent, body),
+                _ => continue,
+            };
+
+            // Ping：有待发事件时回一条事件，否则回 Pong，不进入事件系统
+            if packet.packet_type == PacketType::Ping {
+                debug!("Received Ping, sending Pong");
+                match polled_uplink.as_mut().and_then(|sub| next_pending(sub, conn)) {
+                    Some(msg) => {
+                        let mut push = Vec::with_capacity(2 + msg.payload.len());
+                        push.extend_from_slice(&msg.cmd.to_be_bytes());
+                        push.extend_from_slice(&msg.payload);
+                        self.send_packet(PacketType::Command, packet.seq, &push, route, &mut tx_buffer)
+                            .await;
+                    }
                     None => {
-                        Timer::after(Duration::from_millis(100)).await;
-                        continue;
+                        self.send_packet(PacketType::Pong, packet.seq, &[], route, &mut tx_buffer)
+                            .await
                     }
                 }
-            } else {
-                // TODO: 接入真实串口驱动
-                // 示例：
-                // let mut rx_buffer = [0u8; 512];
-                // match uart.read(&mut rx_buffer).await {
-                //     Ok(n) if n > 0 => &rx_buffer[..n],
-                //     _ => continue,
-                // }
-                error!("Real UART not implemented yet");
-                Timer::after(Duration::from_secs(1)).await;
                 continue;
-            };
-
-            debug!("Serial received {} bytes", rx_data.len());
-
-            // ========== 第二步：使用已有 codec 解码 ==========
-            // 与 tcp_server/connection.rs 中的逻辑完全一致
+            }
 
-            if let Err(e) = codec.feed(rx_data) {
-                warn!("Codec feed error: {:?}", e);
+            // 协议格式：[cmd: 2 bytes][payload: variable]
+            if body.len() < 2 {
+                crate::log_warn!("Packet payload too short");
+                self.send_response(1, 0, packet.seq, route, &mut tx_buffer).await;
                 continue;
             }
 
-            // 尝试解码数据包
-            while let Ok(Some(packet)) = codec.decode(&mut decode_buffer) {
-                debug!(
-                    "Decoded packet: type={:?}, seq={}, len={}",
-                    packet.packet_type,
-                    packet.seq,
-                    packet.payload.len()
-                );
-
-                // 跳过 Ping（由 tcp_server 处理，这里暂不响应）
-                if packet.packet_type == PacketType::Ping {
-                    debug!("Received Ping (ignored in serial mode)");
-                    continue;
-                }
+            let cmd = BigEndian::read_u16(&body[0..2]);
+            let event = Event::NetworkIncoming {
+                cmd,
+                payload: body[2..].to_vec(),
+            };
 
-                // ========== 第三步：提取 cmd + payload ==========
-                // 协议格式：[cmd: 2 bytes][payload: variable]
+            debug!("Injecting NetworkIncoming event: cmd={:04X}", cmd);
 
-                if packet.payload.len() < 2 {
-                    warn!("Packet payload too short");
-                    continue;
-                }
+            event_tx
+                .publish(EventEnvelope::new(Source::Serial, event).with_origin(conn))
+                .await;
+
+            self.send_response(0, cmd, packet.seq, route, &mut tx_buffer).await;
+        }
+    }
 
-                let cmd = BigEndian::read_u16(&packet.payload[0..2]);
-                let payload_data = &packet.payload[2..];
+    /// 写出应答：[error_code: 2B][cmd: 2B]
+    async fn send_response(&self, error_code: u16, cmd: u16, seq: u8, route: Route, tx_buffer: &mut [u8]) {
+        let mut body = [0u8; 4];
+        BigEndian::write_u16(&mut body[0..2], error_code);
+        BigEndian::write_u16(&mut body[2..4], cmd);
+        self.send_packet(PacketType::Response, seq, &body, route, tx_buffer).await;
+    }
 
-                // 转换为 alloc::vec::Vec（Event 需要）
-                let mut payload_vec = Vec::new();
-                payload_vec.extend_from_slice(payload_data);
+    /// 按去向编码并写出数据包
+    async fn send_packet(
+        &self,
+        packet_type: PacketType,
+        seq: u8,
+        body: &[u8],
+        route: Route,
+        tx_buffer: &mut [u8],
+    ) {
+        let encoded = match route {
+            Route::Node { dst, src } => rs485::encode_addressed(packet_type, seq, dst, src, body, tx_buffer),
+            Route::Silent => return,
+        };
 
-                // ========== 第四步：注入 Event::NetworkIncoming ==========
-                // 🎯 关键点：与 tcp_server 产生相同的 Event 类型
-                // 上层系统（dispatch → router → handlers）完全无感
+        match encoded {
+            Ok(len) => {
+                if let Err(e) = self.send_frame(&tx_buffer[..len]).await {
+                    crate::log_warn!("Serial: failed to send reply: {:?}", e);
+                }
+            }
+            Err(e) => crate::log_warn!("Serial: failed to build reply: {:?}", e),
+        }
+    }
+}
 
-                let event = Event::NetworkIncoming {
-                    cmd,
-                    payload: payload_vec,
-                };
+/// 读取一个数据包；读超时丢弃半包，读错误 / 暂无数据时返回 None，由调用方重试
+///
+/// 📌 硬件语义：USB 转网口芯片已完成 TCP/IP 协议栈，串口收到的数据即 TCP socket 中的
+/// 应用层 payload，已保证顺序、完整性、可靠性
+async fn recv_frame<R: Read>(decoder: &mut FrameDecoder, rx: &mut R, read_timeout: Duration) -> Option<Frame> {
+    match with_timeout(read_timeout, decoder.recv(rx)).await {
+        Ok(Ok(frame)) => Some(frame),
+        Ok(Err(TransportError::Disconnected)) => {
+            Timer::after(Duration::from_millis(100)).await;
+            None
+        }
+        Ok(Err(e)) => {
+            // 溢出 / 帧错误等：decoder 已丢弃半包
+            crate::log_error!("Serial read error: {:?}", e);
+            None
+        }
+        Err(_) => {
+            // 空闲超时：丢弃未完成的半包
+            decoder.reset();
+            None
+        }
+    }
+}
 
-                debug!("Injecting NetworkIncoming event: cmd={:04X}", cmd);
+/// 点对点串口链路（供 connection::serve 使用）
+///
+/// 串口没有连接概念：读错误与超时在内部恢复，`recv_frame` 只返回完整数据包
+struct SerialLink<'a, R, W> {
+    rx: &'a mut R,
+    tx: &'a mut W,
+    decoder: FrameDecoder,
+    read_timeout: Duration,
+}
 
-                event_tx.send(event).await;
+impl<R: Read, W: Write> Transport for SerialLink<'_, R, W> {
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        loop {
+            if let Some(frame) = recv_frame(&mut self.decoder, self.rx, self.read_timeout).await {
+                return Ok(frame);
             }
         }
     }
 
-    // ========== Mock 实现（Demo 用） ==========
-    // 🔧 接入真实硬件时，删除以下函数，使用真实的 UART API
+    async fn send_frame(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        self.tx.write_all(frame).await.map_err(|_| TransportError::SendFailed)?;
+        self.tx.flush().await.map_err(|_| TransportError::SendFailed)
+    }
+
+    fn link_state(&self) -> LinkState {
+        LinkState::Up
+    }
+}
 
-    /// Mock: 模拟串口读取
-    ///
-    /// 真实替换路径：
-    /// ```rust
-    /// // 使用 embassy_stm32::usart::Uart
-    /// let mut uart = Uart::new(/* ... */);
-    /// let mut rx_buffer = [0u8; 512];
-    /// match uart.read(&mut rx_buffer).await {
-    ///     Ok(n) => &rx_buffer[..n],
-    ///     Err(_) => continue,
-    /// }
-    /// ```
-    async fn mock_serial_read(&self) -> Option<&'static [u8]> {
-        // Demo: 模拟接收一个 Command 包
-        // 实际数据格式：
-        // - PacketHeader (8 bytes): magic + type + seq + len + checksum
-        // - Payload: [cmd: 0x2001][data: ...]
-
-        Timer::after(Duration::from_secs(5)).await;
-
-        // 构造一个示例 Command 包（cmd=0x2001, 请求状态）
-        // 这里直接返回预编码的字节流
-        static MOCK_DATA: &[u8] = &[
-            // PacketHeader (8 bytes)
-            0xAA, 0x55,       // magic
-            0x20,             // PacketType::Command
-            0x01,             // seq
-            0x00, 0x02,       // payload_len = 2
-            0x00, 0x00,       // checksum (需要计算)
-            // Payload (2 bytes)
-            0x20, 0x01,       // cmd = 0x2001 (Request Status)
-        ];
-
-        // 计算并修正 checksum（这里简化，直接使用示例数据）
-        // 真实环境中，数据来自硬件，已经包含正确的 checksum
-
-        info!("Mock: Simulating serial data reception");
-        Some(MOCK_DATA)
+/// 取一条发往 `conn` 的待发上行事件（不等待）
+fn next_pending(sub: &mut UplinkSubscriber, conn: ConnectionId) -> Option<UplinkMessage> {
+    loop {
+        match sub.try_next_message()? {
+            WaitResult::Message(msg) if msg.is_for(Some(conn)) => return Some(msg),
+            WaitResult::Message(_) => continue,
+            WaitResult::Lagged(n) => crate::log_warn!("Serial: uplink lagged, {} events dropped", n),
+        }
     }
 }
 
@@ -217,60 +322,38 @@ impl SerialTransport {
 //   ↓
 // 提取 cmd + payload
 //   ↓
-// 直接调用 router.handle_message()  ← 不走 Event Channel
+// 直接调用 router.handle_message()  ← 不走事件总线
 // ```
 //
 // ## Serial Transport (src/net/serial_transport.rs)
 // ```text
-// uart.read() / mock_serial_read()
+// Read::read()（UART / 内存管道）
 //   ↓
 // PacketCodec::feed() + decode()  ← 完全相同
 //   ↓
 // 提取 cmd + payload              ← 完全相同
 //   ↓
-// event_tx.send(NetworkIncoming)  ← 标准 Event Producer
+// event_tx.publish(NetworkIncoming)  ← 标准 Event Producer
 //   ↓
 // dispatch_task → route_event → handlers::network
 // ```
 //
 // ## 关键差异
 // - **数据源**：TcpSocket vs UART（但语义等价：都是应用层 payload）
-// - **事件注入**：tcp_server 直接调用 handler，serial_transport 走 Event Channel
+// - **事件注入**：tcp_server 直接调用 handler，serial_transport 走事件总线
 // - **协议处理**：完全相同（PacketCodec + cmd 解析）
+// - **应答**：tcp_server 返回处理结果，serial_transport 返回"已入队"确认，执行结果走上行推送
 //
-// ## 未来真实硬件接入
-//
-// 只需修改 `start()` 函数中的串口读取部分：
-//
-// ```rust
-// // 1. 初始化 UART（main.rs 中）
-// let uart = Uart::new(
-//     p.USART1,
-//     p.PA10,  // RX
-//     p.PA9,   // TX
-//     Irqs,
-//     p.DMA1_CH4,
-//     p.DMA1_CH5,
-//     uart_config,
-// );
-//
-// // 2. 替换 mock_serial_read()
-// let mut rx_buffer = [0u8; 512];
-// let n = uart.read(&mut rx_buffer).await?;
-// let rx_data = &rx_buffer[..n];
-//
-// // 后续流程保持不变（codec、event 注入）
-// ```
-//
-// ## 系统启动配置
+// ## 硬件 / Mock 接入
 //
-// 在 `main.rs` 中，选择一种传输方式启动：
+// 数据源由构造参数决定，`start()` 本身不区分：
 //
 // ```rust
-// // 选项 1: TCP 模式
-// spawner.spawn(tcp_server_task(stack, router)).unwrap();
+// // 硬件：USART1 + DMA 环形缓冲
+// let (rx, tx) = drivers::uart::init(uart_peripherals, UartConfig::default())?;
+// let transport = SerialTransport::new(config, rx, tx);
 //
-// // 选项 2: Serial 模式
-// let serial_transport = SerialTransport::new(Default::default());
-// spawner.spawn(serial_transport_task(serial_transport, event_tx)).unwrap();
+// // Demo / 测试：内存双工管道，另一端由 mock 主机写入
+// let (rx, tx) = pipe.device_end();
+// let transport = SerialTransport::new(config, rx, tx);
 // ```
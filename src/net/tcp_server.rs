@@ -1,6 +1,6 @@
 // TCP 服务器 - 只接受单个客户端连接
-use super::{connection::handle_connection, router::Router};
-use defmt::{error, info, warn};
+use super::connection::{handle_connection, Dispatcher};
+use defmt::info;
 use embassy_net::{tcp::TcpSocket, Stack};
 use embassy_time::{Duration, Timer};
 use static_cell::StaticCell;
@@ -39,17 +39,26 @@ impl TcpServer {
     }
 
     /// 启动 TCP 服务器（只接受一个连接）
-    pub async fn start<'d>(
+    ///
+    /// 只能调用一次（收发缓冲区为静态分配）
+    pub async fn start<'d, D: Dispatcher>(
         &self,
         stack: &'static Stack<'d>,
-        router: &'static Router,
+        dispatcher: &D,
     ) -> ! {
         info!("Starting TCP server on port {} (single connection mode)", self.config.port);
 
+        // 使用 StaticCell 管理缓冲区，各连接依次复用
+        static RX_BUF: StaticCell<[u8; RX_BUFFER_SIZE]> = StaticCell::new();
+        static TX_BUF: StaticCell<[u8; TX_BUFFER_SIZE]> = StaticCell::new();
+
+        let rx_buf = RX_BUF.init([0; RX_BUFFER_SIZE]);
+        let tx_buf = TX_BUF.init([0; TX_BUFFER_SIZE]);
+
         loop {
             // 等待网络就绪
             while !stack.is_link_up() {
-                warn!("Network link down, waiting...");
+                crate::log_warn!("Network link down, waiting...");
                 Timer::after(Duration::from_secs(1)).await;
             }
 
@@ -58,19 +67,12 @@ impl TcpServer {
                 info!("Network ready: IP={:?}", config.address);
             }
 
-            // 使用 StaticCell 管理缓冲区
-            static RX_BUF: StaticCell<[u8; RX_BUFFER_SIZE]> = StaticCell::new();
-            static TX_BUF: StaticCell<[u8; TX_BUFFER_SIZE]> = StaticCell::new();
-
-            let rx_buf = RX_BUF.init([0; RX_BUFFER_SIZE]);
-            let tx_buf = TX_BUF.init([0; TX_BUFFER_SIZE]);
-
-            let mut socket = TcpSocket::new(*stack, rx_buf, tx_buf);
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf[..], &mut tx_buf[..]);
             socket.set_timeout(Some(self.config.recv_timeout));
 
             info!("Listening on port {}", self.config.port);
             if let Err(e) = socket.accept(self.config.port).await {
-                error!("Accept error: {:?}", e);
+                crate::log_error!("Accept error: {:?}", e);
                 Timer::after(Duration::from_secs(1)).await;
                 continue;
             }
@@ -79,8 +81,8 @@ impl TcpServer {
             info!("Client connected: {:?}", remote);
 
             // 处理连接（阻塞直到断开）
-            if let Err(e) = handle_connection(socket, router).await {
-                warn!("Connection error: {:?}", e);
+            if let Err(e) = handle_connection(socket, dispatcher).await {
+                crate::log_warn!("Connection error: {:?}", e);
             }
 
             info!("Client disconnected, waiting for new connection...");
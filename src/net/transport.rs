@@ -0,0 +1,340 @@
+// 传输层抽象
+//
+// `Transport` 统一"收一个数据包 / 发一个数据包 / 链路状态"三个操作，
+// 上层 `connection::serve` 只依赖该 trait，与具体传输方式无关：
+//
+//   TcpTransport        TcpSocket（单连接）
+//   StreamTransport     任意 embedded-io-async Read + Write（UART、内存管道）
+//   LoopbackPair        两个 StreamTransport 经 DuplexPipe 背靠背相连（无硬件联调）
+//
+// 字节流 → 数据包的拆分由 FrameDecoder 完成，各传输共用，不再各自复制解码循环。
+
+use super::codec::{CodecError, DecodedPacket, PacketCodec};
+use super::packet::{PacketType, MAX_PAYLOAD_LEN};
+use super::pipe::{DuplexPipe, SerialPipe};
+use defmt::{debug, Format};
+use embassy_net::tcp::{State, TcpSocket};
+use embassy_time::{Duration, Timer};
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+
+/// 传输错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum TransportError {
+    /// 对端关闭 / 链路断开
+    Disconnected,
+    /// 读取失败
+    ReadFailed,
+    /// 发送失败
+    SendFailed,
+}
+
+/// 链路状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum LinkState {
+    Down,
+    Up,
+}
+
+/// 已解码的数据包（自有载荷，不借用解码缓冲区）
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub packet_type: PacketType,
+    pub seq: u8,
+    pub payload: Vec<u8, MAX_PAYLOAD_LEN>,
+}
+
+impl Frame {
+    /// 以 DecodedPacket 形式借用（供 connection::dispatch_packet 使用）
+    pub fn as_packet(&self) -> DecodedPacket<'_> {
+        DecodedPacket {
+            packet_type: self.packet_type,
+            seq: self.seq,
+            payload: &self.payload,
+        }
+    }
+}
+
+/// 传输层
+pub trait Transport {
+    /// 接收下一个完整数据包
+    ///
+    /// 须可取消：future 在读取中途被丢弃不会丢失已缓冲的字节
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError>;
+
+    /// 发送一个已编码的数据包
+    async fn send_frame(&mut self, frame: &[u8]) -> Result<(), TransportError>;
+
+    /// 当前链路状态
+    fn link_state(&self) -> LinkState;
+}
+
+/// 字节流 → 数据包
+pub struct FrameDecoder {
+    codec: PacketCodec,
+    decode_buffer: [u8; MAX_PAYLOAD_LEN],
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self {
+            codec: PacketCodec::new(),
+            decode_buffer: [0u8; MAX_PAYLOAD_LEN],
+        }
+    }
+
+    /// 丢弃未完成的半包
+    pub fn reset(&mut self) {
+        self.codec.reset();
+    }
+
+    /// 从已缓冲的数据中取出一个数据包（不读取）
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        loop {
+            match self.codec.decode(&mut self.decode_buffer) {
+                Ok(Some(packet)) => {
+                    debug!(
+                        "Decoded packet: type={:?}, seq={}, len={}",
+                        packet.packet_type,
+                        packet.seq,
+                        packet.payload.len()
+                    );
+                    let mut payload = Vec::new();
+                    // payload 长度已由 codec 限制在 MAX_PAYLOAD_LEN 以内
+                    let _ = payload.extend_from_slice(packet.payload);
+                    return Some(Frame {
+                        packet_type: packet.packet_type,
+                        seq: packet.seq,
+                        payload,
+                    });
+                }
+                Ok(None) => return None,
+                // 无效头部 / 校验失败：codec 已丢弃坏数据，继续尝试
+                Err(CodecError::InvalidHeader(_)) | Err(CodecError::InvalidPacket(_)) => continue,
+                Err(e) => {
+                    crate::log_warn!("Decode error: {:?}", e);
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// 读取直到得到一个完整数据包
+    ///
+    /// 读取返回 0 视为对端关闭
+    pub async fn recv<R: Read>(&mut self, rx: &mut R) -> Result<Frame, TransportError> {
+        let mut rx_buffer = [0u8; 512];
+
+        loop {
+            if let Some(frame) = self.next_frame() {
+                return Ok(frame);
+            }
+
+            let n = match rx.read(&mut rx_buffer).await {
+                Ok(0) => return Err(TransportError::Disconnected),
+                Ok(n) => n,
+                Err(_) => {
+                    // 溢出 / 帧错误等：丢弃半包，从下一个帧头重新同步
+                    self.reset();
+                    return Err(TransportError::ReadFailed);
+                }
+            };
+
+            debug!("Received {} bytes", n);
+
+            if let Err(e) = self.codec.feed(&rx_buffer[..n]) {
+                crate::log_warn!("Codec feed error: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// TCP 传输（单个已建立的连接）
+pub struct TcpTransport<'a> {
+    socket: TcpSocket<'a>,
+    decoder: FrameDecoder,
+}
+
+impl<'a> TcpTransport<'a> {
+    pub fn new(socket: TcpSocket<'a>) -> Self {
+        Self {
+            socket,
+            decoder: FrameDecoder::new(),
+        }
+    }
+
+    /// 关闭连接：发出缓冲数据与 FIN，对端未及时关闭时复位
+    pub async fn close(mut self) {
+        let _ = self.socket.flush().await;
+        self.socket.close();
+        Timer::after(Duration::from_millis(100)).await;
+        self.socket.abort();
+        let _ = self.socket.flush().await;
+    }
+}
+
+impl Transport for TcpTransport<'_> {
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        self.decoder.recv(&mut self.socket).await
+    }
+
+    async fn send_frame(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        self.socket
+            .write_all(frame)
+            .await
+            .map_err(|_| TransportError::SendFailed)
+    }
+
+    fn link_state(&self) -> LinkState {
+        match self.socket.state() {
+            State::Established => LinkState::Up,
+            _ => LinkState::Down,
+        }
+    }
+}
+
+/// 字节流传输（UART、内存管道等）
+///
+/// 串口没有连接概念，链路状态恒为 Up
+pub struct StreamTransport<R, W> {
+    rx: R,
+    tx: W,
+    decoder: FrameDecoder,
+}
+
+impl<R: Read, W: Write> StreamTransport<R, W> {
+    pub fn new(rx: R, tx: W) -> Self {
+        Self {
+            rx,
+            tx,
+            decoder: FrameDecoder::new(),
+        }
+    }
+}
+
+impl<R: Read, W: Write> Transport for StreamTransport<R, W> {
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        self.decoder.recv(&mut self.rx).await
+    }
+
+    async fn send_frame(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        self.tx
+            .write_all(frame)
+            .await
+            .map_err(|_| TransportError::SendFailed)?;
+        self.tx.flush().await.map_err(|_| TransportError::SendFailed)
+    }
+
+    fn link_state(&self) -> LinkState {
+        LinkState::Up
+    }
+}
+
+/// 内存管道传输
+pub type PipeTransport<'a> = StreamTransport<&'a SerialPipe, &'a SerialPipe>;
+
+/// 内存环回对：一端发出的数据包由另一端收到
+pub struct LoopbackPair {
+    pipe: DuplexPipe,
+}
+
+impl LoopbackPair {
+    pub const fn new() -> Self {
+        Self {
+            pipe: DuplexPipe::new(),
+        }
+    }
+
+    /// (设备端, 主机端)
+    pub fn split(&self) -> (PipeTransport<'_>, PipeTransport<'_>) {
+        let (device_rx, device_tx) = self.pipe.device_end();
+        let (host_rx, host_tx) = self.pipe.host_end();
+        (
+            StreamTransport::new(device_rx, device_tx),
+            StreamTransport::new(host_rx, host_tx),
+        )
+    }
+}
+
+impl Default for LoopbackPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+
+    fn encode(packet_type: PacketType, seq: u8, payload: &[u8]) -> std::vec::Vec<u8> {
+        let mut buf = [0u8; 64];
+        let len = PacketCodec::encode(packet_type, seq, payload, &mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn decoder_skips_noise_and_corrupt_frames() {
+        let good = encode(PacketType::Command, 7, &[0x20, 0x01, 0xAA]);
+        let mut corrupt = encode(PacketType::Command, 6, &[0x20, 0x01, 0xBB]);
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xFF;
+
+        let mut stream = std::vec![0x00, 0x13, 0xAA, 0x37];
+        stream.extend_from_slice(&corrupt);
+        stream.extend_from_slice(&good);
+
+        let mut decoder = FrameDecoder::new();
+        let mut rx = stream.as_slice();
+        let frame = block_on(decoder.recv(&mut rx)).unwrap();
+        assert_eq!(frame.packet_type, PacketType::Command);
+        assert_eq!(frame.seq, 7);
+        assert_eq!(frame.payload.as_slice(), &[0x20, 0x01, 0xAA]);
+
+        // 流结束视为对端关闭
+        assert_eq!(block_on(decoder.recv(&mut rx)).unwrap_err(), TransportError::Disconnected);
+    }
+
+    #[test]
+    fn decoder_returns_back_to_back_frames_in_order() {
+        let mut stream = encode(PacketType::Ping, 1, &[]);
+        stream.extend_from_slice(&encode(PacketType::Command, 2, &[0x00, 0x01]));
+
+        let mut decoder = FrameDecoder::new();
+        let mut rx = stream.as_slice();
+        assert_eq!(block_on(decoder.recv(&mut rx)).unwrap().packet_type, PacketType::Ping);
+        // 第二个包已在缓冲区中，不需要再读取
+        let frame = decoder.next_frame().unwrap();
+        assert_eq!(frame.seq, 2);
+        assert!(decoder.next_frame().is_none());
+    }
+
+    #[test]
+    fn loopback_reassembles_frames_split_across_writes() {
+        let pair = LoopbackPair::new();
+        let (mut device, mut host) = pair.split();
+        let frame = encode(PacketType::Command, 3, &[0x00, 0x01, 0xDE, 0xAD]);
+
+        block_on(async {
+            for chunk in frame.chunks(3) {
+                host.send_frame(chunk).await.unwrap();
+            }
+            let received = device.recv_frame().await.unwrap();
+            assert_eq!(received.seq, 3);
+            assert_eq!(received.payload.as_slice(), &[0x00, 0x01, 0xDE, 0xAD]);
+
+            // 反方向
+            device.send_frame(&encode(PacketType::Pong, 4, &[])).await.unwrap();
+            let received = host.recv_frame().await.unwrap();
+            assert_eq!(received.packet_type, PacketType::Pong);
+            assert_eq!(received.seq, 4);
+        });
+        assert_eq!(device.link_state(), LinkState::Up);
+    }
+}
@@ -0,0 +1,132 @@
+// 上行消息总线（STM32 -> 客户端）
+//
+// 处理器把编码好的 toc 消息（1001 心跳、1003 按钮、1004 投币、1005 回币、
+// 1006 故障……）发布到这里，各传输层（MQTT 等）各自订阅后发往上位机。
+// 发布永不阻塞：订阅方跟不上时丢弃最旧消息，订阅方会收到 Lagged 通知。
+//
+// 定向消息：每条把命令注入事件系统的连接（串口、USB 会话、MQTT 会话……）持有一个
+// Connection，命令事件携带其 ConnectionId。处理器可用 `publish_to` 只回给发起连接
+// （如 1002 状态订阅），各传输层推送前用 `UplinkMessage::is_for` 过滤。
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use defmt::Format;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::{PubSubChannel, Subscriber};
+use prost::Message;
+
+/// 队列容量
+pub const UPLINK_CAPACITY: usize = 16;
+
+/// 各传输层同时订阅上行的会话数（TCP / WebSocket 服务器一次只服务一条连接）
+const SERIAL_SESSIONS: usize = 1;
+const USB_SESSIONS: usize = 1;
+const TCP_SESSIONS: usize = 1;
+const WEBSOCKET_SESSIONS: usize = 1;
+const MQTT_SESSIONS: usize = 1;
+
+/// 最大订阅者数量（全部传输层同时在线）
+pub const UPLINK_SUBSCRIBERS: usize =
+    SERIAL_SESSIONS + USB_SESSIONS + TCP_SESSIONS + WEBSOCKET_SESSIONS + MQTT_SESSIONS;
+
+/// 连接标识（开机以来唯一，不复用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct ConnectionId(pub u32);
+
+static NEXT_CONNECTION_ID: AtomicU32 = AtomicU32::new(1);
+
+/// 一条连接的生命周期；释放时清理该连接的状态订阅
+pub struct Connection {
+    id: ConnectionId,
+}
+
+impl Connection {
+    /// 分配新的连接标识
+    pub fn open() -> Self {
+        Self {
+            id: ConnectionId(NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        crate::app::subscriptions::remove(Some(self.id));
+    }
+}
+
+/// 上行消息
+#[derive(Debug, Clone)]
+pub struct UplinkMessage {
+    /// 命令码（如 0x1001）
+    pub cmd: u16,
+    /// protobuf 编码后的载荷
+    pub payload: Vec<u8>,
+    /// 目标连接（None = 广播）
+    pub target: Option<ConnectionId>,
+}
+
+impl UplinkMessage {
+    /// 是否应发往连接 `conn`（None = 不属于任何连接的传输，只收广播）
+    pub fn is_for(&self, conn: Option<ConnectionId>) -> bool {
+        match self.target {
+            None => true,
+            Some(target) => conn == Some(target),
+        }
+    }
+}
+
+/// 上行订阅者类型
+pub type UplinkSubscriber = Subscriber<
+    'static,
+    CriticalSectionRawMutex,
+    UplinkMessage,
+    UPLINK_CAPACITY,
+    UPLINK_SUBSCRIBERS,
+    0,
+>;
+
+static UPLINK: PubSubChannel<
+    CriticalSectionRawMutex,
+    UplinkMessage,
+    UPLINK_CAPACITY,
+    UPLINK_SUBSCRIBERS,
+    0,
+> = PubSubChannel::new();
+
+/// 订阅上行消息
+pub fn subscribe() -> Option<UplinkSubscriber> {
+    match UPLINK.subscriber() {
+        Ok(sub) => Some(sub),
+        Err(_) => {
+            crate::log_warn!("Uplink: too many subscribers");
+            None
+        }
+    }
+}
+
+/// 发布已编码的上行消息
+pub fn publish_raw(cmd: u16, payload: Vec<u8>) {
+    publish_raw_to(None, cmd, payload);
+}
+
+/// 发布已编码的上行消息到指定连接（None = 广播）
+pub fn publish_raw_to(target: Option<ConnectionId>, cmd: u16, payload: Vec<u8>) {
+    UPLINK
+        .immediate_publisher()
+        .publish_immediate(UplinkMessage { cmd, payload, target });
+}
+
+/// 编码并发布 protobuf 消息
+pub fn publish<M: Message>(cmd: u16, msg: &M) {
+    publish_raw(cmd, msg.encode_to_vec());
+}
+
+/// 编码并发布 protobuf 消息到指定连接（None = 广播）
+pub fn publish_to<M: Message>(target: Option<ConnectionId>, cmd: u16, msg: &M) {
+    publish_raw_to(target, cmd, msg.encode_to_vec());
+}
@@ -0,0 +1,153 @@
+// USB CDC-ACM 传输（技术员笔记本直连维护口）
+//
+// 协议与串口 / TCP 完全相同（PacketCodec 数据包），会话由 `connection::serve` 驱动，
+// 行为与 SerialTransport 一致：Ping → Pong，命令注入 Event::NetworkIncoming 后回
+// “已入队”应答，上行事件推送。
+//
+// 连接检测：
+// - 枚举完成（wait_connection）且主机置位 DTR（串口终端打开）视为已连接
+// - DTR 清零（终端关闭）或 USB 拔出视为断开，结束本次会话，等待下次连接
+// - 每次会话单独订阅上行事件，断开期间的事件不积压
+//
+// 与网络传输互不影响：两者各自订阅上行总线、各自回写应答，命令都发布到同一事件总线
+
+use super::connection::{serve, EventInjector};
+use super::transport::{Frame, FrameDecoder, LinkState, Transport, TransportError};
+use super::uplink::Connection;
+use crate::event::Source;
+use crate::event_bus::EventPublisher;
+use defmt::info;
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Timer};
+use embassy_usb::class::cdc_acm::{self, BufferedReceiver, CdcAcmClass, ControlChanged};
+use embassy_usb::driver::Driver;
+
+/// USB 传输配置
+#[derive(Clone, Copy)]
+pub struct UsbTransportConfig {
+    /// DTR 置位后等待多久开始会话（部分终端打开端口时会抖动 DTR）
+    pub connect_delay: Duration,
+}
+
+impl Default for UsbTransportConfig {
+    fn default() -> Self {
+        Self {
+            connect_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+/// CDC-ACM 传输（实现 Transport）
+pub struct UsbTransport<'d, D: Driver<'d>> {
+    tx: cdc_acm::Sender<'d, D>,
+    rx: BufferedReceiver<'d, D>,
+    control: ControlChanged<'d>,
+    decoder: FrameDecoder,
+}
+
+impl<'d, D: Driver<'d>> UsbTransport<'d, D> {
+    /// `rx_buffer` 至少为一个 USB 包长
+    pub fn new(class: CdcAcmClass<'d, D>, rx_buffer: &'d mut [u8]) -> Self {
+        let (tx, rx, control) = class.split_with_control();
+        Self {
+            tx,
+            rx: rx.into_buffered(rx_buffer),
+            control,
+            decoder: FrameDecoder::new(),
+        }
+    }
+
+    /// 等待主机连接（枚举完成且 DTR 置位）
+    pub async fn wait_connected(&mut self) {
+        loop {
+            self.rx.wait_connection().await;
+            if self.rx.dtr() {
+                self.decoder.reset();
+                return;
+            }
+            self.control.control_changed().await;
+        }
+    }
+}
+
+impl<'d, D: Driver<'d>> Transport for UsbTransport<'d, D> {
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        loop {
+            if !self.rx.dtr() {
+                return Err(TransportError::Disconnected);
+            }
+            match select(self.decoder.recv(&mut self.rx), self.control.control_changed()).await {
+                // 端点被禁用（拔出 / 总线复位）同样视为断开
+                Either::First(result) => {
+                    return result.map_err(|_| TransportError::Disconnected);
+                }
+                // 线路状态变化：回到循环开头检查 DTR
+                Either::Second(()) => continue,
+            }
+        }
+    }
+
+    async fn send_frame(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        if !self.tx.dtr() {
+            return Err(TransportError::Disconnected);
+        }
+        let max = self.tx.max_packet_size() as usize;
+        for chunk in frame.chunks(max) {
+            self.tx
+                .write_packet(chunk)
+                .await
+                .map_err(|_| TransportError::SendFailed)?;
+        }
+        // 恰为整包时补零长包，主机才会立即交付
+        if frame.len().is_multiple_of(max) {
+            self.tx
+                .write_packet(&[])
+                .await
+                .map_err(|_| TransportError::SendFailed)?;
+        }
+        Ok(())
+    }
+
+    fn link_state(&self) -> LinkState {
+        if self.tx.dtr() {
+            LinkState::Up
+        } else {
+            LinkState::Down
+        }
+    }
+}
+
+/// USB 维护口服务（Event Producer，与 SerialTransport 并列）
+pub struct UsbSerial {
+    config: UsbTransportConfig,
+}
+
+impl UsbSerial {
+    pub const fn new(config: UsbTransportConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动服务：循环等待连接并处理会话
+    pub async fn start<'d, D: Driver<'d>>(
+        &self,
+        transport: &mut UsbTransport<'d, D>,
+        event_tx: EventPublisher,
+    ) -> ! {
+        info!("Starting USB serial transport");
+
+        loop {
+            transport.wait_connected().await;
+            Timer::after(self.config.connect_delay).await;
+            if transport.link_state() == LinkState::Down {
+                continue;
+            }
+
+            info!("USB: host connected");
+            // 会话结束时释放，清理该主机的状态订阅
+            let conn = Connection::open();
+            let dispatcher = EventInjector::new(Source::Usb, &event_tx);
+            let result = serve(transport, &dispatcher, conn.id()).await;
+            info!("USB: host disconnected ({:?})", result);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+// WebSocket 服务器传输层（浏览器运维控制台直连，无需代理）
+//
+// 1. HTTP Upgrade 握手（RFC 6455，Sec-WebSocket-Accept = base64(sha1(key + GUID))）
+// 2. 二进制消息承载与 TCP 完全相同的 0xAA55 数据包字节流：
+//    - 入站：binary / continuation 帧载荷去掩码后喂给 PacketCodec，
+//      解码结果交给 connection::Dispatcher（与 TCP 共用同一个分发器）
+//    - 出站：应答数据包、发往本连接（及广播）的 uplink 推送事件各封装为一个 binary 帧
+// 3. 控制帧：ping -> pong（原样回显载荷），close -> 回 close 后断开，
+//    未加掩码的客户端帧 / text 帧按协议错误关闭
+
+use super::{
+    codec::PacketCodec,
+    connection::{encode_push, Dispatcher, MAX_FRAME_LEN},
+    uplink::{self, Connection},
+};
+use base64::Engine as _;
+use defmt::{debug, info, Format};
+use embassy_futures::select::{select3, Either3};
+use embassy_net::{tcp::TcpSocket, Stack};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::Write as _;
+use heapless::Vec;
+use sha1::{Digest, Sha1};
+
+/// WebSocket 握手 GUID
+const WS_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 握手请求最大长度
+const HANDSHAKE_MAX_LEN: usize = 1024;
+
+/// 单帧最大载荷（客户端帧）
+const MAX_FRAME_PAYLOAD: usize = MAX_FRAME_LEN;
+
+/// 帧操作码
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// 关闭状态码
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// WebSocket 服务器配置
+#[derive(Clone, Copy)]
+pub struct WebSocketServerConfig {
+    /// 监听端口
+    pub port: u16,
+    /// 接收超时
+    pub recv_timeout: Duration,
+    /// 服务器主动 ping 间隔
+    pub ping_interval: Duration,
+}
+
+impl Default for WebSocketServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8081,
+            recv_timeout: Duration::from_secs(60),
+            ping_interval: Duration::from_secs(20),
+        }
+    }
+}
+
+/// WebSocket 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum WsError {
+    /// 握手失败
+    Handshake,
+    /// 连接断开
+    Disconnected,
+    /// 协议错误（以给定状态码关闭）
+    Protocol(u16),
+    /// 对端发起关闭
+    Closed,
+}
+
+/// WebSocket 服务器（只处理单个连接）
+pub struct WebSocketServer {
+    config: WebSocketServerConfig,
+}
+
+impl WebSocketServer {
+    /// 创建新的 WebSocket 服务器
+    pub const fn new(config: WebSocketServerConfig) -> Self {
+        Self { config }
+    }
+
+    /// 启动 WebSocket 服务器
+    pub async fn start<'d, D: Dispatcher>(&self, stack: &'static Stack<'d>, dispatcher: &D) -> ! {
+        info!("Starting WebSocket server on port {}", self.config.port);
+
+        let mut rx_buf = [0u8; 2048];
+        let mut tx_buf = [0u8; 4096];
+
+        loop {
+            while !stack.is_link_up() {
+                crate::log_warn!("Network link down, waiting...");
+                Timer::after(Duration::from_secs(1)).await;
+            }
+
+            let mut socket = TcpSocket::new(*stack, &mut rx_buf, &mut tx_buf);
+            socket.set_timeout(Some(self.config.recv_timeout));
+
+            if let Err(e) = socket.accept(self.config.port).await {
+                crate::log_error!("WebSocket accept error: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            info!("WebSocket client connected: {:?}", socket.remote_endpoint());
+
+            match handshake(&mut socket).await {
+                Ok(()) => match self.serve(&mut socket, dispatcher).await {
+                    Err(WsError::Protocol(code)) => {
+                        crate::log_warn!("WebSocket protocol error, closing with {}", code);
+                        let _ = send_close(&mut socket, code).await;
+                    }
+                    Err(e) => info!("WebSocket session ended: {:?}", e),
+                    Ok(()) => {}
+                },
+                Err(e) => crate::log_warn!("WebSocket handshake failed: {:?}", e),
+            }
+
+            let _ = socket.flush().await;
+            socket.close();
+            Timer::after(Duration::from_millis(100)).await;
+            socket.abort();
+        }
+    }
+
+    /// 处理已升级的连接
+    async fn serve<D: Dispatcher>(&self, socket: &mut TcpSocket<'_>, dispatcher: &D) -> Result<(), WsError> {
+        let conn = Connection::open();
+        let mut frames = FrameReader::new();
+        let mut codec = PacketCodec::new();
+        let mut read_buf = [0u8; 512];
+        let mut decode_buffer = [0u8; 1024];
+        let mut tx_buffer = [0u8; MAX_FRAME_LEN];
+        let mut push_seq = 0u8;
+        let mut uplink_rx = uplink::subscribe();
+        let mut next_ping = Instant::now() + self.config.ping_interval;
+
+        loop {
+            let uplink_next = async {
+                match uplink_rx.as_mut() {
+                    Some(sub) => sub.next_message().await,
+                    None => core::future::pending().await,
+                }
+            };
+
+            match select3(socket.read(frames.read_window(&mut read_buf)), uplink_next, Timer::at(next_ping)).await {
+                Either3::First(result) => {
+                    let n = match result {
+                        Ok(0) | Err(_) => return Err(WsError::Disconnected),
+                        Ok(n) => n,
+                    };
+                    frames.feed(&read_buf[..n]);
+
+                    while let Some(frame) = frames.next_frame()? {
+                        match frame.opcode {
+                            OP_BINARY | OP_CONTINUATION => {
+                                if codec.feed(frame.payload).is_err() {
+                                    crate::log_warn!("WebSocket codec overflow");
+                                }
+                                while let Ok(Some(packet)) = codec.decode(&mut decode_buffer) {
+                                    debug!(
+                                        "WebSocket packet: type={:?}, seq={}",
+                                        packet.packet_type, packet.seq
+                                    );
+                                    if let Ok(Some(len)) =
+                                        dispatcher.dispatch(conn.id(), &packet, &mut tx_buffer).await
+                                    {
+                                        send_frame(socket, OP_BINARY, &tx_buffer[..len]).await?;
+                                    }
+                                }
+                            }
+                            OP_PING => {
+                                // 控制帧载荷不超过 125 字节，先复制出来再回写
+                                let mut echo: Vec<u8, 125> = Vec::new();
+                                let _ = echo.extend_from_slice(frame.payload);
+                                frames.consume();
+                                send_frame(socket, OP_PONG, &echo).await?;
+                                continue;
+                            }
+                            OP_PONG => {
+                                debug!("WebSocket pong");
+                            }
+                            OP_CLOSE => {
+                                let code = if frame.payload.len() >= 2 {
+                                    u16::from_be_bytes([frame.payload[0], frame.payload[1]])
+                                } else {
+                                    CLOSE_NORMAL
+                                };
+                                info!("WebSocket close from peer ({})", code);
+                                frames.consume();
+                                let _ = send_close(socket, CLOSE_NORMAL).await;
+                                return Err(WsError::Closed);
+                            }
+                            OP_TEXT => return Err(WsError::Protocol(CLOSE_UNSUPPORTED_DATA)),
+                            _ => return Err(WsError::Protocol(CLOSE_PROTOCOL_ERROR)),
+                        }
+                        frames.consume();
+                    }
+                }
+
+                Either3::Second(WaitResult::Message(msg)) if !msg.is_for(Some(conn.id())) => {}
+
+                Either3::Second(WaitResult::Message(msg)) => {
+                    if let Ok(len) = encode_push(&msg, push_seq, &mut tx_buffer) {
+                        push_seq = push_seq.wrapping_add(1);
+                        send_frame(socket, OP_BINARY, &tx_buffer[..len]).await?;
+                    }
+                }
+
+                Either3::Second(WaitResult::Lagged(n)) => {
+                    crate::log_warn!("WebSocket uplink lagged, {} events dropped", n);
+                }
+
+                Either3::Third(()) => {
+                    send_frame(socket, OP_PING, &[]).await?;
+                    next_ping = Instant::now() + self.config.ping_interval;
+                }
+            }
+        }
+    }
+}
+
+/// 完成 HTTP Upgrade 握手
+async fn handshake(socket: &mut TcpSocket<'_>) -> Result<(), WsError> {
+    let mut buf = [0u8; HANDSHAKE_MAX_LEN];
+    let mut len = 0;
+
+    // 读取完整请求头
+    let header_end = loop {
+        if len == buf.len() {
+            return Err(WsError::Handshake);
+        }
+        match socket.read(&mut buf[len..]).await {
+            Ok(0) | Err(_) => return Err(WsError::Disconnected),
+            Ok(n) => len += n,
+        }
+        if let Some(pos) = buf[..len].windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+    };
+
+    let head = core::str::from_utf8(&buf[..header_end]).map_err(|_| WsError::Handshake)?;
+    let mut lines = head.split("\r\n");
+    if !lines.next().is_some_and(|line| line.starts_with("GET ")) {
+        return Err(WsError::Handshake);
+    }
+
+    let mut key = None;
+    let mut upgrade = false;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("upgrade") && value.eq_ignore_ascii_case("websocket") {
+            upgrade = true;
+        } else if name.eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value);
+        }
+    }
+
+    let Some(key) = key.filter(|_| upgrade) else {
+        let _ = socket
+            .write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n")
+            .await;
+        return Err(WsError::Handshake);
+    };
+
+    // Sec-WebSocket-Accept
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WS_GUID);
+    let digest = hasher.finalize();
+    let mut accept = [0u8; 28];
+    let accept_len = base64::engine::general_purpose::STANDARD
+        .encode_slice(digest, &mut accept)
+        .map_err(|_| WsError::Handshake)?;
+
+    let mut response: Vec<u8, 160> = Vec::new();
+    let _ = response.extend_from_slice(
+        b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: ",
+    );
+    let _ = response.extend_from_slice(&accept[..accept_len]);
+    let _ = response.extend_from_slice(b"\r\n\r\n");
+
+    socket
+        .write_all(&response)
+        .await
+        .map_err(|_| WsError::Disconnected)?;
+
+    info!("WebSocket handshake complete");
+    Ok(())
+}
+
+/// 发送服务器帧（服务器帧不加掩码）
+async fn send_frame(socket: &mut TcpSocket<'_>, opcode: u8, payload: &[u8]) -> Result<(), WsError> {
+    let mut header: Vec<u8, 4> = Vec::new();
+    let _ = header.push(0x80 | opcode);
+    if payload.len() < 126 {
+        let _ = header.push(payload.len() as u8);
+    } else {
+        let _ = header.push(126);
+        let _ = header.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    }
+
+    socket
+        .write_all(&header)
+        .await
+        .map_err(|_| WsError::Disconnected)?;
+    socket
+        .write_all(payload)
+        .await
+        .map_err(|_| WsError::Disconnected)
+}
+
+/// 发送 close 帧
+async fn send_close(socket: &mut TcpSocket<'_>, code: u16) -> Result<(), WsError> {
+    send_frame(socket, OP_CLOSE, &code.to_be_bytes()).await
+}
+
+/// 已解析的客户端帧
+struct Frame<'a> {
+    opcode: u8,
+    payload: &'a [u8],
+}
+
+/// 从 TCP 字节流中切分并去掩码客户端帧
+struct FrameReader {
+    buffer: Vec<u8, { MAX_FRAME_PAYLOAD + 14 }>,
+    /// 当前帧总长度（next_frame 成功后有效）
+    current_len: usize,
+}
+
+impl FrameReader {
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            current_len: 0,
+        }
+    }
+
+    /// 本次读取可用的缓冲区（不超过剩余空间）
+    ///
+    /// 缓冲区能容纳一个最大帧，未完成的帧总有剩余空间，超长只能由帧头的载荷长度判定
+    fn read_window<'b>(&self, read_buf: &'b mut [u8]) -> &'b mut [u8] {
+        let len = (self.buffer.capacity() - self.buffer.len()).min(read_buf.len());
+        &mut read_buf[..len]
+    }
+
+    /// 追加读到的数据（不超过 read_window 的长度）
+    fn feed(&mut self, data: &[u8]) {
+        let _ = self.buffer.extend_from_slice(data);
+    }
+
+    /// 取出下一个完整帧（载荷已去掩码）
+    fn next_frame(&mut self) -> Result<Option<Frame<'_>>, WsError> {
+        if self.buffer.len() < 2 {
+            return Ok(None);
+        }
+
+        let b0 = self.buffer[0];
+        let b1 = self.buffer[1];
+
+        // RSV 位必须为 0（未协商扩展）
+        if b0 & 0x70 != 0 {
+            return Err(WsError::Protocol(CLOSE_PROTOCOL_ERROR));
+        }
+        // 客户端帧必须加掩码
+        if b1 & 0x80 == 0 {
+            return Err(WsError::Protocol(CLOSE_PROTOCOL_ERROR));
+        }
+
+        let opcode = b0 & 0x0F;
+        let (payload_len, mut pos) = match b1 & 0x7F {
+            126 => {
+                if self.buffer.len() < 4 {
+                    return Ok(None);
+                }
+                (u16::from_be_bytes([self.buffer[2], self.buffer[3]]) as usize, 4)
+            }
+            127 => return Err(WsError::Protocol(CLOSE_TOO_BIG)),
+            n => (n as usize, 2),
+        };
+
+        // 控制帧：载荷 <= 125 且不可分片
+        if opcode & 0x08 != 0 && (payload_len > 125 || b0 & 0x80 == 0) {
+            return Err(WsError::Protocol(CLOSE_PROTOCOL_ERROR));
+        }
+        if payload_len > MAX_FRAME_PAYLOAD {
+            return Err(WsError::Protocol(CLOSE_TOO_BIG));
+        }
+
+        if self.buffer.len() < pos + 4 + payload_len {
+            return Ok(None);
+        }
+
+        let mask = [
+            self.buffer[pos],
+            self.buffer[pos + 1],
+            self.buffer[pos + 2],
+            self.buffer[pos + 3],
+        ];
+        pos += 4;
+
+        let payload = &mut self.buffer[pos..pos + payload_len];
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        self.current_len = pos + payload_len;
+        Ok(Some(Frame {
+            opcode,
+            payload: &self.buffer[pos..pos + payload_len],
+        }))
+    }
+
+    /// 丢弃 next_frame 返回的帧
+    fn consume(&mut self) {
+        let n = self.current_len.min(self.buffer.len());
+        self.buffer.as_mut_slice().copy_within(n.., 0);
+        self.buffer.truncate(self.buffer.len() - n);
+        self.current_len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 编码一个加掩码的客户端帧
+    fn client_frame(opcode: u8, payload: &[u8]) -> std::vec::Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut out = std::vec![0x80 | opcode];
+        if payload.len() < 126 {
+            out.push(0x80 | payload.len() as u8);
+        } else {
+            out.push(0x80 | 126);
+            out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        out.extend_from_slice(&mask);
+        out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        out
+    }
+
+    /// 按 512 字节读取（受 read_window 限制）输入，返回各帧的 (opcode, 载荷)
+    fn read_all(stream: &[u8]) -> Result<std::vec::Vec<(u8, std::vec::Vec<u8>)>, WsError> {
+        let mut reader = FrameReader::new();
+        let mut read_buf = [0u8; 512];
+        let mut frames = std::vec::Vec::new();
+        let mut rest = stream;
+        while !rest.is_empty() {
+            let window = reader.read_window(&mut read_buf);
+            let n = window.len().min(rest.len());
+            assert!(n > 0, "reader stalled with a full buffer");
+            window[..n].copy_from_slice(&rest[..n]);
+            rest = &rest[n..];
+            reader.feed(&read_buf[..n]);
+            while let Some(frame) = reader.next_frame()? {
+                frames.push((frame.opcode, frame.payload.to_vec()));
+                reader.consume();
+            }
+        }
+        Ok(frames)
+    }
+
+    #[test]
+    fn frames_within_the_limit_never_overflow_the_reader() {
+        let small = [0x11u8; 300];
+        let large = [0x22u8; 1000];
+        let max = [0x33u8; MAX_FRAME_PAYLOAD];
+        let mut stream = client_frame(OP_BINARY, &small);
+        stream.extend_from_slice(&client_frame(OP_BINARY, &large));
+        stream.extend_from_slice(&client_frame(OP_BINARY, &max));
+        stream.extend_from_slice(&client_frame(OP_PING, b"hi"));
+
+        let frames = read_all(&stream).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], (OP_BINARY, small.to_vec()));
+        assert_eq!(frames[1], (OP_BINARY, large.to_vec()));
+        assert_eq!(frames[2], (OP_BINARY, max.to_vec()));
+        assert_eq!(frames[3], (OP_PING, b"hi".to_vec()));
+    }
+
+    #[test]
+    fn only_a_declared_oversized_payload_closes_with_1009() {
+        let stream = client_frame(OP_BINARY, &[0u8; MAX_FRAME_PAYLOAD + 1]);
+        assert!(matches!(read_all(&stream), Err(WsError::Protocol(CLOSE_TOO_BIG))));
+    }
+
+    #[test]
+    fn unmasked_client_frames_are_a_protocol_error() {
+        let stream = [0x80 | OP_BINARY, 0x01, 0xAA];
+        assert!(matches!(read_all(&stream), Err(WsError::Protocol(CLOSE_PROTOCOL_ERROR))));
+    }
+}
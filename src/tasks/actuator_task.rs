@@ -0,0 +1,48 @@
+// 执行器任务
+use crate::app::actuators;
+use crate::drivers::actuator::{LocalActuators, Mirrored};
+use crate::drivers::can::{CanBusRx, CanBusTx};
+use crate::net::{CanMaster, RemoteBoard};
+use defmt::info;
+use embassy_futures::select::{select, Either};
+
+/// CAN 主控（收发端为 CAN1）
+pub type CanBusMaster = CanMaster<CanBusTx, CanBusRx>;
+
+/// 本机执行器任务
+///
+/// 依次执行 2002 / 2003 及安全策略下发的命令，写入本机状态
+#[embassy_executor::task]
+pub async fn actuator_task() -> ! {
+    info!("Actuator task started (local)");
+    let (mut motors, mut lights) = (LocalActuators, LocalActuators);
+    actuators::run(&mut motors, &mut lights).await
+}
+
+/// 远端执行器任务
+///
+/// 命令经 CAN 下发到 `node` 驱动板，驱动板接受后同步写入本机状态
+#[embassy_executor::task]
+pub async fn remote_actuator_task(master: &'static CanBusMaster, node: u8) -> ! {
+    info!("Actuator task started (CAN node {})", node);
+    let mut motors = Mirrored::new(RemoteBoard::new(master, node));
+    let mut lights = Mirrored::new(RemoteBoard::new(master, node));
+    actuators::run(&mut motors, &mut lights).await
+}
+
+/// CAN 主控接收任务
+///
+/// 接收应答与节点事件；节点事件目前只记录日志
+#[embassy_executor::task]
+pub async fn can_master_task(master: &'static CanBusMaster) -> ! {
+    match select(master.run(), log_events(master)).await {
+        Either::First(never) | Either::Second(never) => never,
+    }
+}
+
+async fn log_events(master: &CanBusMaster) -> ! {
+    loop {
+        let event = master.next_event().await;
+        info!("CAN: event {:04X} from node {} ({} bytes)", event.cmd, event.node, event.body.len());
+    }
+}
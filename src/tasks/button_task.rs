@@ -1,16 +1,18 @@
 // 按钮事件任务
-use crate::event::Event;
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
 use defmt::info;
-use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Sender;
 use embassy_time::{Duration, Timer};
 
+/// 模拟按住时长
+const HOLD_MS: u32 = 100;
+
 /// 按钮任务
 ///
-/// 监听按钮事件并发送到事件队列
+/// 监听按钮事件（按下 / 抬起）并发送到事件队列
 #[embassy_executor::task]
 pub async fn button_task(
-    event_tx: Sender<'static, CriticalSectionRawMutex, Event, 32>,
+    event_tx: EventPublisher,
 ) -> ! {
     info!("Button task started");
 
@@ -24,12 +26,20 @@ pub async fn button_task(
 
         let event = Event::ButtonPress {
             button_id,
-            duration_ms: Some(100),
+            duration_ms: None,
         };
 
         info!("Button {} pressed, sending event", button_id);
 
         // 发送事件到队列
-        event_tx.send(event).await;
+        event_tx.publish(EventEnvelope::new(Source::Button, event)).await;
+
+        // 按住 100ms 后抬起
+        Timer::after(Duration::from_millis(HOLD_MS as u64)).await;
+        let event = Event::ButtonRelease {
+            button_id,
+            held_ms: HOLD_MS,
+        };
+        event_tx.publish(EventEnvelope::new(Source::Button, event)).await;
     }
 }
@@ -1,28 +1,35 @@
 // 事件分发任务
 use crate::app::router::route_event;
-use crate::event::Event;
+use crate::event_bus::{EventSubscriber, Lagged};
 use defmt::info;
-use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Receiver;
 
 /// 事件分发任务
 ///
-/// 从事件队列接收事件并路由到对应的处理器
+/// 作为事件总线的订阅者之一，接收事件并路由到对应的处理器
 #[embassy_executor::task]
-pub async fn dispatch_task(
-    event_rx: Receiver<'static, CriticalSectionRawMutex, Event, 32>,
-) -> ! {
+pub async fn dispatch_task(mut events: EventSubscriber) -> ! {
     info!("Dispatch task started");
 
     loop {
-        // 从队列接收事件
-        let event = event_rx.receive().await;
+        // 从总线接收事件
+        let envelope = match events.next().await {
+            Ok(envelope) => envelope,
+            Err(Lagged(n)) => {
+                crate::log_warn!("Dispatch lagged: {} events skipped (total {})", n, events.lagged());
+                continue;
+            }
+        };
 
-        info!("Dispatching event");
+        info!(
+            "Dispatching event #{} from {:?} at {}ms",
+            envelope.id,
+            envelope.source,
+            envelope.timestamp.as_millis()
+        );
 
         // 路由到对应的处理器
-        if let Err(_e) = route_event(event) {
-            defmt::warn!("Event routing failed");
+        if let Err(e) = route_event(envelope) {
+            crate::log_warn!("Event routing failed: {:?}", e);
         }
     }
 }
@@ -0,0 +1,22 @@
+// 灯光效果任务
+use crate::app::effects;
+use crate::event_bus::{EventSubscriber, Lagged};
+use defmt::info;
+
+/// 灯光效果任务
+///
+/// 作为事件总线观察者，按钮事件驱动按钮灯；丢事件后按当前状态重新同步
+#[embassy_executor::task]
+pub async fn effects_task(mut events: EventSubscriber) -> ! {
+    info!("Light effects task started");
+
+    loop {
+        match events.next().await {
+            Ok(envelope) => effects::on_event(&envelope.event),
+            Err(Lagged(n)) => {
+                crate::log_warn!("Light effects lagged: {} events skipped, resyncing", n);
+                effects::resync();
+            }
+        }
+    }
+}
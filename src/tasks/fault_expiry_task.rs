@@ -0,0 +1,12 @@
+// 模拟故障到期清除任务
+use crate::app::fault_registry;
+use defmt::info;
+
+/// 模拟故障到期清除任务
+///
+/// 2005 注入时带持续时间的模拟故障，到期后自动清除（上报 1006 cleared=1）
+#[embassy_executor::task]
+pub async fn fault_expiry_task() -> ! {
+    info!("Fault expiry task started");
+    fault_registry::run_expiry().await
+}
@@ -0,0 +1,13 @@
+// 模拟故障注入任务
+use crate::app::fault_injection;
+use crate::event_bus::EventPublisher;
+use defmt::info;
+
+/// 模拟故障注入任务
+///
+/// 把 2005 注入的模拟故障作为 FaultDetected 事件发布到事件总线
+#[embassy_executor::task]
+pub async fn fault_injection_task(event_tx: EventPublisher) -> ! {
+    info!("Fault injection task started");
+    fault_injection::run(event_tx).await
+}
@@ -1,8 +1,7 @@
 // 心跳任务
-use crate::event::Event;
+use crate::event::{Event, EventEnvelope, Source};
+use crate::event_bus::EventPublisher;
 use defmt::info;
-use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Sender;
 use embassy_time::{Duration, Timer};
 
 /// 心跳任务
@@ -10,7 +9,7 @@ use embassy_time::{Duration, Timer};
 /// 定期发送心跳事件
 #[embassy_executor::task]
 pub async fn heartbeat_task(
-    event_tx: Sender<'static, CriticalSectionRawMutex, Event, 32>,
+    event_tx: EventPublisher,
 ) -> ! {
     info!("Heartbeat task started");
 
@@ -23,6 +22,6 @@ pub async fn heartbeat_task(
         let event = Event::HeartbeatTick;
 
         // 发送心跳事件
-        event_tx.send(event).await;
+        event_tx.publish(EventEnvelope::new(Source::Heartbeat, event)).await;
     }
 }
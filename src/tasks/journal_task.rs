@@ -0,0 +1,21 @@
+// 事件日志任务
+use crate::app::journal;
+use crate::event_bus::{EventSubscriber, Lagged};
+use defmt::info;
+
+/// 事件日志任务
+///
+/// 作为事件总线订阅者，把事件写入环形日志（心跳在订阅时过滤）
+#[embassy_executor::task]
+pub async fn journal_task(mut events: EventSubscriber) -> ! {
+    info!("Journal task started");
+
+    loop {
+        match events.next().await {
+            Ok(envelope) => journal::on_event(&envelope),
+            Err(Lagged(n)) => {
+                crate::log_warn!("Journal lagged: {} events not recorded", n);
+            }
+        }
+    }
+}
@@ -2,3 +2,12 @@ pub mod button_task;
 pub mod network_task;
 pub mod heartbeat_task;
 pub mod dispatch_task;
+pub mod state_task;
+pub mod uplink_task;
+pub mod effects_task;
+pub mod journal_task;
+pub mod status_task;
+pub mod fault_expiry_task;
+pub mod fault_injection_task;
+pub mod motor_timer_task;
+pub mod actuator_task;
@@ -0,0 +1,12 @@
+// 马达定时运行到期任务
+use crate::app::state;
+use defmt::info;
+
+/// 马达定时运行到期任务
+///
+/// RUN_TIME 到期后把马达状态写回停止（状态版本递增，订阅方收到 1002）
+#[embassy_executor::task]
+pub async fn motor_timer_task() -> ! {
+    info!("Motor timer task started");
+    state::run_motor_timers().await
+}
@@ -1 +1,63 @@
-// network_task
+// 网络任务（以太网协议栈及其上的网络服务）
+use crate::drivers::eth::EthDevice;
+use crate::event::Source;
+use crate::event_bus::EventPublisher;
+use crate::net::connection::EventInjector;
+use crate::utils::log::{self, SyslogConfig};
+use crate::net::{
+    DiscoveryConfig, DiscoveryService, HttpServer, HttpServerConfig, ModbusTcpConfig, ModbusTcpServer,
+    MqttClient, MqttConfig, TcpServer, TcpServerConfig, WebSocketServer, WebSocketServerConfig,
+};
+use embassy_net::{Runner, Stack};
+
+/// 协议栈任务
+///
+/// 驱动 embassy-net（收发、DHCP、定时器），其余网络任务都依赖它
+#[embassy_executor::task]
+pub async fn net_stack_task(mut runner: Runner<'static, EthDevice>) -> ! {
+    runner.run().await
+}
+
+/// TCP 服务器任务（0xAA55 数据包，与串口相同的命令注入与上行推送）
+#[embassy_executor::task]
+pub async fn tcp_server_task(stack: &'static Stack<'static>, event_tx: EventPublisher) -> ! {
+    let injector = EventInjector::new(Source::Network, &event_tx);
+    TcpServer::new(TcpServerConfig::default()).start(stack, &injector).await
+}
+
+/// UDP 发现任务（应答查询，按配置广播信标）
+#[embassy_executor::task]
+pub async fn discovery_task(stack: &'static Stack<'static>, config: DiscoveryConfig) -> ! {
+    DiscoveryService::new(config).start(stack).await
+}
+
+/// HTTP 状态与控制接口任务
+#[embassy_executor::task]
+pub async fn http_task(stack: &'static Stack<'static>, config: HttpServerConfig) -> ! {
+    HttpServer::new(config).start(stack).await
+}
+
+/// MQTT 客户端任务（断线自动重连）
+#[embassy_executor::task]
+pub async fn mqtt_task(stack: &'static Stack<'static>, config: MqttConfig, event_tx: EventPublisher) -> ! {
+    MqttClient::new(config).start(stack, event_tx).await
+}
+
+/// WebSocket 传输任务（浏览器运维控制台）
+#[embassy_executor::task]
+pub async fn websocket_task(stack: &'static Stack<'static>, event_tx: EventPublisher) -> ! {
+    let injector = EventInjector::new(Source::Network, &event_tx);
+    WebSocketServer::new(WebSocketServerConfig::default()).start(stack, &injector).await
+}
+
+/// Modbus TCP 从站任务
+#[embassy_executor::task]
+pub async fn modbus_tcp_task(stack: &'static Stack<'static>) -> ! {
+    ModbusTcpServer::new(ModbusTcpConfig::default()).start(stack).await
+}
+
+/// syslog 转发任务（RFC 5424 over UDP）
+#[embassy_executor::task]
+pub async fn syslog_task(stack: &'static Stack<'static>, config: SyslogConfig) -> ! {
+    log::run_syslog(stack, config).await
+}
@@ -0,0 +1,21 @@
+// 状态记录任务
+use crate::app::state;
+use crate::event_bus::{EventSubscriber, Lagged};
+use defmt::info;
+
+/// 状态记录任务
+///
+/// 作为事件总线订阅者（不丢事件），把按钮、门开关 / 倾斜与投币计数写入机器状态
+#[embassy_executor::task]
+pub async fn state_task(mut events: EventSubscriber) -> ! {
+    info!("State task started");
+
+    loop {
+        match events.next().await {
+            Ok(envelope) => state::on_event(&envelope.event),
+            Err(Lagged(n)) => {
+                crate::log_warn!("State lagged: {} events not applied (total {})", n, events.lagged());
+            }
+        }
+    }
+}
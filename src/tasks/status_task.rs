@@ -0,0 +1,12 @@
+// 状态订阅推送任务
+use crate::app::subscriptions;
+use defmt::info;
+
+/// 状态订阅推送任务
+///
+/// 状态变化后按各订阅的最小间隔推送增量 1002
+#[embassy_executor::task]
+pub async fn status_task() -> ! {
+    info!("Status subscription task started");
+    subscriptions::run().await
+}
@@ -0,0 +1,26 @@
+// 事件上行任务
+use crate::app::router::route_uplink;
+use crate::event_bus::{EventSubscriber, Lagged};
+use defmt::info;
+
+/// 事件上行任务
+///
+/// 作为事件总线订阅者（不丢事件），把按钮、投币 / 回币 / 退币与心跳编码后上行
+#[embassy_executor::task]
+pub async fn uplink_task(mut events: EventSubscriber) -> ! {
+    info!("Uplink task started");
+
+    loop {
+        let envelope = match events.next().await {
+            Ok(envelope) => envelope,
+            Err(Lagged(n)) => {
+                crate::log_warn!("Uplink lagged: {} events not reported (total {})", n, events.lagged());
+                continue;
+            }
+        };
+
+        if let Err(e) = route_uplink(envelope).await {
+            crate::log_warn!("Event uplink failed: {:?}", e);
+        }
+    }
+}
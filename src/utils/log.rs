@@ -1 +1,364 @@
-// log
+// 日志门面：defmt(RTT) + 可选 syslog(RFC 5424) over UDP
+//
+// 用法：
+//     log_info!("Coin inserted: channel={}", channel_id);
+//
+// - 每条日志总是输出到 defmt（经 Display2Format，受 DEFMT_LOG 过滤：
+//   被过滤掉的级别不会在运行时格式化）
+// - syslog 启动后，级别不低于 `SyslogConfig::level` 的日志格式化后放入队列，
+//   由 syslog_task 发送到采集端；超过 LOG_LINE_MAX 的按字节截断并以 "..." 结尾
+// - 令牌桶限速 + 非阻塞入队：限速或队列满时丢弃并计数，日志永远不会阻塞
+//   调用方或挤占网络；丢弃计数会周期性以 syslog 消息形式上报
+//
+// 参数使用 core::fmt 格式化（Display / Debug），不是 defmt::Format。
+
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use defmt::Format;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{IpEndpoint, Stack};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Instant, Timer};
+use heapless::String;
+
+/// 单条日志最大长度
+pub const LOG_LINE_MAX: usize = 160;
+
+/// syslog 队列深度
+const LOG_QUEUE_DEPTH: usize = 16;
+
+/// 日志级别（数值即 syslog severity）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Format)]
+#[repr(u8)]
+pub enum Level {
+    Error = 3,
+    Warn = 4,
+    Info = 6,
+    Debug = 7,
+}
+
+/// syslog 配置
+#[derive(Clone, Copy)]
+pub struct SyslogConfig {
+    /// 采集端地址（通常为 UDP 514）
+    pub collector: IpEndpoint,
+    /// 发送到 syslog 的最低级别（独立于 DEFMT_LOG）
+    pub level: Level,
+    /// syslog facility（16 = local0）
+    pub facility: u8,
+    /// APP-NAME 字段
+    pub app_name: &'static str,
+    /// 每秒允许发送的日志条数
+    pub rate_per_sec: u32,
+    /// 令牌桶容量（允许的突发条数）
+    pub burst: u32,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            collector: IpEndpoint::new(embassy_net::IpAddress::v4(192, 168, 1, 2), 514),
+            level: Level::Info,
+            facility: 16,
+            app_name: "coinpusher",
+            rate_per_sec: 10,
+            burst: 20,
+        }
+    }
+}
+
+/// 排队等待发送的日志
+struct LogLine {
+    level: Level,
+    uptime_ms: u64,
+    text: String<LOG_LINE_MAX>,
+}
+
+/// 令牌桶
+struct RateLimiter {
+    tokens: u32,
+    burst: u32,
+    rate_per_sec: u32,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    const fn new() -> Self {
+        Self {
+            tokens: 0,
+            burst: 0,
+            rate_per_sec: 0,
+            last_refill: Instant::from_ticks(0),
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed_us = (now - self.last_refill).as_micros();
+        let refill = elapsed_us * self.rate_per_sec as u64 / 1_000_000;
+        if refill > 0 {
+            let tokens = (self.tokens as u64 + refill).min(self.burst as u64) as u32;
+            if tokens == self.burst {
+                // 桶已满，多余的时间不再累计
+                self.last_refill = now;
+            } else {
+                // 只扣除换成令牌的时间，不足一个令牌的余量留到下次
+                self.last_refill += Duration::from_micros(refill * 1_000_000 / self.rate_per_sec as u64);
+            }
+            self.tokens = tokens;
+        }
+
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+/// syslog 最低级别（0 = 未启用）
+static SYSLOG_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// 因限速或队列满而丢弃的日志数
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+static LIMITER: Mutex<CriticalSectionRawMutex, RefCell<RateLimiter>> =
+    Mutex::new(RefCell::new(RateLimiter::new()));
+
+static LOG_QUEUE: Channel<CriticalSectionRawMutex, LogLine, LOG_QUEUE_DEPTH> = Channel::new();
+
+/// 因限速或队列满而丢弃的日志总数
+pub fn dropped_count() -> u32 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// 写满即截断的格式化缓冲（按 UTF-8 字符边界截断，不丢弃整段参数）
+struct Truncating<'a> {
+    text: &'a mut String<LOG_LINE_MAX>,
+    truncated: bool,
+}
+
+impl fmt::Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        let room = LOG_LINE_MAX - self.text.len();
+        if s.len() <= room {
+            let _ = self.text.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let _ = self.text.push_str(&s[..end]);
+        self.truncated = true;
+        Ok(())
+    }
+}
+
+/// 格式化一条日志，超长时截断并以 "..." 结尾
+fn format_line(args: fmt::Arguments<'_>) -> String<LOG_LINE_MAX> {
+    let mut text: String<LOG_LINE_MAX> = String::new();
+    let mut writer = Truncating {
+        text: &mut text,
+        truncated: false,
+    };
+    let _ = writer.write_fmt(args);
+    if writer.truncated {
+        while text.len() > LOG_LINE_MAX - 3 {
+            text.pop();
+        }
+        let _ = text.push_str("...");
+    }
+    text
+}
+
+/// 记录一条日志（通常通过 log_* 宏调用）
+pub fn log(level: Level, args: fmt::Arguments<'_>) {
+    match level {
+        Level::Error => defmt::error!("{}", defmt::Display2Format(&args)),
+        Level::Warn => defmt::warn!("{}", defmt::Display2Format(&args)),
+        Level::Info => defmt::info!("{}", defmt::Display2Format(&args)),
+        Level::Debug => defmt::debug!("{}", defmt::Display2Format(&args)),
+    }
+
+    let min_level = SYSLOG_LEVEL.load(Ordering::Relaxed);
+    if min_level == 0 || level as u8 > min_level {
+        return;
+    }
+
+    let now = Instant::now();
+    if !LIMITER.lock(|l| l.borrow_mut().try_acquire(now)) {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+
+    let text = format_line(args);
+
+    let line = LogLine {
+        level,
+        uptime_ms: now.as_millis(),
+        text,
+    };
+    if LOG_QUEUE.try_send(line).is_err() {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// syslog 发送任务主体
+///
+/// 启用 syslog 转发并持续发送队列中的日志，应在独立任务中运行
+pub async fn run_syslog<'d>(stack: &'static Stack<'d>, config: SyslogConfig) -> ! {
+    LIMITER.lock(|l| {
+        let mut l = l.borrow_mut();
+        l.rate_per_sec = config.rate_per_sec;
+        l.burst = config.burst;
+        l.tokens = config.burst;
+        l.last_refill = Instant::now();
+    });
+    SYSLOG_LEVEL.store(config.level as u8, Ordering::Relaxed);
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0u8; 16];
+    let mut tx_buffer = [0u8; 1024];
+
+    while !stack.is_config_up() {
+        Timer::after(Duration::from_secs(1)).await;
+    }
+
+    let mut socket = UdpSocket::new(
+        *stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    if let Err(e) = socket.bind(0) {
+        defmt::error!("Syslog bind error: {:?}", e);
+        loop {
+            Timer::after(Duration::from_secs(60)).await;
+        }
+    }
+
+    defmt::info!("Syslog forwarding to {:?}", config.collector);
+
+    let mut reported_dropped = 0u32;
+    let mut datagram: String<{ LOG_LINE_MAX + 96 }> = String::new();
+
+    loop {
+        let line = LOG_QUEUE.receive().await;
+
+        // 上报新增的丢弃数（不经过限速，避免丢弃信息本身被丢弃）
+        let dropped = dropped_count();
+        if dropped != reported_dropped {
+            let mut text: String<LOG_LINE_MAX> = String::new();
+            let _ = write!(text, "{} log lines dropped", dropped.wrapping_sub(reported_dropped));
+            reported_dropped = dropped;
+            let notice = LogLine {
+                level: Level::Warn,
+                uptime_ms: line.uptime_ms,
+                text,
+            };
+            format_rfc5424(&mut datagram, &config, &notice);
+            let _ = socket.send_to(datagram.as_bytes(), config.collector).await;
+        }
+
+        format_rfc5424(&mut datagram, &config, &line);
+        if let Err(e) = socket.send_to(datagram.as_bytes(), config.collector).await {
+            defmt::warn!("Syslog send failed: {:?}", e);
+        }
+    }
+}
+
+/// 格式化 RFC 5424 报文
+///
+/// `<PRI>1 - HOSTNAME APP-NAME - - - [uptime_ms] MSG`
+/// 无实时时钟，TIMESTAMP 使用 NILVALUE；HOSTNAME 为机器 ID
+fn format_rfc5424<const N: usize>(out: &mut String<N>, config: &SyslogConfig, line: &LogLine) {
+    out.clear();
+    let pri = config.facility as u32 * 8 + line.level as u32;
+    let _ = write!(
+        out,
+        "<{}>1 - {} {} - - - [{}ms] {}",
+        pri,
+        crate::app::device::machine_id(),
+        config.app_name,
+        line.uptime_ms,
+        line.text,
+    );
+}
+
+/// 记录 ERROR 日志
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::utils::log::log($crate::utils::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+/// 记录 WARN 日志
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::utils::log::log($crate::utils::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+/// 记录 INFO 日志
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::utils::log::log($crate::utils::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+/// 记录 DEBUG 日志
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::utils::log::log($crate::utils::log::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(rate_per_sec: u32, burst: u32) -> RateLimiter {
+        RateLimiter {
+            tokens: 0,
+            burst,
+            rate_per_sec,
+            last_refill: Instant::from_ticks(0),
+        }
+    }
+
+    #[test]
+    fn partial_intervals_carry_over_to_the_next_refill() {
+        let mut limiter = limiter(10, 20);
+        // 每 150ms 取一次：100ms 一个令牌，余下的 50ms 累计，不会被丢弃
+        let granted = (1..=4)
+            .filter(|&i| limiter.try_acquire(Instant::from_millis(150 * i)))
+            .count();
+        assert_eq!(granted, 4);
+        assert!(limiter.try_acquire(Instant::from_millis(650)));
+        assert_eq!(limiter.tokens, 1);
+    }
+
+    #[test]
+    fn full_bucket_does_not_bank_idle_time() {
+        let mut limiter = limiter(10, 2);
+        let idle = Instant::from_secs(60);
+        assert!(limiter.try_acquire(idle));
+        assert!(limiter.try_acquire(idle));
+        assert!(!limiter.try_acquire(idle));
+        // 空闲时间超出桶容量的部分不再换成令牌
+        assert!(!limiter.try_acquire(idle + Duration::from_millis(50)));
+        assert!(limiter.try_acquire(idle + Duration::from_millis(100)));
+    }
+}
@@ -0,0 +1,66 @@
+#!/usr/bin/env python3
+"""录制串口字节流为回放抓包文件（captures/*.cap，供 drivers::replay 使用）
+
+用法:
+    python3 tools/capture.py /dev/ttyUSB0 [--baud 115200] [--gap 2] [-o captures/field.cap]
+
+间隔不超过 `--gap` 毫秒的字节合并为一行；每行记录距上一行的毫秒延迟：
+
+    +<延迟ms> AA 55 20 01 ...
+
+Ctrl+C 结束录制。需要 pyserial（pip install pyserial）。
+"""
+
+import argparse
+import sys
+import time
+
+import serial
+
+
+def main():
+    parser = argparse.ArgumentParser(description="录制串口抓包")
+    parser.add_argument("port", help="串口设备，如 /dev/ttyUSB0 或 COM3")
+    parser.add_argument("--baud", type=int, default=115200)
+    parser.add_argument("--gap", type=float, default=2.0, help="合并为一行的最大字节间隔(ms)")
+    parser.add_argument("-o", "--output", help="输出文件（默认 stdout）")
+    args = parser.parse_args()
+
+    out = open(args.output, "w") if args.output else sys.stdout
+    out.write(f"# 录制自 {args.port} @ {args.baud}，{time.strftime('%Y-%m-%d %H:%M:%S')}\n")
+
+    line = []
+    line_start = None
+    last_line_start = None
+    last_byte = None
+
+    def flush():
+        nonlocal line, last_line_start
+        if not line:
+            return
+        delay = 0 if last_line_start is None else round((line_start - last_line_start) * 1000)
+        out.write(f"+{delay} " + " ".join(f"{b:02X}" for b in line) + "\n")
+        out.flush()
+        last_line_start = line_start
+        line = []
+
+    with serial.Serial(args.port, args.baud, timeout=args.gap / 1000) as port:
+        try:
+            while True:
+                data = port.read(256)
+                now = time.monotonic()
+                if not data:
+                    if last_byte is not None and (now - last_byte) * 1000 > args.gap:
+                        flush()
+                    continue
+                if not line:
+                    line_start = now
+                line.extend(data)
+                last_byte = now
+        except KeyboardInterrupt:
+            pass
+    flush()
+
+
+if __name__ == "__main__":
+    main()
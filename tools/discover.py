@@ -0,0 +1,78 @@
+#!/usr/bin/env python3
+"""列出子网内所有推币机（UDP 发现服务客户端）
+
+用法:
+    python3 tools/discover.py [--port 48899] [--timeout 2.0] [--listen]
+
+默认广播一次 `CPDISCOVER` 查询并汇总应答；`--listen` 模式只被动收听信标。
+"""
+
+import argparse
+import json
+import socket
+import time
+
+DISCOVERY_PORT = 48899
+DISCOVERY_QUERY = b"CPDISCOVER"
+
+SEVERITY = {0: "-", 1: "INFO", 2: "WARN", 3: "ERROR", 4: "FATAL"}
+
+
+def collect(sock, timeout):
+    machines = {}
+    deadline = time.monotonic() + timeout
+    while True:
+        remaining = deadline - time.monotonic()
+        if remaining <= 0:
+            break
+        sock.settimeout(remaining)
+        try:
+            data, addr = sock.recvfrom(512)
+        except socket.timeout:
+            break
+        try:
+            info = json.loads(data.decode("utf-8"))
+        except (UnicodeDecodeError, json.JSONDecodeError):
+            continue
+        if "machine_id" not in info:
+            continue
+        info.setdefault("ip", addr[0])
+        machines[info["machine_id"]] = info
+    return machines
+
+
+def main():
+    parser = argparse.ArgumentParser(description="Discover coin pusher machines")
+    parser.add_argument("--port", type=int, default=DISCOVERY_PORT)
+    parser.add_argument("--timeout", type=float, default=2.0)
+    parser.add_argument("--broadcast", default="255.255.255.255")
+    parser.add_argument("--listen", action="store_true", help="只收听信标，不发送查询")
+    args = parser.parse_args()
+
+    sock = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+    sock.setsockopt(socket.SOL_SOCKET, socket.SO_BROADCAST, 1)
+    sock.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+
+    if args.listen:
+        sock.bind(("", args.port))
+    else:
+        sock.bind(("", 0))
+        sock.sendto(DISCOVERY_QUERY, (args.broadcast, args.port))
+
+    machines = collect(sock, args.timeout)
+
+    if not machines:
+        print("No machines found")
+        return
+
+    print(f"{'MACHINE ID':<26}{'IP':<17}{'PORT':<7}{'FW':<10}{'FAULTS':<8}SEVERITY")
+    for info in sorted(machines.values(), key=lambda m: m.get("ip", "")):
+        print(
+            f"{info['machine_id']:<26}{info.get('ip', '?'):<17}"
+            f"{info.get('tcp_port', '?'):<7}{info.get('fw', '?'):<10}"
+            f"{info.get('faults', '?'):<8}{SEVERITY.get(info.get('max_severity'), '?')}"
+        )
+
+
+if __name__ == "__main__":
+    main()